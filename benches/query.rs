@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use things::{Component, Read, Things};
+
+#[derive(Component, Debug)]
+struct Velocity(u32);
+
+#[derive(Component, Debug)]
+struct Position(u32);
+
+const ENTITIES: u32 = 100_000;
+
+fn things() -> Things {
+    let mut ecs = Things::new();
+    ecs.spawn_batch((0..ENTITIES).map(|n| (Velocity(n), Position(n))));
+    ecs
+}
+
+fn query(c: &mut Criterion) {
+    let mut ecs = things();
+
+    c.bench_function("query read pair", |b| {
+        b.iter(|| {
+            let sum: u64 = ecs
+                .query::<(Read<Velocity>, Read<Position>)>()
+                .unwrap()
+                .map(|(velocity, position)| u64::from(velocity.0 + position.0))
+                .sum();
+
+            black_box(sum)
+        })
+    });
+}
+
+fn zipped_stores(c: &mut Criterion) {
+    let ecs = things();
+
+    c.bench_function("zipped stores", |b| {
+        b.iter(|| {
+            let velocities = ecs.store::<Velocity>().unwrap().as_slice();
+            let positions = ecs.store::<Position>().unwrap().as_slice();
+
+            let sum: u64 = velocities
+                .iter()
+                .zip(positions)
+                .filter_map(|(velocity, position)| {
+                    Some(u64::from(velocity.as_ref()?.0 + position.as_ref()?.0))
+                })
+                .sum();
+
+            black_box(sum)
+        })
+    });
+}
+
+criterion_group!(benches, query, zipped_stores);
+criterion_main!(benches);
@@ -0,0 +1,187 @@
+use crate::HashMap;
+use alloc::{vec, vec::Vec};
+use core::{any::TypeId, cmp, ops::Range};
+
+/// `Archetypes` groups the entities by the exact set of component types they
+/// hold, and records the group of every row.
+///
+/// The components of an entity are kept at the same position in every store,
+/// so entities of the same archetype can't be moved next to each other without
+/// moving them in all stores. Instead, queries look up the archetype of each
+/// row, and skip every run of rows whose archetype can't match the query,
+/// without visiting the stores.
+///
+/// The first archetype is the empty archetype, which holds no components. It is
+/// the archetype of every row that does not belong to an entity.
+pub(crate) struct Archetypes {
+    /// types holds the sorted component types of each archetype.
+    types: Vec<Vec<TypeId>>,
+
+    /// ids maps the sorted component types of each archetype to its index in
+    /// `types`.
+    ids: HashMap<Vec<TypeId>, usize>,
+
+    /// rows holds the archetype of each row. Rows beyond its end are in the
+    /// empty archetype. It is scanned by every query that skips rows, so the
+    /// archetypes are kept as `u32`s to keep it small.
+    rows: Vec<u32>,
+
+    /// stale is set if components may have been added or removed without
+    /// updating the archetypes of their rows.
+    stale: bool,
+}
+
+impl Default for Archetypes {
+    fn default() -> Self {
+        let mut ids = HashMap::default();
+        ids.insert(Vec::new(), 0);
+
+        Archetypes {
+            types: vec![Vec::new()],
+            ids,
+            rows: Vec::new(),
+            stale: false,
+        }
+    }
+}
+
+impl Archetypes {
+    /// Returns the archetype holding the given component types, creating it if
+    /// it does not exist yet.
+    pub(crate) fn insert(&mut self, mut types: Vec<TypeId>) -> usize {
+        types.sort_unstable();
+
+        if let Some(&id) = self.ids.get(&types) {
+            return id;
+        }
+
+        let id = self.types.len();
+        self.types.push(types.clone());
+        self.ids.insert(types, id);
+        id
+    }
+
+    /// Assigns the given rows to an archetype.
+    pub(crate) fn assign(&mut self, rows: Range<usize>, archetype: usize) {
+        if self.rows.len() < rows.end {
+            self.rows.resize(rows.end, 0);
+        }
+
+        for row in &mut self.rows[rows] {
+            *row = archetype as u32;
+        }
+    }
+
+    /// Returns for every archetype whether its rows can match a query, or
+    /// `None` if every row has to be visited.
+    ///
+    /// Rows of the empty archetype are not worth skipping on their own, as
+    /// they are reused by new entities, so if every other archetype matches,
+    /// every row is visited.
+    pub(crate) fn matching(&self, matches: fn(&[TypeId]) -> bool) -> Option<Vec<bool>> {
+        if self.stale {
+            return None;
+        }
+
+        let matching = self
+            .types
+            .iter()
+            .map(|types| matches(types))
+            .collect::<Vec<_>>();
+        if matching[1..].iter().all(|&matches| matches) {
+            return None;
+        }
+
+        Some(matching)
+    }
+
+    /// Returns the number of rows, of the `len` rows starting at `row`, that
+    /// can be skipped before reaching a row whose archetype matches.
+    pub(crate) fn skippable(&self, row: usize, len: usize, matching: &[bool]) -> usize {
+        let end = row + len;
+        let rows = self
+            .rows
+            .get(row..cmp::min(end, self.rows.len()))
+            .unwrap_or(&[]);
+        let skipped = rows
+            .iter()
+            .take_while(|&&archetype| !matching[archetype as usize])
+            .count();
+
+        // The rows beyond the end are in the empty archetype.
+        if skipped == rows.len() && !matching[0] {
+            return len;
+        }
+
+        skipped
+    }
+
+    /// Releases the capacity held beyond the last row that is not in the empty
+    /// archetype.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        let len = self.rows.iter().rposition(|&archetype| archetype != 0);
+        self.rows.truncate(len.map_or(0, |len| len + 1));
+        self.rows.shrink_to_fit();
+    }
+
+    /// Marks the archetypes as out of date, until they are rebuilt.
+    pub(crate) fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    pub(crate) fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Moves every row back to the empty archetype. The archetypes themselves
+    /// are kept, as entities are likely to be created with the same components
+    /// again.
+    pub(crate) fn clear(&mut self) {
+        self.rows.clear();
+        self.stale = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archetypes() {
+        let a = TypeId::of::<u8>();
+        let b = TypeId::of::<u16>();
+
+        let mut archetypes = Archetypes::default();
+        let ab = archetypes.insert(vec![a, b]);
+        assert_eq!(archetypes.insert(vec![b, a]), ab);
+
+        let only_a = archetypes.insert(vec![a]);
+        archetypes.assign(2..4, ab);
+        archetypes.assign(4..5, only_a);
+
+        assert_eq!(
+            archetypes.rows,
+            vec![0, 0, ab as u32, ab as u32, only_a as u32]
+        );
+
+        let matching = archetypes.matching(|types| types.contains(&TypeId::of::<u16>()));
+        assert_eq!(matching, Some(vec![false, true, false]));
+        assert_eq!(archetypes.matching(|types| !types.is_empty()), None);
+
+        let matching = matching.unwrap();
+        assert_eq!(archetypes.skippable(0, 10, &matching), 2);
+        assert_eq!(archetypes.skippable(3, 7, &matching), 0);
+        assert_eq!(archetypes.skippable(4, 6, &matching), 6);
+        assert_eq!(archetypes.skippable(4, 0, &matching), 0);
+
+        archetypes.assign(3..5, 0);
+        archetypes.shrink_to_fit();
+        assert_eq!(archetypes.rows, vec![0, 0, ab as u32]);
+
+        archetypes.invalidate();
+        assert_eq!(archetypes.matching(|types| types.is_empty()), None);
+
+        archetypes.clear();
+        assert!(archetypes.rows.is_empty());
+    }
+}
@@ -1,6 +1,10 @@
-use crate::{Component, Read, Write};
-use rustc_hash::FxHashSet as HashSet;
-use std::{any::TypeId, error, fmt};
+use crate::{system::QuerySet, Added, Changed, Commands, Component, Despawns, Entity, EventReader,
+            HashMap, Or, Read, Res, ResMut, Tick, With, Without, World, Write};
+use alloc::vec::Vec;
+use core::{any::{type_name, TypeId},
+           fmt};
+#[cfg(feature = "std")]
+use std::error;
 
 #[derive(Default)]
 pub(crate) struct RuntimeBorrow {
@@ -14,49 +18,138 @@ impl RuntimeBorrow {
         }
     }
 
-    pub(crate) fn push_access<R: RegisterBorrow>(&mut self) -> Result<(), BorrowError> {
-        let borrow = R::register_borrow()?;
+    pub(crate) fn push(&mut self, borrow: Borrow) {
         self.borrows.push(borrow);
-        Ok(())
     }
 
-    pub(crate) fn validate(&self) -> Result<(), BorrowError> {
-        let invalid = self.borrows.iter().enumerate().any(|(idx, borrow)| {
-            let non_exclusive_internal_write = !borrow.writes.is_disjoint(&borrow.reads);
-
-            let cross_borrow_non_exclusive_write = self.borrows.iter().skip(idx + 1).any(|other| {
-                !borrow.writes.is_disjoint(&other.writes)
-                    || !borrow.writes.is_disjoint(&other.reads)
-            });
+    /// Registers the borrow, unless it conflicts with any of the borrows that
+    /// are already registered, in which case it is handed back.
+    pub(crate) fn try_push(&mut self, borrow: Borrow) -> Result<(), Borrow> {
+        if self
+            .borrows
+            .iter()
+            .any(|other| other.conflict_with(&borrow).is_some())
+        {
+            return Err(borrow);
+        }
 
-            non_exclusive_internal_write || cross_borrow_non_exclusive_write
-        });
+        self.borrows.push(borrow);
+        Ok(())
+    }
 
-        match invalid {
-            true => Err(BorrowError::InvalidBorrow),
-            false => Ok(()),
-        }
+    /// Removes all registered borrows, allowing the next validation pass to
+    /// start from a clean slate.
+    pub(crate) fn clear(&mut self) {
+        self.borrows.clear();
     }
 }
 
 #[derive(Default)]
 pub struct Borrow {
-    reads: HashSet<TypeId>,
-    writes: HashSet<TypeId>,
+    /// reads and writes map the type IDs of the borrowed components and
+    /// resources to their type names and kinds, which are used to report
+    /// conflicting borrows.
+    reads: HashMap<TypeId, (&'static str, BorrowKind)>,
+    writes: HashMap<TypeId, (&'static str, BorrowKind)>,
+
+    /// reads_all is set if the whole world is read, through a
+    /// [`World`](crate::World) element, which conflicts with every write.
+    reads_all: bool,
 }
 
 impl Borrow {
     fn new() -> Self {
         Borrow {
-            reads: HashSet::default(),
-            writes: HashSet::default(),
+            reads: HashMap::default(),
+            writes: HashMap::default(),
+            reads_all: false,
+        }
+    }
+
+    /// Returns an error if any of the written components is also read, and a
+    /// token allowing the query to be iterated otherwise.
+    pub(crate) fn validate(&self) -> Result<BorrowToken, BorrowError> {
+        let read_write =
+            first_shared(&self.writes, &self.reads).or_else(|| self.first_write_read_by(self));
+
+        match read_write {
+            Some((type_name, kind)) => Err(WriteReadConflict { type_name, kind }),
+            None => Ok(BorrowToken(())),
+        }
+    }
+
+    /// Returns the error describing the first conflict found, if either borrow
+    /// writes a component that the other borrow reads or writes.
+    fn conflict_with(&self, other: &Borrow) -> Option<BorrowError> {
+        if let Some((type_name, kind)) = first_shared(&self.writes, &other.writes) {
+            return Some(ConflictingWrite { type_name, kind });
         }
+
+        first_shared(&self.writes, &other.reads)
+            .or_else(|| first_shared(&other.writes, &self.reads))
+            .or_else(|| self.first_write_read_by(other))
+            .or_else(|| other.first_write_read_by(self))
+            .map(|(type_name, kind)| WriteReadConflict { type_name, kind })
+    }
+
+    /// Returns the type name and kind of the first type written by this borrow,
+    /// if the other borrow reads the whole world.
+    fn first_write_read_by(&self, other: &Borrow) -> Option<(&'static str, BorrowKind)> {
+        if !other.reads_all {
+            return None;
+        }
+
+        self.writes.values().next().copied()
     }
+
+    /// Adds the reads and writes of another borrow to this borrow.
+    fn extend(&mut self, other: Borrow) {
+        self.reads.extend(other.reads);
+        self.writes.extend(other.writes);
+        self.reads_all |= other.reads_all;
+    }
+}
+
+/// Returns the type name and kind of the first type present in both maps.
+fn first_shared(
+    a: &HashMap<TypeId, (&'static str, BorrowKind)>,
+    b: &HashMap<TypeId, (&'static str, BorrowKind)>,
+) -> Option<(&'static str, BorrowKind)> {
+    a.iter()
+        .find(|&(id, _)| b.contains_key(id))
+        .map(|(_, &borrowed)| borrowed)
+}
+
+/// `BorrowKind` tells whether a borrowed type is stored as a component or as a
+/// resource, see [`BorrowError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorrowKind {
+    Component,
+    Resource,
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for BorrowKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BorrowKind::Component => f.write_str("component"),
+            BorrowKind::Resource => f.write_str("resource"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BorrowError {
-    InvalidBorrow,
+    /// The component or resource is written more than once.
+    ConflictingWrite {
+        type_name: &'static str,
+        kind: BorrowKind,
+    },
+
+    /// The component or resource is both written and read.
+    WriteReadConflict {
+        type_name: &'static str,
+        kind: BorrowKind,
+    },
 }
 
 use BorrowError::*;
@@ -64,40 +157,232 @@ use BorrowError::*;
 impl fmt::Display for BorrowError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            InvalidBorrow => f.write_str("unable to borrow component"),
+            ConflictingWrite { type_name, kind } => {
+                write!(f, "{} `{}` is written more than once", kind, type_name)
+            },
+            WriteReadConflict { type_name, kind } => {
+                write!(f, "{} `{}` is both written and read", kind, type_name)
+            },
         }
     }
 }
 
-impl error::Error for BorrowError {
-    fn description(&self) -> &str {
-        match self {
-            InvalidBorrow => "unable to borrow component",
+// A borrow error is never caused by another error, so the default `source`,
+// which returns `None`, is kept.
+#[cfg(feature = "std")]
+impl error::Error for BorrowError {}
+
+/// `BorrowConflict` describes a conflict between the borrows of two systems,
+/// see [`Schedule::validate`](crate::Schedule::validate).
+///
+/// A system whose own borrows are invalid is reported as conflicting with
+/// itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BorrowConflict {
+    /// The type names of the conflicting systems, in the order in which they
+    /// were added.
+    pub systems: (&'static str, &'static str),
+
+    /// The first conflict found between the borrows of the systems.
+    pub error: BorrowError,
+}
+
+impl fmt::Display for BorrowConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.systems {
+            (a, b) if a == b => write!(f, "system `{}` conflicts with itself", a),
+            (a, b) => write!(f, "system `{}` conflicts with system `{}`", a, b),
         }
     }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for BorrowConflict {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+pub(crate) type BorrowResult = Result<Borrow, BorrowError>;
 
-    fn cause(&self) -> Option<&error::Error> {
-        None
+/// `BorrowToken` proves the borrows of a query have been validated, and is
+/// required to create the iterator of a [`Query`](crate::Query).
+///
+/// The iterator of a query writing a component hands out mutable references
+/// through a shared reference to [`Things`](crate::Things), which is only
+/// sound as long as no other iterator accesses the same component. Tokens are
+/// only created by the library, after validating the borrows of a query that
+/// is about to be iterated through an exclusive reference to `Things`, or
+/// alongside queries whose borrows are disjoint from its own. Each token is
+/// consumed by the iterator it creates.
+///
+/// Outside of the library, queries can only be iterated through methods such
+/// as [`Things::query`](crate::Things::query), which validate them first:
+///
+/// ```compile_fail
+/// # use things::{BorrowToken, Component, Query, Things, Write};
+/// # #[derive(Component, Debug)]
+/// # struct Position(u32);
+/// let ecs = Things::new();
+/// let first = <Write<Position> as Query>::iter(&ecs, BorrowToken(()));
+/// ```
+#[derive(Debug)]
+pub struct BorrowToken(());
+
+impl BorrowToken {
+    /// Creates a token without validating any borrows, for queries that are
+    /// made up of other queries that have already been validated together.
+    pub(crate) fn new() -> Self {
+        BorrowToken(())
     }
 }
 
-type BorrowResult = Result<Borrow, BorrowError>;
+/// Validates the borrows of the query of a single system.
+///
+/// Systems are executed through `&mut Things`, which already rules out any
+/// other system running at the same time, so only the borrows within the query
+/// itself can conflict, and no state has to be kept between executions.
+pub(crate) fn validate<R: RegisterBorrow>() -> Result<BorrowToken, BorrowError> {
+    R::register_borrow()?.validate()
+}
+
+/// Registers the borrows of a query, for callers that keep them for later,
+/// such as a schedule validating all of its systems at once.
+pub(crate) fn register<R: RegisterBorrow>() -> BorrowResult {
+    R::register_borrow()
+}
+
+/// Returns every conflict within the borrows of each of the given systems, and
+/// between the borrows of every pair of them.
+///
+/// Pairs involving a system whose own borrows are invalid are not checked, as
+/// its borrows are not known in full.
+pub(crate) fn conflicts(systems: &[(&'static str, BorrowResult)]) -> Vec<BorrowConflict> {
+    let mut conflicts = Vec::new();
+    let mut borrows = Vec::new();
+
+    for &(name, ref borrow) in systems {
+        let error = match borrow {
+            Ok(borrow) => match borrow.validate() {
+                Ok(_) => {
+                    borrows.push((name, borrow));
+                    continue;
+                },
+                Err(error) => error,
+            },
+            Err(error) => error.clone(),
+        };
+
+        conflicts.push(BorrowConflict {
+            systems: (name, name),
+            error,
+        });
+    }
+
+    for (i, &(a, borrow)) in borrows.iter().enumerate() {
+        for &(b, other) in &borrows[i + 1..] {
+            if let Some(error) = borrow.conflict_with(other) {
+                conflicts.push(BorrowConflict {
+                    systems: (a, b),
+                    error,
+                });
+            }
+        }
+    }
+
+    conflicts
+}
 
 pub trait RegisterBorrow {
     /// Creates a new borrow
     fn register_borrow() -> BorrowResult;
 }
 
-impl<A, B> RegisterBorrow for (A, B)
-where
-    A: PushBorrow,
-    B: PushBorrow,
-{
+/// Implements [`RegisterBorrow`] for a tuple of query elements, matching the
+/// arities for which [`Query`](crate::Query) is implemented.
+macro_rules! impl_register_borrow {
+    ($($element:ident),+) => {
+        impl<$($element),+> RegisterBorrow for ($($element,)+)
+        where
+            $($element: PushBorrow),+
+        {
+            fn register_borrow() -> BorrowResult {
+                let mut borrow = Borrow::new();
+
+                $($element::push_borrow(&mut borrow)?;)+
+
+                Ok(borrow)
+            }
+        }
+    };
+}
+
+impl_register_borrow!(A);
+impl_register_borrow!(A, B);
+impl_register_borrow!(A, B, C);
+impl_register_borrow!(A, B, C, D);
+impl_register_borrow!(A, B, C, D, E);
+impl_register_borrow!(A, B, C, D, E, F);
+impl_register_borrow!(A, B, C, D, E, F, G);
+impl_register_borrow!(A, B, C, D, E, F, G, H);
+
+/// Implements [`RegisterBorrow`] for a set of queries, matching the arities for
+/// which [`QuerySet`] implements [`Query`](crate::Query).
+///
+/// Every query is validated on its own, and then against the combined borrows
+/// of the queries before it, which are returned together, so a system holding
+/// the set conflicts with the same systems as the queries would.
+macro_rules! impl_register_borrow_set {
+    ($($query:ident),+) => {
+        impl<$($query),+> RegisterBorrow for QuerySet<($($query,)+)>
+        where
+            $($query: RegisterBorrow),+
+        {
+            fn register_borrow() -> BorrowResult {
+                let mut borrow = Borrow::new();
+
+                $(
+                    let other = $query::register_borrow()?;
+                    other.validate()?;
+
+                    if let Some(err) = borrow.conflict_with(&other) {
+                        return Err(err);
+                    }
+                    borrow.extend(other);
+                )+
+
+                Ok(borrow)
+            }
+        }
+    };
+}
+
+impl_register_borrow_set!(A, B);
+impl_register_borrow_set!(A, B, C);
+impl_register_borrow_set!(A, B, C, D);
+
+impl<C: Component> RegisterBorrow for Read<C> {
     fn register_borrow() -> BorrowResult {
         let mut borrow = Borrow::new();
+        Self::push_borrow(&mut borrow)?;
 
-        A::push_borrow(&mut borrow)?;
-        B::push_borrow(&mut borrow)?;
+        Ok(borrow)
+    }
+}
+
+impl<C: Component> RegisterBorrow for Write<C> {
+    fn register_borrow() -> BorrowResult {
+        let mut borrow = Borrow::new();
+        Self::push_borrow(&mut borrow)?;
+
+        Ok(borrow)
+    }
+}
+
+impl<R: 'static> RegisterBorrow for ResMut<R> {
+    fn register_borrow() -> BorrowResult {
+        let mut borrow = Borrow::new();
+        Self::push_borrow(&mut borrow)?;
 
         Ok(borrow)
     }
@@ -111,18 +396,255 @@ impl<C: Component> PushBorrow for Read<C> {
     /// Multiple reads are always allowed and therefor we can always return
     /// true.
     fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
-        borrow.reads.insert(TypeId::of::<C>());
+        borrow
+            .reads
+            .insert(TypeId::of::<C>(), (type_name::<C>(), BorrowKind::Component));
+        Ok(())
+    }
+}
+
+impl<C: Component> PushBorrow for Option<Read<C>> {
+    /// An optional read borrows the component in the same way as a regular
+    /// read does.
+    fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+        Read::<C>::push_borrow(borrow)
+    }
+}
+
+impl PushBorrow for Entity {
+    /// Entities are never mutated by a query, so they don't have to be
+    /// registered.
+    fn push_borrow(_: &mut Borrow) -> Result<(), BorrowError> {
+        Ok(())
+    }
+}
+
+impl PushBorrow for Despawns {
+    /// Despawns are only applied after a system has finished, so they never
+    /// conflict with any other borrow.
+    fn push_borrow(_: &mut Borrow) -> Result<(), BorrowError> {
+        Ok(())
+    }
+}
+
+impl PushBorrow for Commands {
+    /// Commands are only applied after a system has finished, so they never
+    /// conflict with any other borrow.
+    fn push_borrow(_: &mut Borrow) -> Result<(), BorrowError> {
+        Ok(())
+    }
+}
+
+impl PushBorrow for World {
+    /// The world is read as a whole, so it conflicts with every write, whether
+    /// to a component or to a resource.
+    fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+        borrow.reads_all = true;
         Ok(())
     }
 }
 
+impl PushBorrow for Tick {
+    /// The tick is only advanced in between systems, so reading it never
+    /// conflicts with any other borrow.
+    fn push_borrow(_: &mut Borrow) -> Result<(), BorrowError> {
+        Ok(())
+    }
+}
+
+impl<R: 'static> PushBorrow for Res<R> {
+    /// Resources are registered under the type ID of their `Res` element, to
+    /// keep them apart from components of the same type.
+    fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+        borrow.reads.insert(
+            TypeId::of::<Res<R>>(),
+            (type_name::<R>(), BorrowKind::Resource),
+        );
+        Ok(())
+    }
+}
+
+impl<R: 'static> PushBorrow for ResMut<R> {
+    fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+        match borrow.writes.insert(
+            TypeId::of::<Res<R>>(),
+            (type_name::<R>(), BorrowKind::Resource),
+        ) {
+            None => Ok(()),
+            Some((type_name, kind)) => Err(ConflictingWrite { type_name, kind }),
+        }
+    }
+}
+
+impl<E: 'static> PushBorrow for EventReader<E> {
+    /// Events can only be sent from outside of a query, or through
+    /// [`Commands`], so reading them never conflicts with any other borrow.
+    fn push_borrow(_: &mut Borrow) -> Result<(), BorrowError> {
+        Ok(())
+    }
+}
+
+impl<C: Component> PushBorrow for With<C> {
+    /// Filtering on the presence of a component requires reading its store,
+    /// which means the component can't be written to in the same query.
+    fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+        Read::<C>::push_borrow(borrow)
+    }
+}
+
+impl<C: Component> PushBorrow for Without<C> {
+    /// Filtering on the absence of a component requires reading its store,
+    /// which means the component can't be written to in the same query.
+    fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+        Read::<C>::push_borrow(borrow)
+    }
+}
+
+impl<C: Component> PushBorrow for Changed<C> {
+    /// Filtering on changes of a component requires reading its store, which
+    /// means the component can't be written to in the same query.
+    fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+        Read::<C>::push_borrow(borrow)
+    }
+}
+
+impl<C: Component> PushBorrow for Added<C> {
+    /// Filtering on additions of a component requires reading its store, which
+    /// means the component can't be written to in the same query.
+    fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+        Read::<C>::push_borrow(borrow)
+    }
+}
+
+/// Implements [`PushBorrow`] for [`Or`] over a tuple of filters, matching the
+/// arities for which it implements [`QueryElement`](crate::QueryElement).
+macro_rules! impl_push_borrow_or {
+    ($($element:ident),+) => {
+        impl<$($element: PushBorrow),+> PushBorrow for Or<($($element,)+)> {
+            /// Every filter is checked for every row, so the stores read by any
+            /// of them are borrowed.
+            fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+                $($element::push_borrow(borrow)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_push_borrow_or!(A);
+impl_push_borrow_or!(A, B);
+impl_push_borrow_or!(A, B, C);
+impl_push_borrow_or!(A, B, C, D);
+impl_push_borrow_or!(A, B, C, D, E);
+impl_push_borrow_or!(A, B, C, D, E, F);
+impl_push_borrow_or!(A, B, C, D, E, F, G);
+impl_push_borrow_or!(A, B, C, D, E, F, G, H);
+
 impl<C: Component> PushBorrow for Write<C> {
-    /// Only a single write borrow is allowed. The `HashSet` returns false if
-    /// the `TypeId` of the component is already present in the borrow.
+    /// Only a single write borrow is allowed. The `HashMap` returns the
+    /// previous type name if the `TypeId` of the component is already present
+    /// in the borrow.
     fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
-        match borrow.writes.insert(TypeId::of::<C>()) {
-            true => Ok(()),
-            false => Err(BorrowError::InvalidBorrow),
+        match borrow
+            .writes
+            .insert(TypeId::of::<C>(), (type_name::<C>(), BorrowKind::Component))
+        {
+            None => Ok(()),
+            Some((type_name, kind)) => Err(ConflictingWrite { type_name, kind }),
         }
     }
 }
+
+impl<C: Component> PushBorrow for Option<Write<C>> {
+    /// An optional write borrows the component in the same way as a regular
+    /// write does.
+    fn push_borrow(borrow: &mut Borrow) -> Result<(), BorrowError> {
+        Write::<C>::push_borrow(borrow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component, Debug)]
+    struct A;
+
+    #[derive(Component, Debug)]
+    struct B;
+
+    fn check<R: RegisterBorrow>() -> Result<(), BorrowError> {
+        validate::<R>().map(drop)
+    }
+
+    /// Asserts that a writing element of `A` conflicts with each of the given
+    /// reading elements of `A`, in either order, and with other elements in
+    /// between.
+    macro_rules! assert_write_read {
+        ($write:ty; $($read:ty),+) => {
+            let conflict = Err(WriteReadConflict {
+                type_name: type_name::<A>(),
+                kind: BorrowKind::Component,
+            });
+            $(
+                assert_eq!(check::<($write, $read)>(), conflict);
+                assert_eq!(check::<($read, $write)>(), conflict);
+                assert_eq!(check::<($write, Read<B>, $read)>(), conflict);
+                assert_eq!(check::<($read, Entity, Write<B>, $write)>(), conflict);
+            )+
+        };
+    }
+
+    #[test]
+    fn test_aliasing_borrows() {
+        assert_write_read!(Write<A>; Read<A>, Option<Read<A>>, With<A>, Without<A>, Changed<A>,
+                           Added<A>);
+        assert_write_read!(Option<Write<A>>; Read<A>, Option<Read<A>>, With<A>, Without<A>,
+                           Changed<A>, Added<A>);
+
+        let conflict = Err(ConflictingWrite {
+            type_name: type_name::<A>(),
+            kind: BorrowKind::Component,
+        });
+        assert_eq!(check::<(Write<A>, Write<A>)>(), conflict);
+        assert_eq!(check::<(Write<A>, Option<Write<A>>)>(), conflict);
+        assert_eq!(check::<(Option<Write<A>>, Read<B>, Write<A>)>(), conflict);
+        assert_eq!(check::<(Option<Write<A>>, Option<Write<A>>)>(), conflict);
+
+        assert_eq!(
+            check::<(Read<A>, Option<Read<A>>, With<A>, Changed<A>)>(),
+            Ok(())
+        );
+        assert_eq!(check::<(Write<A>, Read<B>, Without<B>)>(), Ok(()));
+        assert_eq!(check::<(Write<A>, Write<B>)>(), Ok(()));
+    }
+
+    #[test]
+    fn test_world_borrows() {
+        let conflict = Err(WriteReadConflict {
+            type_name: type_name::<A>(),
+            kind: BorrowKind::Component,
+        });
+        assert_eq!(check::<(Write<A>, World)>(), conflict);
+        assert_eq!(check::<(World, Read<B>, Option<Write<A>>)>(), conflict);
+
+        let conflict = Err(WriteReadConflict {
+            type_name: type_name::<u32>(),
+            kind: BorrowKind::Resource,
+        });
+        assert_eq!(check::<(World, ResMut<u32>)>(), conflict);
+
+        assert_eq!(check::<(World, World, Read<A>, Res<u32>, Entity)>(), Ok(()));
+
+        let world = <(World, Read<B>)>::register_borrow().unwrap();
+        let writer = <(Read<B>, Write<A>)>::register_borrow().unwrap();
+        let reader = <(World, Read<A>)>::register_borrow().unwrap();
+        let conflict = Some(WriteReadConflict {
+            type_name: type_name::<A>(),
+            kind: BorrowKind::Component,
+        });
+        assert_eq!(world.conflict_with(&writer), conflict);
+        assert_eq!(writer.conflict_with(&world), conflict);
+        assert!(world.conflict_with(&reader).is_none());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{component::{push, row_count, store_mut, StoreResult},
+            store::ComponentStores,
+            Component, Entity, Things};
+use alloc::{boxed::Box, vec::Vec};
+use core::any::TypeId;
+
+type PushFn = Box<dyn FnOnce(&mut ComponentStores, usize, usize) -> usize>;
+
+/// `ThingsBuilder` configures a [`Things`] before any entity is created.
+///
+/// The store of a component type is normally created when the first
+/// component of that type is stored. Registering it up front makes sure the
+/// capacity reserved by [`ThingsBuilder::with_capacity`] also applies to it:
+///
+/// ```
+/// use things::{Component, SparseStore, Things};
+///
+/// #[derive(Component, Debug)]
+/// struct Position(f32, f32);
+///
+/// #[derive(Component, Debug)]
+/// #[component(storage = "SparseStore")]
+/// struct Health(u8);
+///
+/// let ecs = Things::builder()
+///     .with_capacity(1000)
+///     .register_store::<Position>()
+///     .register_store::<Health>()
+///     .build();
+///
+/// assert!(ecs.store::<Health>().is_some());
+/// ```
+///
+/// Component types that are not registered are still given a store as soon as
+/// their first component is stored.
+#[derive(Default)]
+pub struct ThingsBuilder {
+    things: Things,
+    capacity: usize,
+}
+
+impl ThingsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves capacity for at least `capacity` entities, and for as many
+    /// components in the store of every registered component type.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Creates the store of the given component type.
+    ///
+    /// The type of the store is always the [`Component::Storage`] of the
+    /// component type, which is selected using the `component` attribute of
+    /// the derive macro.
+    pub fn register_store<C: Component>(mut self) -> Self {
+        store_mut::<C>(&mut self.things.component_stores, self.things.change_tick);
+        self
+    }
+
+    /// Returns the configured [`Things`].
+    pub fn build(mut self) -> Things {
+        self.things.reserve(self.capacity);
+        self.things
+    }
+}
+
+/// `EntityBuilder` collects the components of a new entity one at a time,
+/// before storing them all at once. See [`Things::spawn`].
+pub struct EntityBuilder<'a> {
+    things: &'a mut Things,
+
+    /// components holds the type of each added component, along with a
+    /// function pushing it into its store at a given cursor and tick.
+    components: Vec<(TypeId, PushFn)>,
+}
+
+impl<'a> EntityBuilder<'a> {
+    pub(crate) fn new(things: &'a mut Things) -> Self {
+        EntityBuilder {
+            things,
+            components: Vec::new(),
+        }
+    }
+
+    /// Adds a component to the entity.
+    ///
+    /// Adding multiple components of the same type makes the entity span
+    /// multiple rows, the same as repeating a type in a tuple of components.
+    pub fn with<C: Component>(mut self, component: C) -> Self {
+        let push = move |stores: &mut _, cursor, tick| push(stores, cursor, tick, component);
+        self.components.push((TypeId::of::<C>(), Box::new(push)));
+        self
+    }
+
+    /// Creates the entity, and returns its handle.
+    ///
+    /// An entity without any components takes up a single empty row, the same
+    /// as an entity whose components were all removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the generations of the entities overflow, see
+    /// [`Things::try_create_entity`].
+    pub fn build(self) -> Entity {
+        let EntityBuilder { things, components } = self;
+        let entity = things
+            .alloc_entity()
+            .unwrap_or_else(|err| panic!("{}", err));
+
+        let types = components.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+        let rows = row_count(&types);
+
+        let cursor = things
+            .take_free_rows(rows)
+            .unwrap_or(things.component_cursor);
+
+        let tick = things.change_tick;
+        let positions = components
+            .into_iter()
+            .map(|(_, push)| push(&mut things.component_stores, cursor, tick))
+            .collect::<Vec<_>>();
+
+        let position = positions.iter().copied().min().unwrap_or(cursor);
+        let len = positions.iter().max().map_or(1, |max| max - position + 1);
+        things.place_entity(entity, StoreResult { position, len });
+
+        entity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SparseStore;
+
+    #[derive(Component, Debug)]
+    struct A;
+
+    #[derive(Component, Debug)]
+    #[component(storage = "SparseStore")]
+    struct B;
+
+    #[test]
+    fn test_builder() {
+        let mut ecs = Things::builder()
+            .register_store::<B>()
+            .with_capacity(8)
+            .build();
+
+        assert!(ecs.entities.capacity() >= 8);
+        assert!(ecs.store::<A>().is_none());
+        assert_eq!(ecs.store::<B>().map(crate::Store::slots), Some(0));
+
+        ecs.create_entity((A,));
+        for store in ecs.component_stores.values() {
+            assert_eq!(store.slots(), 1);
+        }
+    }
+
+    #[test]
+    fn test_entity_builder_layout() {
+        #[derive(Component, Debug, PartialEq)]
+        struct D(usize);
+
+        let mut tuples = Things::new();
+        let mut built = Things::new();
+
+        for ecs in [&mut tuples, &mut built] {
+            let a = ecs.create_entity((A, D(0)));
+            ecs.create_entity((B,));
+            ecs.despawn(a);
+        }
+
+        let a = tuples.create_entity((D(1), B, D(2)));
+        let b = tuples.create_entity((A,));
+        let c = built.spawn().with(D(1)).with(B).with(D(2)).build();
+        let d = built.spawn().with(A).build();
+
+        assert_eq!(tuples.references(a), built.references(c));
+        assert_eq!(tuples.references(b), built.references(d));
+        assert_eq!(tuples.component_cursor, built.component_cursor);
+        assert_eq!(tuples.store_lengths(), built.store_lengths());
+        assert_eq!(
+            built.get_all::<D>(c).collect::<Vec<_>>(),
+            vec![&D(1), &D(2)]
+        );
+        assert!(built.has_component::<A>(d));
+
+        let e = built.spawn().build();
+        assert!(built.is_alive(e));
+        assert_eq!(built.references(e), Some((built.component_cursor - 1, 1)));
+    }
+}
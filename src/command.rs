@@ -0,0 +1,267 @@
+use crate::{component::{Component, ComponentCollection},
+            system::{Data, QueryElement},
+            Entity, Mutex, Things};
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "parallel")]
+use core::cell::Cell;
+
+type Command = Box<dyn FnOnce(&mut Things) + Send>;
+
+#[cfg(feature = "parallel")]
+std::thread_local! {
+    /// LANE holds the index of the system running on this thread within its
+    /// stage, see [`Queues`].
+    static LANE: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// `Commands` records structural changes to the world, such as spawning or
+/// despawning entities, to be applied once the running system has finished.
+///
+/// Systems can't change the layout of the component stores while iterating
+/// them, so instead they request `Commands` as an element of their query, and
+/// the recorded commands are applied in order after the system returns.
+#[derive(Default)]
+pub struct Commands {
+    queue: Mutex<Vec<Command>>,
+}
+
+impl Commands {
+    /// Records the creation of a new entity from a collection of components.
+    pub fn spawn<CC: ComponentCollection + Send + 'static>(&self, components: CC) {
+        self.push(move |things| {
+            things.create_entity(components);
+        });
+    }
+
+    /// Records the removal of an entity, and all of its components.
+    pub fn despawn(&self, entity: Entity) {
+        self.push(move |things| {
+            things.despawn(entity);
+        });
+    }
+
+    /// Records the addition of a component to an existing entity.
+    pub fn insert<C: Component>(&self, entity: Entity, component: C) {
+        self.push(move |things| {
+            things.add_component(entity, component);
+        });
+    }
+
+    /// Records the removal of a component from an existing entity.
+    pub fn remove<C: Component>(&self, entity: Entity) {
+        self.push(move |things| {
+            things.remove_component::<C>(entity);
+        });
+    }
+
+    /// Records the sending of an event.
+    pub fn send<E: Send + Sync + 'static>(&self, event: E) {
+        self.push(move |things| {
+            things.send_event(event);
+        });
+    }
+
+    fn push<F: FnOnce(&mut Things) + Send + 'static>(&self, command: F) {
+        self.queue.lock().push(Box::new(command));
+    }
+
+    /// Takes all recorded commands out of the queue.
+    pub(crate) fn drain(&self) -> Vec<Command> {
+        core::mem::take(&mut *self.queue.lock())
+    }
+}
+
+/// `Despawns` marks entities to be despawned once the running system has
+/// finished, as a lighter alternative to [`Commands`] for the most common
+/// structural change.
+///
+/// Systems request `Despawns` as an element of their query, and the same
+/// marks are made by [`Things::mark_despawn`] outside of systems:
+///
+/// ```
+/// # use things::{Component, Despawns, Entity, QueryIter, Read, Things};
+/// # #[derive(Component, Debug)]
+/// # struct Health(u8);
+/// fn die(components: QueryIter<(Entity, Read<Health>, Despawns)>) {
+///     for (entity, health, despawns) in components {
+///         if health.0 == 0 {
+///             despawns.mark(entity);
+///         }
+///     }
+/// }
+///
+/// let mut ecs = Things::new();
+/// ecs.create_entity((Health(0),));
+/// ecs.create_entity((Health(10),));
+///
+/// ecs.run(die).unwrap();
+/// assert_eq!(ecs.entity_count(), 1);
+/// ```
+#[derive(Default)]
+pub struct Despawns {
+    queue: Mutex<Vec<Entity>>,
+}
+
+impl Despawns {
+    /// Marks an entity to be despawned. An entity that is marked more than
+    /// once, or that is no longer alive by the time the marks are applied, is
+    /// skipped.
+    pub fn mark(&self, entity: Entity) {
+        self.queue.lock().push(entity);
+    }
+
+    /// Takes all marked entities out of the queue, in the order in which they
+    /// were marked.
+    pub(crate) fn drain(&self) -> Vec<Entity> {
+        core::mem::take(&mut *self.queue.lock())
+    }
+}
+
+/// `Queues` holds the [`Commands`] and [`Despawns`] recorded through a shared
+/// reference to the world, until they are applied.
+///
+/// The systems of a stage run at the same time with the `parallel` feature,
+/// so if they shared a single queue, their changes would be applied in the
+/// order in which their threads happened to record them. Instead, every system
+/// of a stage records into a lane of its own, and the lanes are applied in the
+/// order in which the systems were given, after anything recorded outside of
+/// a stage.
+#[derive(Default)]
+pub(crate) struct Queues {
+    commands: Commands,
+    despawns: Despawns,
+
+    /// lanes holds the queues of each system of a stage, by their index within
+    /// the stage. Lanes are kept between stages, to reuse their allocations.
+    #[cfg(feature = "parallel")]
+    lanes: Vec<(Commands, Despawns)>,
+}
+
+impl Queues {
+    /// Returns the commands buffer of the system running on this thread, or
+    /// the shared buffer outside of a stage.
+    pub(crate) fn commands(&self) -> &Commands {
+        match self.lane() {
+            Some((commands, _)) => commands,
+            None => &self.commands,
+        }
+    }
+
+    /// Returns the despawn queue of the system running on this thread, or the
+    /// shared queue outside of a stage.
+    pub(crate) fn despawns(&self) -> &Despawns {
+        match self.lane() {
+            Some((_, despawns)) => despawns,
+            None => &self.despawns,
+        }
+    }
+
+    /// Makes sure there is a lane for each of the given number of systems.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn open_lanes(&mut self, systems: usize) {
+        if self.lanes.len() < systems {
+            self.lanes.resize_with(systems, Default::default);
+        }
+    }
+
+    /// Takes all recorded commands out of the queues, in the order in which
+    /// they are applied.
+    pub(crate) fn drain_commands(&self) -> Vec<Command> {
+        let mut commands = self.commands.drain();
+        for (lane, _) in self.lanes() {
+            commands.extend(lane.drain());
+        }
+
+        commands
+    }
+
+    /// Takes all marked entities out of the queues, in the order in which they
+    /// are despawned.
+    pub(crate) fn drain_despawns(&self) -> Vec<Entity> {
+        let mut despawns = self.despawns.drain();
+        for (_, lane) in self.lanes() {
+            despawns.extend(lane.drain());
+        }
+
+        despawns
+    }
+
+    #[cfg(feature = "parallel")]
+    fn lane(&self) -> Option<&(Commands, Despawns)> {
+        LANE.with(Cell::get).and_then(|lane| self.lanes.get(lane))
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn lane(&self) -> Option<&(Commands, Despawns)> {
+        None
+    }
+
+    #[cfg(feature = "parallel")]
+    fn lanes(&self) -> impl Iterator<Item = &(Commands, Despawns)> {
+        self.lanes.iter()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn lanes(&self) -> impl Iterator<Item = &(Commands, Despawns)> {
+        core::iter::empty()
+    }
+}
+
+/// Returns the lane of the system running on this thread, if any.
+#[cfg(feature = "parallel")]
+pub(crate) fn current_lane() -> Option<usize> {
+    LANE.with(Cell::get)
+}
+
+/// Calls the closure with the lane of this thread set to the given lane, such
+/// as to run a system of a stage, or to prepare a chunk of its query on
+/// another thread. The previous lane is restored afterwards, even if the
+/// closure panics, as `rayon` may run another system on this thread while the
+/// current one waits.
+#[cfg(feature = "parallel")]
+pub(crate) fn with_lane<R>(lane: Option<usize>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<usize>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            LANE.with(|lane| lane.set(self.0));
+        }
+    }
+
+    let _restore = Restore(LANE.with(|current| current.replace(lane)));
+    f()
+}
+
+/// The same `Commands` buffer is yielded for every row of the query.
+impl<'a> QueryElement<'a> for Commands {
+    type Item = &'a Commands;
+    type Kind = Data;
+    type Slots = &'a Commands;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        things.queues.commands()
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        Some(*slots)
+    }
+
+    fn skip(_slots: &mut Self::Slots, _rows: usize) {}
+}
+
+/// The same `Despawns` marks are yielded for every row of the query.
+impl<'a> QueryElement<'a> for Despawns {
+    type Item = &'a Despawns;
+    type Kind = Data;
+    type Slots = &'a Despawns;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        things.queues.despawns()
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        Some(*slots)
+    }
+
+    fn skip(_slots: &mut Self::Slots, _rows: usize) {}
+}
@@ -1,104 +1,593 @@
-use crate::store::{ComponentStore, DefaultStore, Store};
-use core::fmt::Debug;
-use rustc_hash::FxHashMap as HashMap;
-use std::any::TypeId;
+use crate::{store::{ComponentStores, Store},
+            DefaultStore, HashMap, TagStore};
+use alloc::{boxed::Box, vec::Vec};
+use core::{any::{self, Any, TypeId},
+           fmt::{self, Debug},
+           ops::{Deref, DerefMut}};
 
 /// The `Component` trait is a marker trait that allows any object implementing
 /// it to be stored in a component store.
-pub trait Component: 'static + Debug {}
+///
+/// Types have to opt into being a component, which is most easily done using
+/// the derive macro. The `component` attribute selects a store other than the
+/// [`DefaultStore`]:
+///
+/// ```
+/// use things::Component;
+///
+/// #[derive(Component, Debug)]
+/// struct Position(f32, f32);
+///
+/// #[derive(Component, Debug)]
+/// #[component(storage = "things::SparseStore")]
+/// struct Selected;
+/// ```
+///
+/// Components have to implement `Debug`, so the world can be printed. Fields
+/// of types that don't implement it can be wrapped in an [`Opaque`]:
+///
+/// ```
+/// use things::{Component, Opaque};
+///
+/// struct Handle(u64);
+///
+/// #[derive(Component, Debug)]
+/// struct Texture(Opaque<Handle>);
+/// ```
+///
+/// [`DefaultStore`]: crate::DefaultStore
+pub trait Component: 'static + Debug + Send + Sync {
+    /// `Storage` is the [`Store`] implementation used to store all components
+    /// of this type, such as [`DefaultStore`] or [`SparseStore`].
+    ///
+    /// [`DefaultStore`]: crate::DefaultStore
+    /// [`SparseStore`]: crate::SparseStore
+    type Storage: Store<Item = Self> + 'static;
+}
+
+/// `ZeroSized` selects the store of a component derived without a `component`
+/// attribute, through [`SelectStorage`], based on whether the component is
+/// zero-sized. It is only used by the code generated by the derive macro.
+#[doc(hidden)]
+pub struct ZeroSized<const ZERO_SIZED: bool>;
+
+#[doc(hidden)]
+pub trait SelectStorage<C> {
+    type Storage;
+}
 
-/// `Component` is automatically implemented for any object that has both a
-/// static lifetime, and implements `Debug`.
-impl<T> Component for T where T: 'static + Debug {}
+impl<C: Component> SelectStorage<C> for ZeroSized<true> {
+    type Storage = TagStore<C>;
+}
+
+impl<C: Component> SelectStorage<C> for ZeroSized<false> {
+    type Storage = DefaultStore<C>;
+}
 
 /// A collection of one or more components should implement this trait to be
 /// able to manipulate each individual component, or store them for later use.
 pub trait ComponentCollection {
-    fn store(self, stores: &mut HashMap<TypeId, Box<ComponentStore>>, cursor: usize)
-        -> StoreResult;
+    fn store(self, stores: &mut ComponentStores, cursor: usize, tick: usize) -> StoreResult;
+
+    /// Returns the number of rows a collection takes up, which is the number of
+    /// times the most repeated component type occurs in the collection.
+    fn rows() -> usize
+    where
+        Self: Sized;
+
+    /// Stores a batch of collections, one entity per collection, and returns
+    /// the location of each entity.
+    ///
+    /// The position of each entity is returned by `cursor`, which is called
+    /// once for every entity, in order. The store of each component type is
+    /// only looked up once for the whole batch, and the entities are laid out
+    /// exactly as if they were stored one at a time at those positions.
+    fn store_batch<Batch, Cursor>(
+        batch: Batch,
+        stores: &mut ComponentStores,
+        cursor: Cursor,
+        tick: usize,
+    ) -> Vec<StoreResult>
+    where
+        Batch: IntoIterator<Item = Self>,
+        Cursor: FnMut() -> usize,
+        Self: Sized;
+}
+
+/// `Bundle` is implemented by named, reusable groups of components, such as a
+/// `PlayerBundle`, which can be used anywhere a tuple of components is
+/// accepted.
+///
+/// Bundles are most easily defined using the derive macro, which stores every
+/// field of the struct as a component:
+///
+/// ```
+/// use things::{Bundle, Component, Things};
+///
+/// #[derive(Component, Debug)]
+/// struct Position(f32, f32);
+///
+/// #[derive(Component, Debug)]
+/// struct Health(u8);
+///
+/// #[derive(Bundle)]
+/// struct PlayerBundle {
+///     position: Position,
+///     health: Health,
+/// }
+///
+/// let mut ecs = Things::new();
+/// let player = ecs.create_entity(PlayerBundle {
+///     position: Position(0.0, 0.0),
+///     health: Health(100),
+/// });
+/// ```
+pub trait Bundle {
+    /// `Components` is the tuple of components the bundle is stored as.
+    type Components: ComponentCollection;
+
+    /// `into_components` turns the bundle into its tuple of components.
+    fn into_components(self) -> Self::Components;
+}
+
+impl<B: Bundle> ComponentCollection for B {
+    fn store(self, stores: &mut ComponentStores, cursor: usize, tick: usize) -> StoreResult {
+        self.into_components().store(stores, cursor, tick)
+    }
+
+    fn rows() -> usize {
+        B::Components::rows()
+    }
+
+    fn store_batch<Batch, Cursor>(
+        batch: Batch,
+        stores: &mut ComponentStores,
+        cursor: Cursor,
+        tick: usize,
+    ) -> Vec<StoreResult>
+    where
+        Batch: IntoIterator<Item = Self>,
+        Cursor: FnMut() -> usize,
+    {
+        let batch = batch.into_iter().map(Bundle::into_components);
+        ComponentCollection::store_batch(batch, stores, cursor, tick)
+    }
+}
+
+/// Returns the number of rows taken up by an entity holding components of the
+/// given types, which is the number of times the most repeated type occurs. An
+/// entity without any components takes up a single row.
+pub(crate) fn row_count(types: &[TypeId]) -> usize {
+    types
+        .iter()
+        .map(|id| types.iter().filter(|&other| other == id).count())
+        .max()
+        .unwrap_or(1)
+}
+
+/// `Opaque` wraps a value whose type does not implement `Debug`, and formats it
+/// as the name of its type, to allow it to be stored in a [`Component`].
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Opaque<T>(pub T);
+
+impl<T> Opaque<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Debug for Opaque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Opaque<{}>", any::type_name::<T>())
+    }
+}
+
+impl<T> Deref for Opaque<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Opaque<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
 }
 
+impl<T> From<T> for Opaque<T> {
+    fn from(value: T) -> Self {
+        Opaque(value)
+    }
+}
+
+/// `ComponentsBundle` holds the components taken out of a despawned entity, see
+/// [`Things::despawn_take`].
+///
+/// The components are type-erased, and can be taken out again by their type.
+///
+/// [`Things::despawn_take`]: crate::Things::despawn_take
+#[derive(Debug, Default)]
+pub struct ComponentsBundle {
+    components: HashMap<TypeId, Vec<Box<dyn Any + Send + Sync>>>,
+}
+
+impl ComponentsBundle {
+    pub(crate) fn insert(&mut self, id: TypeId, component: Box<dyn Any + Send + Sync>) {
+        self.components.entry(id).or_default().push(component);
+    }
+
+    /// Returns a reference to a component of the given type.
+    ///
+    /// If the entity held multiple components of the same type, the first one
+    /// is returned.
+    pub fn get<C: Component>(&self) -> Option<&C> {
+        self.components
+            .get(&TypeId::of::<C>())?
+            .first()?
+            .downcast_ref::<C>()
+    }
+
+    /// Takes a component of the given type out of the bundle.
+    ///
+    /// If the entity held multiple components of the same type, they are taken
+    /// in the order in which they were stored.
+    pub fn take<C: Component>(&mut self) -> Option<C> {
+        let components = self.components.get_mut(&TypeId::of::<C>())?;
+        if components.is_empty() {
+            return None;
+        }
+
+        components
+            .remove(0)
+            .downcast::<C>()
+            .ok()
+            .map(|component| *component)
+    }
+
+    /// Returns the number of components in the bundle.
+    pub fn len(&self) -> usize {
+        self.components.values().map(Vec::len).sum()
+    }
+
+    /// Returns `true` if the bundle holds no components.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The location of the components of a single entity within the stores.
+#[derive(Debug, PartialEq)]
 pub struct StoreResult {
     pub position: usize,
     pub len: usize,
 }
 
-impl<A, B> ComponentCollection for (A, B)
-where
-    A: Component,
-    B: Component,
-{
-    fn store(
-        self,
-        stores: &mut HashMap<TypeId, Box<ComponentStore>>,
-        cursor: usize,
-    ) -> StoreResult {
-        let id_a = TypeId::of::<A>();
-        let id_b = TypeId::of::<B>();
-
-        let store = stores
-            .entry(id_a)
-            .or_insert_with(|| Box::new(DefaultStore::<A>::default()))
-            .as_mut_store::<A>()
-            .unwrap();
-
-        let position_a = store.push(cursor, self.0);
-
-        let store = stores
-            .entry(id_b)
-            .or_insert_with(|| Box::new(DefaultStore::<B>::default()))
-            .as_mut_store::<B>()
-            .unwrap();
-
-        let position_b = store.push(cursor, self.1);
-
-        // Take the lowest inserted position as the starting point for the
-        // components of this entity.
-        //
-        // If components `Position` and `Velocity` are inserted, then both will
-        // be at the same position, but if two `Position` components are stored
-        // for a single entity, one will come after the other, so we have to
-        // keep track of both the starting position in the store, and the count
-        // of the component which is used the most for this entity.
-        let position = std::cmp::min(position_a, position_b);
-        let len = if position_a == position_b {
-            1
-        } else {
-            std::cmp::max(position_a, position_b) - position
-        };
-
-        StoreResult { position, len }
-    }
+/// Returns the store of the given component type, creating the store at the
+/// given tick if it does not exist yet.
+///
+/// The store is always created from the [`Component::Storage`] of the type, so
+/// components never end up in a store of another type, whichever path they
+/// are added through.
+pub(crate) fn store_mut<C: Component>(
+    stores: &mut ComponentStores,
+    tick: usize,
+) -> &mut C::Storage {
+    stores
+        .get_or_insert_with(TypeId::of::<C>(), || {
+            let mut store = C::Storage::default();
+            Store::set_tick(&mut store, tick);
+            Box::new(store)
+        })
+        .expect_mut_store::<C>()
+}
+
+/// Pushes a single component into the store of its type, creating the store if
+/// it does not exist yet, and returns the position at which it was stored.
+pub(crate) fn push<C: Component>(
+    stores: &mut ComponentStores,
+    cursor: usize,
+    tick: usize,
+    component: C,
+) -> usize {
+    store_mut::<C>(stores, tick).push(cursor, component)
 }
 
+/// Implements [`ComponentCollection`] for a tuple of components.
+///
+/// Each component type is paired with the name of the column holding the
+/// components of that type while storing a batch.
+macro_rules! impl_component_collection {
+    ($($component:ident $column:ident),+) => {
+        impl<$($component),+> ComponentCollection for ($($component,)+)
+        where
+            $($component: Component),+
+        {
+            #[allow(non_snake_case)]
+            fn store(
+                self,
+                stores: &mut ComponentStores,
+                cursor: usize,
+                tick: usize,
+            ) -> StoreResult {
+                let ($($component,)+) = self;
+                let positions = [$(push(stores, cursor, tick, $component)),+];
+
+                // Take the lowest inserted position as the starting point for
+                // the components of this entity.
+                //
+                // If components `Position` and `Velocity` are inserted, then
+                // both will be at the same position, but if two `Position`
+                // components are stored for a single entity, one will come
+                // after the other, so we have to keep track of both the
+                // starting position in the store, and the count of the
+                // component which is used the most for this entity.
+                let position = *positions.iter().min().unwrap();
+                let len = positions.iter().max().unwrap() - position + 1;
+
+                StoreResult { position, len }
+            }
+
+            fn rows() -> usize {
+                row_count(&[$(TypeId::of::<$component>()),+])
+            }
+
+            #[allow(non_snake_case)]
+            fn store_batch<Batch, Cursor>(
+                batch: Batch,
+                stores: &mut ComponentStores,
+                mut cursor: Cursor,
+                tick: usize,
+            ) -> Vec<StoreResult>
+            where
+                Batch: IntoIterator<Item = Self>,
+                Cursor: FnMut() -> usize,
+            {
+                $(let mut $column = Vec::new();)+
+                for ($($component,)+) in batch {
+                    $($column.push($component);)+
+                }
+
+                // Every entity takes up the same number of rows, since every
+                // collection in the batch holds the same component types.
+                let len = Self::rows();
+
+                let count = [$($column.len()),+][0];
+                let positions = (0..count).map(|_| cursor()).collect::<Vec<_>>();
+
+                $(
+                    let store = store_mut::<$component>(stores, tick);
+                    Store::reserve(store, count * len);
+
+                    for (&position, component) in positions.iter().zip($column) {
+                        store.push(position, component);
+                    }
+                )+
+
+                positions
+                    .into_iter()
+                    .map(|position| StoreResult { position, len })
+                    .collect()
+            }
+        }
+    };
+}
+
+impl_component_collection!(A a);
+impl_component_collection!(A a, B b);
+impl_component_collection!(A a, B b, C c);
+impl_component_collection!(A a, B b, C c, D d);
+impl_component_collection!(A a, B b, C c, D d, E e);
+impl_component_collection!(A a, B b, C c, D d, E e, F f);
+impl_component_collection!(A a, B b, C c, D d, E e, F f, G g);
+impl_component_collection!(A a, B b, C c, D d, E e, F f, G g, H h);
+impl_component_collection!(A a, B b, C c, D d, E e, F f, G g, H h, I i);
+impl_component_collection!(A a, B b, C c, D d, E e, F f, G g, H h, I i, J j);
+impl_component_collection!(A a, B b, C c, D d, E e, F f, G g, H h, I i, J j, K k);
+impl_component_collection!(A a, B b, C c, D d, E e, F f, G g, H h, I i, J j, K k, L l);
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Component, DefaultStore, SparseStore, TagStore};
 
     #[test]
-    fn test_implicit_component_trait() {
-        #[derive(Debug)]
+    fn test_derived_component_trait() {
+        #[derive(Component, Debug)]
         struct C;
 
-        let _: Box<Component> = Box::new(C);
+        #[derive(Component, Debug)]
+        #[component(storage = "SparseStore")]
+        struct D;
+
+        #[derive(Component, Debug)]
+        struct E(#[allow(dead_code)] usize);
+
+        fn assert_storage<C: Component<Storage = S>, S>() {}
+        assert_storage::<C, TagStore<C>>();
+        assert_storage::<D, SparseStore<D>>();
+        assert_storage::<E, DefaultStore<E>>();
+    }
+
+    #[test]
+    fn test_derived_zero_sized_storage() {
+        use core::marker::PhantomData;
+
+        #[derive(Component, Debug)]
+        struct C;
+
+        #[derive(Component, Debug)]
+        struct M<T: Debug + Send + Sync + 'static>(PhantomData<T>);
+
+        #[derive(Component, Debug)]
+        struct E(#[allow(dead_code)] [u8; 0]);
+
+        #[derive(Component, Debug)]
+        struct W(#[allow(dead_code)] C);
+
+        #[derive(Component, Debug)]
+        struct G<T: Debug + Send + Sync + 'static>(#[allow(dead_code)] T);
+
+        #[derive(Component, Debug)]
+        struct N(#[allow(dead_code)] usize);
+
+        fn assert_storage<C: Component<Storage = S>, S>() {}
+        assert_storage::<M<u8>, TagStore<M<u8>>>();
+        assert_storage::<E, TagStore<E>>();
+        assert_storage::<W, TagStore<W>>();
+        assert_storage::<G<()>, DefaultStore<G<()>>>();
+        assert_storage::<N, DefaultStore<N>>();
+    }
+
+    #[test]
+    #[should_panic(expected = "is not a `things::store::DefaultStore")]
+    fn test_store_mut_mismatched_store() {
+        #[derive(Component, Debug)]
+        struct E(#[allow(dead_code)] usize);
+
+        let mut stores = ComponentStores::default();
+        stores.insert(TypeId::of::<E>(), Box::new(SparseStore::<E>::new()));
+
+        store_mut::<E>(&mut stores, 0);
     }
 
     #[test]
     fn test_component_collection() {
-        #[derive(Debug)]
+        #[derive(Component, Debug)]
         struct C;
 
-        let _: Box<ComponentCollection> = Box::new((C, C));
+        let _: Box<dyn ComponentCollection> = Box::new((C, C));
     }
 
     #[test]
     fn test_component_collection_heterogeneous() {
-        #[derive(Debug)]
+        #[derive(Component, Debug)]
         struct C;
 
-        #[derive(Debug)]
+        #[derive(Component, Debug)]
+        struct D(#[allow(dead_code)] usize);
+
+        let _: Box<dyn ComponentCollection> = Box::new((C, D(1)));
+    }
+
+    #[test]
+    fn test_store_result_single() {
+        #[derive(Component, Debug)]
+        struct C;
+
+        let mut stores = ComponentStores::default();
+        assert_eq!(
+            (C,).store(&mut stores, 0, 0),
+            StoreResult {
+                position: 0,
+                len: 1
+            }
+        );
+        assert_eq!(
+            (C,).store(&mut stores, 1, 0),
+            StoreResult {
+                position: 1,
+                len: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_store_result_heterogeneous() {
+        #[derive(Component, Debug)]
+        struct C;
+
+        #[derive(Component, Debug)]
+        struct D(#[allow(dead_code)] usize);
+
+        let mut stores = ComponentStores::default();
+        let result = (C, D(1), D(2)).store(&mut stores, 3, 0);
+
+        assert_eq!(
+            result,
+            StoreResult {
+                position: 3,
+                len: 2
+            }
+        );
+        assert_eq!(<(C, D, D)>::rows(), 2);
+    }
+
+    #[test]
+    fn test_opaque_debug() {
+        struct Handle(u32);
+
+        #[derive(Component, Debug)]
+        struct Texture(Opaque<Handle>);
+
+        let texture = Texture(Opaque(Handle(7)));
+        assert_eq!(
+            format!("{:?}", texture),
+            format!("Texture(Opaque<{}>)", any::type_name::<Handle>())
+        );
+
+        let Handle(id) = texture.0.into_inner();
+        assert_eq!(id, 7);
+    }
+
+    #[test]
+    fn test_store_result_homogeneous() {
+        #[derive(Component, Debug)]
+        struct C;
+
+        let mut stores = ComponentStores::default();
+        assert_eq!(
+            (C, C, C).store(&mut stores, 0, 0),
+            StoreResult {
+                position: 0,
+                len: 3
+            }
+        );
+        assert_eq!(
+            (C, C).store(&mut stores, 3, 0),
+            StoreResult {
+                position: 3,
+                len: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_store_batch() {
+        #[derive(Component, Debug, PartialEq)]
+        struct C(usize);
+
+        #[derive(Component, Debug, PartialEq)]
         struct D(usize);
 
-        let _: Box<ComponentCollection> = Box::new((C, D(1)));
+        let mut stores = ComponentStores::default();
+        let batch = (0..2).map(|i| (C(i), D(i), C(i + 10)));
+        let mut cursors = vec![3, 0].into_iter();
+        let results =
+            ComponentCollection::store_batch(batch, &mut stores, || cursors.next().unwrap(), 0);
+
+        assert_eq!(
+            results,
+            vec![
+                StoreResult {
+                    position: 3,
+                    len: 2
+                },
+                StoreResult {
+                    position: 0,
+                    len: 2
+                },
+            ]
+        );
+
+        let c = stores[&TypeId::of::<C>()].as_store::<C>().unwrap();
+        let d = stores[&TypeId::of::<D>()].as_store::<D>().unwrap();
+        assert_eq!(
+            c.as_slice(),
+            &[Some(C(1)), Some(C(11)), None, Some(C(0)), Some(C(10))]
+        );
+        assert_eq!(d.as_slice(), &[Some(D(1)), None, None, Some(D(0))]);
     }
 }
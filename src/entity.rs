@@ -1,10 +1,209 @@
+use crate::{Component, Things};
+use core::fmt;
 use generational_arena::Index;
+#[cfg(feature = "std")]
+use std::error;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Entity(Index);
 
+impl Entity {
+    /// Returns the generational index backing this entity handle.
+    pub(crate) fn index(self) -> Index {
+        self.0
+    }
+}
+
 impl From<Index> for Entity {
     fn from(index: Index) -> Self {
         Entity(index)
     }
 }
+
+/// `Relation` is implemented by components that refer to another entity, such
+/// as a parent or a target, to have them removed once that entity is
+/// despawned, see [`Things::register_relation`].
+///
+/// ```
+/// use things::{Component, Entity, Relation, Things};
+///
+/// #[derive(Component, Debug)]
+/// struct Target(Entity);
+///
+/// impl Relation for Target {
+///     fn target(&self) -> Entity {
+///         self.0
+///     }
+///
+///     fn set_target(&mut self, target: Entity) {
+///         self.0 = target;
+///     }
+/// }
+///
+/// let mut ecs = Things::new();
+/// ecs.register_relation::<Target>();
+///
+/// let enemy = ecs.spawn().build();
+/// let turret = ecs.create_entity((Target(enemy),));
+///
+/// ecs.despawn(enemy);
+/// assert!(!ecs.has_component::<Target>(turret));
+/// ```
+pub trait Relation: Component {
+    /// Returns the entity the component refers to.
+    fn target(&self) -> Entity;
+
+    /// Makes the component refer to another entity, such as to the new handle
+    /// of its target once the entities of another world are merged, see
+    /// [`Things::merge`].
+    fn set_target(&mut self, target: Entity);
+}
+
+/// `EntityRef` is a read-only view of a single live entity, returned by
+/// [`Things::entity`].
+///
+/// The view borrows the world, so the entity stays alive for as long as the
+/// view exists.
+#[derive(Clone, Copy)]
+pub struct EntityRef<'a> {
+    things: &'a Things,
+    entity: Entity,
+}
+
+impl<'a> EntityRef<'a> {
+    pub(crate) fn new(things: &'a Things, entity: Entity) -> Self {
+        EntityRef { things, entity }
+    }
+
+    /// Returns the handle of the entity.
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns a reference to a component of the entity, see [`Things::get`].
+    pub fn get<C: Component>(&self) -> Option<&'a C> {
+        self.things.get(self.entity)
+    }
+
+    /// Returns `true` if the entity holds a component of the given type.
+    pub fn has<C: Component>(&self) -> bool {
+        self.things.has_component::<C>(self.entity)
+    }
+}
+
+/// `EntityMut` is a mutable view of a single live entity, returned by
+/// [`Things::entity_mut`], which groups the operations on that entity:
+///
+/// ```
+/// # use things::{Component, Things};
+/// # #[derive(Component, Debug, PartialEq)]
+/// # struct Position(u32);
+/// # #[derive(Component, Debug, PartialEq)]
+/// # struct Health(u8);
+/// let mut ecs = Things::new();
+/// let entity = ecs.create_entity((Position(0),));
+///
+/// let mut player = ecs.entity_mut(entity).unwrap();
+/// player.insert(Health(100)).get_mut::<Position>().unwrap().0 += 1;
+/// assert_eq!(player.remove::<Health>(), Some(Health(100)));
+/// player.despawn();
+///
+/// assert!(!ecs.is_alive(entity));
+/// ```
+///
+/// The view borrows the world mutably, so the entity can only be despawned by
+/// consuming the view.
+pub struct EntityMut<'a> {
+    things: &'a mut Things,
+    entity: Entity,
+}
+
+impl<'a> EntityMut<'a> {
+    pub(crate) fn new(things: &'a mut Things, entity: Entity) -> Self {
+        EntityMut { things, entity }
+    }
+
+    /// Returns the handle of the entity.
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns a reference to a component of the entity, see [`Things::get`].
+    pub fn get<C: Component>(&self) -> Option<&C> {
+        self.things.get(self.entity)
+    }
+
+    /// Returns a mutable reference to a component of the entity, see
+    /// [`Things::get_mut`].
+    pub fn get_mut<C: Component>(&mut self) -> Option<&mut C> {
+        self.things.get_mut(self.entity)
+    }
+
+    /// Returns `true` if the entity holds a component of the given type.
+    pub fn has<C: Component>(&self) -> bool {
+        self.things.has_component::<C>(self.entity)
+    }
+
+    /// Adds a component to the entity, see [`Things::add_component`], and
+    /// returns the view to allow chaining further operations.
+    pub fn insert<C: Component>(&mut self, component: C) -> &mut Self {
+        self.things.add_component(self.entity, component);
+        self
+    }
+
+    /// Removes a component from the entity, and returns it, see
+    /// [`Things::remove_component`].
+    pub fn remove<C: Component>(&mut self) -> Option<C> {
+        self.things.remove_component(self.entity)
+    }
+
+    /// Despawns the entity, and drops all of its components.
+    pub fn despawn(self) {
+        self.things.despawn(self.entity);
+    }
+}
+
+/// The error returned by [`Things::try_create_entity`].
+///
+/// [`Things::try_create_entity`]: crate::Things::try_create_entity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpawnError {
+    /// The generation counter of the entities wrapped around, so a new entity
+    /// could be mistaken for one that was despawned before.
+    ///
+    /// Generations are 64-bit and increase by one for every despawned entity,
+    /// so this takes 2^64 despawns to happen.
+    GenerationOverflow,
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SpawnError::GenerationOverflow => "entity generations overflowed",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for SpawnError {}
+
+/// The error returned by methods that require a live entity, such as
+/// [`Things::try_get`].
+///
+/// [`Things::try_get`]: crate::Things::try_get
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntityError {
+    /// The entity was despawned, so its handle is stale.
+    Dead(Entity),
+}
+
+impl fmt::Display for EntityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntityError::Dead(entity) => write!(f, "entity {:?} is not alive", entity),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for EntityError {}
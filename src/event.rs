@@ -0,0 +1,165 @@
+use crate::{system::{Data, QueryElement},
+            HashMap, Things};
+use alloc::{boxed::Box, vec::Vec};
+use core::any::TypeId;
+use downcast_rs::{impl_downcast, Downcast};
+
+/// `Events` stores the events sent between systems, one double-buffered queue
+/// for each event type.
+///
+/// Newly sent events are appended to the current frame. Once per frame, the
+/// current frame is completed, which makes its events readable, stamped with
+/// the tick at which it was completed. The events of the two most recently
+/// completed frames are kept, so a system that skipped a frame still reads the
+/// events of the frame before.
+#[derive(Default)]
+pub(crate) struct Events(HashMap<TypeId, Box<dyn EventQueue>>);
+
+impl Events {
+    pub(crate) fn send<E: Send + Sync + 'static>(&mut self, event: E) {
+        self.0
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Buffers::<E>::default()))
+            .downcast_mut::<Buffers<E>>()
+            .unwrap()
+            .events
+            .push(event);
+    }
+
+    /// Returns the events of the completed frames that were stamped after the
+    /// given tick, oldest first.
+    pub(crate) fn read<E: Send + Sync + 'static>(&self, last_run: usize) -> &[E] {
+        self.0
+            .get(&TypeId::of::<E>())
+            .and_then(|queue| queue.downcast_ref::<Buffers<E>>())
+            .map_or(&[], |buffers| buffers.read(last_run))
+    }
+
+    /// Completes the current frame of all event types, stamping it with the
+    /// given tick.
+    pub(crate) fn update(&mut self, tick: usize) {
+        for queue in self.0.values_mut() {
+            queue.complete(tick);
+        }
+    }
+}
+
+/// `EventQueue` is the type-erased counterpart of the buffers of a single
+/// event type.
+trait EventQueue: Downcast + Send + Sync {
+    fn complete(&mut self, tick: usize);
+}
+impl_downcast!(EventQueue);
+
+/// `Frame` is the tick at which a frame was completed, and the number of events
+/// sent during it.
+#[derive(Clone, Copy, Default)]
+struct Frame {
+    tick: usize,
+    len: usize,
+}
+
+struct Buffers<E> {
+    /// events holds the events of the older and the newer completed frame,
+    /// followed by those of the current frame.
+    events: Vec<E>,
+    frames: [Frame; 2],
+}
+
+impl<E> Default for Buffers<E> {
+    fn default() -> Self {
+        Buffers {
+            events: Vec::new(),
+            frames: [Frame::default(); 2],
+        }
+    }
+}
+
+impl<E> Buffers<E> {
+    fn read(&self, last_run: usize) -> &[E] {
+        let [older, newer] = self.frames;
+        let start = if newer.tick <= last_run {
+            older.len + newer.len
+        } else if older.tick <= last_run {
+            older.len
+        } else {
+            0
+        };
+
+        &self.events[start..older.len + newer.len]
+    }
+}
+
+impl<E: Send + Sync + 'static> EventQueue for Buffers<E> {
+    fn complete(&mut self, tick: usize) {
+        let [older, newer] = self.frames;
+        self.events.drain(..older.len);
+
+        let len = self.events.len() - newer.len;
+        self.frames = [newer, Frame { tick, len }];
+    }
+}
+
+/// `EventReader` requests read-only access to the events of a single type
+/// within a `Query`.
+///
+/// The events of the completed frames that the system has not read yet are
+/// yielded as a slice, the same slice for every row of the query. A system
+/// running once per frame reads the events sent during the previous frame,
+/// and reads each of them once, no matter how often it runs. Events sent
+/// during the current frame only become readable after
+/// [`Things::update_events`] is called.
+///
+/// Events are kept for two frames, so a system that skipped a frame reads the
+/// events of both frames it missed, while older events are dropped. Closures
+/// run through [`Things::run`], and ad-hoc queries, don't keep track of what
+/// they read, and read the events of both kept frames.
+pub struct EventReader<E: 'static>(E);
+
+impl<'a, E: Send + Sync + 'static> QueryElement<'a> for EventReader<E> {
+    type Item = &'a [E];
+    type Kind = Data;
+    type Slots = &'a [E];
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        Self::slots_since(things, 0)
+    }
+
+    fn slots_since(things: &'a Things, last_run: usize) -> Self::Slots {
+        things.events.read::<E>(last_run)
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        Some(*slots)
+    }
+
+    fn skip(_slots: &mut Self::Slots, _rows: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_double_buffered() {
+        let mut events = Events::default();
+        events.send(1usize);
+        assert!(events.read::<usize>(0).is_empty());
+
+        events.update(1);
+        events.send(2usize);
+        assert_eq!(events.read::<usize>(0), &[1]);
+
+        events.update(2);
+        assert_eq!(events.read::<usize>(0), &[1, 2]);
+        assert_eq!(events.read::<usize>(1), &[2]);
+        assert!(events.read::<usize>(2).is_empty());
+
+        events.update(3);
+        assert_eq!(events.read::<usize>(0), &[2]);
+
+        events.update(4);
+        assert!(events.read::<usize>(0).is_empty());
+        assert!(events.read::<u8>(0).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{relation::Relations, store::ComponentStores, Component, Entity, HashMap};
+use alloc::boxed::Box;
+use core::{any::{Any, TypeId},
+           ops::Range};
+
+/// A hook, with the component type erased, so hooks of all component types
+/// can be kept in a single map.
+type Hook = Box<dyn FnMut(Entity, &dyn Any) + Send + Sync>;
+
+/// `Hooks` stores the functions that are called when a component is added to,
+/// or removed from an entity, one of each for every component type.
+#[derive(Default)]
+pub(crate) struct Hooks {
+    on_add: HashMap<TypeId, Hook>,
+    on_remove: HashMap<TypeId, Hook>,
+
+    /// relations indexes the components of the registered relation types,
+    /// which is updated whenever the hooks are called.
+    pub(crate) relations: Relations,
+}
+
+impl Hooks {
+    pub(crate) fn set_on_add<C, F>(&mut self, hook: F)
+    where
+        C: Component,
+        F: FnMut(Entity, &C) + Send + Sync + 'static,
+    {
+        self.on_add.insert(TypeId::of::<C>(), erase(hook));
+    }
+
+    pub(crate) fn set_on_remove<C, F>(&mut self, hook: F)
+    where
+        C: Component,
+        F: FnMut(Entity, &C) + Send + Sync + 'static,
+    {
+        self.on_remove.insert(TypeId::of::<C>(), erase(hook));
+    }
+
+    /// Calls the hook of the component type for a single added component.
+    pub(crate) fn added<C: Component>(&mut self, entity: Entity, component: &C) {
+        self.relations.added(entity, TypeId::of::<C>(), component);
+        call(&mut self.on_add, entity, component)
+    }
+
+    /// Calls the hook of the component type for a single removed component.
+    pub(crate) fn removed<C: Component>(&mut self, entity: Entity, component: &C) {
+        self.relations.removed(entity, TypeId::of::<C>(), component);
+        call(&mut self.on_remove, entity, component)
+    }
+
+    /// Calls the hooks for all components stored in the given rows of the
+    /// entity, after they were added.
+    pub(crate) fn added_rows(
+        &mut self,
+        stores: &ComponentStores,
+        entity: Entity,
+        rows: Range<usize>,
+    ) {
+        self.relations.added_rows(stores, entity, rows.clone());
+        call_rows(&mut self.on_add, stores, entity, rows)
+    }
+
+    /// Calls the hooks for all components stored in the given rows of the
+    /// entity, before they are removed.
+    pub(crate) fn removed_rows(
+        &mut self,
+        stores: &ComponentStores,
+        entity: Entity,
+        rows: Range<usize>,
+    ) {
+        self.relations.removed_rows(stores, entity, rows.clone());
+        call_rows(&mut self.on_remove, stores, entity, rows)
+    }
+
+    /// Returns `true` if any hook is called when components are removed.
+    pub(crate) fn has_removed(&self) -> bool {
+        !self.on_remove.is_empty()
+    }
+}
+
+fn erase<C, F>(mut hook: F) -> Hook
+where
+    C: Component,
+    F: FnMut(Entity, &C) + Send + Sync + 'static,
+{
+    Box::new(move |entity, component| hook(entity, component.downcast_ref::<C>().unwrap()))
+}
+
+fn call<C: Component>(hooks: &mut HashMap<TypeId, Hook>, entity: Entity, component: &C) {
+    if let Some(hook) = hooks.get_mut(&TypeId::of::<C>()) {
+        hook(entity, component);
+    }
+}
+
+fn call_rows(
+    hooks: &mut HashMap<TypeId, Hook>,
+    stores: &ComponentStores,
+    entity: Entity,
+    rows: Range<usize>,
+) {
+    // The hooks are called in the order of the stores, as the order of the
+    // hooks map depends on the type IDs.
+    for (id, store) in stores.iter() {
+        let hook = match hooks.get_mut(id) {
+            Some(hook) => hook,
+            None => continue,
+        };
+
+        for row in rows.clone() {
+            if let Some(component) = store.any(row) {
+                hook(entity, component);
+            }
+        }
+    }
+}
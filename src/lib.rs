@@ -12,31 +12,83 @@
 //! and often combined with data-oriented design techniques.
 //!
 //! see: https://en.wikipedia.org/wiki/Entity–component–system
+//!
+//! The library depends on `std` through its default `std` feature. Disabling
+//! the default features builds it for `no_std` environments that provide
+//! `alloc`, without the `parallel` and `serde` features, which require `std`.
+
+#![cfg_attr(feature = "doc", doc = include_str!("../README.md"))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-#![cfg_attr(feature = "doc", feature(external_doc))]
-#![feature(vec_resize_default)]
+// Allows the derive macros to refer to `::things` from within this crate.
+extern crate self as things;
 
+extern crate alloc;
+
+mod archetype;
 mod borrow;
+mod builder;
+mod command;
 mod component;
 mod entity;
+mod event;
+mod hook;
+mod relation;
+mod resource;
+#[cfg(feature = "serde")]
+mod save;
+mod schedule;
 mod store;
 mod system;
 
-pub use crate::{borrow::BorrowError,
-                component::Component,
-                entity::Entity,
-                store::Store,
-                system::{Query, Read, System, Write}};
-use crate::{borrow::{RegisterBorrow, RuntimeBorrow},
-            component::ComponentCollection,
-            store::ComponentStore};
+pub use things_derive::{Bundle, Component};
+
+#[doc(hidden)]
+pub use crate::component::{SelectStorage, ZeroSized};
+
+use crate::{archetype::Archetypes,
+            borrow::{RegisterBorrow, RuntimeBorrow},
+            command::Queues,
+            component::{store_mut, ComponentCollection, StoreResult},
+            event::Events,
+            hook::Hooks,
+            resource::Resources,
+            store::ComponentStores,
+            system::{Data, QueryElement}};
+pub use crate::{borrow::{BorrowConflict, BorrowError, BorrowKind, BorrowToken},
+                builder::{EntityBuilder, ThingsBuilder},
+                command::{Commands, Despawns},
+                component::{Bundle, Component, ComponentsBundle, Opaque},
+                entity::{Entity, EntityError, EntityMut, EntityRef, Relation, SpawnError},
+                event::EventReader,
+                resource::{Res, ResGuard, ResMut},
+                schedule::Schedule,
+                store::{DefaultStore, SparseStore, Store, TagStore, Ticks},
+                system::{Added, AnySystem, Changed, IntoSystem, Or, Query, QueryChunks,
+                         QueryFilter, QueryIter, QuerySet, Read, ReadOnly, SingleError,
+                         StatefulSystem, System, Tick, With, Without, World, Write}};
+use alloc::{vec, vec::Vec};
+use core::{any::TypeId, fmt, ops::Range};
 use generational_arena::Arena;
-use parking_lot::Mutex;
-use rustc_hash::FxHashMap as HashMap;
-use std::any::TypeId;
+
+/// Without `std`, the hash map and mutex of the standard library are replaced
+/// by those of the `hashbrown` and `spin` crates, which only depend on `alloc`.
+#[cfg(feature = "std")]
+pub(crate) use parking_lot::Mutex;
+#[cfg(feature = "std")]
+pub(crate) use rustc_hash::FxHashMap as HashMap;
+#[cfg(not(feature = "std"))]
+pub(crate) use spin::Mutex;
+#[cfg(not(feature = "std"))]
+pub(crate) type HashMap<K, V> =
+    hashbrown::HashMap<K, V, core::hash::BuildHasherDefault<rustc_hash::FxHasher>>;
 
 /// Things is the top-level object used to interact with an instance of the ECS
 /// functionality.
+///
+/// `Things` is `Send` and `Sync`, so it can be moved to, or shared with other
+/// threads. Components, resources and events are required to be `Send` and
+/// `Sync` for this reason.
 pub struct Things {
     /// entities are stored in a generational index, using the
     /// `generational-arena` crate.
@@ -44,8 +96,9 @@ pub struct Things {
 
     /// component_stores is a map of stores, one store for each component type.
     /// The type ID of each component is used as the key of the map, to allow
-    /// linking the components back to the entity.
-    component_stores: HashMap<TypeId, Box<ComponentStore>>,
+    /// linking the components back to the entity. The stores are kept in the
+    /// order in which they were created, see `ComponentStores`.
+    component_stores: ComponentStores,
 
     /// component_cursor keeps track of the next position in the stores where
     /// the components of the next added entity should be stored. This is used
@@ -53,6 +106,17 @@ pub struct Things {
     /// which components belong to a single `Entity`.
     component_cursor: usize,
 
+    /// free_rows holds the ranges of rows vacated by despawned entities, or by
+    /// entities that shrank, as a position and a length. New entities are
+    /// stored in a vacated range that fits them before the component cursor
+    /// is advanced, which keeps the stores from growing while entities are
+    /// spawned and despawned.
+    free_rows: Vec<(usize, usize)>,
+
+    /// archetypes groups the entities by the component types they hold, which
+    /// allows queries to skip the rows of entities that can't match them.
+    archetypes: Archetypes,
+
     /// entity_component_references is a map of [`Component`] locations, as they
     /// relate to an [`Entity`]. This map allows finding all components
     /// belonging to a single entity.
@@ -69,7 +133,39 @@ pub struct Things {
     /// entity.
     entity_component_references: HashMap<Entity, (usize, usize)>,
 
-    runtime_borrow: Mutex<RuntimeBorrow>,
+    /// generation is the highest generation of any entity created so far. The
+    /// generations of the arena only ever increase, so a lower generation
+    /// means they overflowed.
+    generation: u64,
+
+    /// hooks are called when components are added to, or removed from an
+    /// entity, and keep the index of the registered [`Relation`] types.
+    hooks: Hooks,
+
+    /// resources holds global data that does not belong to any entity.
+    resources: Resources,
+
+    /// events holds the events sent between systems, for the current frame and
+    /// the two frames before.
+    events: Events,
+
+    /// change_tick is the current tick, at which components are recorded as
+    /// added or changed. It is advanced every time a system is executed.
+    change_tick: usize,
+
+    /// system_ticks records the tick at which each system last ran, keyed by
+    /// the type ID of the system.
+    system_ticks: HashMap<TypeId, usize>,
+
+    /// registry holds the (de)serialization functions of the component types
+    /// that are saved along with the world.
+    #[cfg(feature = "serde")]
+    registry: save::Registry,
+
+    /// queues buffer the structural changes requested by systems, and the
+    /// entities marked to be despawned through a shared reference, which are
+    /// applied after each system has finished running.
+    queues: Queues,
 }
 
 impl Default for Things {
@@ -78,42 +174,1941 @@ impl Default for Things {
     }
 }
 
+/// Lists every live entity along with the components it holds, to help with
+/// diagnosing why an entity does or does not match a query.
+///
+/// Components of the same entity are listed in no particular order.
+impl fmt::Debug for Things {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Things")
+            .field("entities", &Entities(self))
+            .finish()
+    }
+}
+
+struct Entities<'a>(&'a Things);
+
+impl fmt::Debug for Entities<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Entities(things) = *self;
+
+        f.debug_map()
+            .entries(
+                things
+                    .entities()
+                    .map(|entity| (entity, Components(things, entity))),
+            )
+            .finish()
+    }
+}
+
+struct Components<'a>(&'a Things, Entity);
+
+impl fmt::Debug for Components<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Components(things, entity) = *self;
+        let (position, len) = things.references(entity).unwrap_or_default();
+
+        let components = (position..position + len).flat_map(|row| {
+            things
+                .component_stores
+                .values()
+                .filter_map(move |store| store.debug(row))
+        });
+
+        f.debug_list().entries(components).finish()
+    }
+}
+
 impl Things {
     pub fn new() -> Self {
         Things {
             entities: Arena::new(),
-            component_stores: HashMap::default(),
+            component_stores: ComponentStores::default(),
             component_cursor: 0,
+            free_rows: Vec::new(),
+            archetypes: Archetypes::default(),
             entity_component_references: HashMap::default(),
-            runtime_borrow: Mutex::new(RuntimeBorrow::new()),
+            generation: 0,
+            hooks: Hooks::default(),
+            resources: Resources::default(),
+            events: Events::default(),
+            change_tick: 1,
+            system_ticks: HashMap::default(),
+            #[cfg(feature = "serde")]
+            registry: save::Registry::default(),
+            queues: Queues::default(),
+        }
+    }
+
+    /// Returns a [`ThingsBuilder`] to configure a new instance before any
+    /// entity is created.
+    pub fn builder() -> ThingsBuilder {
+        ThingsBuilder::new()
+    }
+
+    /// Creates a new instance with room for `capacity` entities, see
+    /// [`Things::reserve`].
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut things = Self::new();
+        things.reserve(capacity);
+        things
+    }
+
+    /// Reserves capacity for at least `additional` more entities, and for as
+    /// many components in each of the existing stores.
+    ///
+    /// Stores are created when the first component of their type is stored,
+    /// so only the entity slots are reserved for stores that do not exist yet.
+    pub fn reserve(&mut self, additional: usize) {
+        self.entities.reserve(additional);
+        self.entity_component_references.reserve(additional);
+
+        for store in self.component_stores.values_mut() {
+            store.reserve(additional);
+        }
+    }
+
+    /// Releases the capacity held by the stores beyond what their components
+    /// and slots need, for example after despawning many entities.
+    ///
+    /// This only gives up unused capacity, the rows vacated by despawned
+    /// entities are kept, see [`Things::compact`] to remove them as well. The
+    /// entity slots themselves are never released, as the generational arena
+    /// that hands out entities can't shrink without invalidating them.
+    pub fn shrink_to_fit(&mut self) {
+        self.entity_component_references.shrink_to_fit();
+        self.free_rows.shrink_to_fit();
+        self.archetypes.shrink_to_fit();
+
+        for store in self.component_stores.values_mut() {
+            store.shrink_to_fit();
+        }
+    }
+
+    /// Moves the components of all entities towards the start of the stores,
+    /// removing the rows vacated by despawned entities, and then releases the
+    /// unused capacity, see [`Things::shrink_to_fit`].
+    ///
+    /// Entities keep their handles and the ticks of their components, but
+    /// unlike [`Things::shrink_to_fit`], every component after the first
+    /// vacated row is moved, so this is best done once, after a large number
+    /// of entities has been despawned.
+    pub fn compact(&mut self) {
+        let mut references = self
+            .entity_component_references
+            .iter()
+            .map(|(&entity, &references)| (entity, references))
+            .collect::<Vec<_>>();
+        references.sort_unstable_by_key(|&(_, (position, _))| position);
+
+        // Rows are only ever moved towards the start of the stores, into rows
+        // that were vacated, or that were already moved themselves.
+        let mut cursor = 0;
+        for (entity, (position, len)) in references {
+            if position != cursor {
+                for store in self.component_stores.values_mut() {
+                    for offset in 0..len {
+                        store.relocate(position + offset, cursor + offset);
+                    }
+                }
+
+                self.entity_component_references
+                    .insert(entity, (cursor, len));
+            }
+
+            cursor += len;
+        }
+
+        self.component_cursor = cursor;
+        self.free_rows.clear();
+        for store in self.component_stores.values_mut() {
+            store.truncate(cursor);
         }
+
+        self.archetypes.invalidate();
+        self.refresh_archetypes();
+        self.shrink_to_fit();
     }
 
-    pub fn execute_system<'a, S: System<'a>>(&'a mut self) -> Result<(), BorrowError>
+    /// Executes a single system.
+    ///
+    /// The borrows of the system are validated first. If they are invalid, the
+    /// error is returned without running the system.
+    ///
+    /// The system only borrows `self` for the duration of the call, so multiple
+    /// systems can be executed in a row, after which `self` can be used again:
+    ///
+    /// ```
+    /// # use things::{Component, Query, Read, System, Things, Write};
+    /// # #[derive(Component, Debug)]
+    /// # struct Velocity(u8);
+    /// # #[derive(Component, Debug, PartialEq)]
+    /// # struct Position(u32);
+    /// struct Movement;
+    /// impl<'a> System<'a> for Movement {
+    ///     type Query = (Read<Velocity>, Write<Position>);
+    ///
+    ///     fn update(components: <Self::Query as Query<'a>>::Iter) {
+    ///         for (velocity, position) in components {
+    ///             position.0 += u32::from(velocity.0);
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut ecs = Things::new();
+    /// let entity = ecs.create_entity((Velocity(1), Position(10)));
+    ///
+    /// for _ in 0..2 {
+    ///     ecs.execute_system::<Movement>().unwrap();
+    /// }
+    ///
+    /// assert_eq!(ecs.get::<Position>(entity), Some(&Position(12)));
+    /// ```
+    #[must_use = "the system is not run if its borrows are invalid"]
+    pub fn execute_system<S>(&mut self) -> Result<(), BorrowError>
     where
-        S::Query: Query<'a>,
-        <S::Query as Query<'a>>::Borrow: RegisterBorrow,
+        S: for<'a> System<'a> + 'static,
+        for<'a> <<S as System<'a>>::Query as Query<'a>>::Borrow: RegisterBorrow,
     {
-        self.borrow_and_validate::<<S::Query as Query<'a>>::Borrow>()?;
+        let token = borrow::validate::<<<S as System>::Query as Query>::Borrow>()?;
+        self.refresh_archetypes();
 
-        let query = S::Query::iter(&self.component_stores);
+        let id = TypeId::of::<S>();
+        let query = <S as System>::Query::iter_since(self, self.last_run(id), token);
 
         S::update(query);
+        self.system_ticks.insert(id, self.change_tick);
+        self.advance_tick();
+        self.apply_commands();
+        self.apply_despawns();
+        Ok(())
+    }
+
+    /// Validates the borrows of a system, without executing it.
+    ///
+    /// Returns the same error [`Things::execute_system`] would, which allows
+    /// checking all systems of a frame before any of them runs. The world is
+    /// left untouched.
+    pub fn can_run<S>(&self) -> Result<(), BorrowError>
+    where
+        S: for<'a> System<'a> + 'static,
+        for<'a> <<S as System<'a>>::Query as Query<'a>>::Borrow: RegisterBorrow,
+    {
+        borrow::validate::<<<S as System>::Query as Query>::Borrow>()?;
+        Ok(())
+    }
+
+    /// Executes a single system that keeps state between runs.
+    ///
+    /// The system is updated through the given reference, and is otherwise
+    /// executed in the same way as [`Things::execute_system`]. Runs of systems
+    /// of the same type share the tick at which the system last ran.
+    #[must_use = "the system is not run if its borrows are invalid"]
+    pub fn execute_stateful_system<S>(&mut self, system: &mut S) -> Result<(), BorrowError>
+    where
+        S: for<'a> StatefulSystem<'a> + 'static,
+        for<'a> <<S as StatefulSystem<'a>>::Query as Query<'a>>::Borrow: RegisterBorrow,
+    {
+        let token = borrow::validate::<<<S as StatefulSystem>::Query as Query>::Borrow>()?;
+        self.refresh_archetypes();
+
+        let id = TypeId::of::<S>();
+        let query = <S as StatefulSystem>::Query::iter_since(self, self.last_run(id), token);
+
+        system.update(query);
+        self.system_ticks.insert(id, self.change_tick);
+        self.advance_tick();
+        self.apply_commands();
+        self.apply_despawns();
+        Ok(())
+    }
+
+    /// Executes multiple systems, running systems whose borrows do not conflict
+    /// at the same time.
+    ///
+    /// The systems are grouped into stages. A system joins the current stage if
+    /// its borrows are disjoint from those of the systems already in it, and
+    /// starts a new stage otherwise, so conflicting systems always run in the
+    /// given order. With the `parallel` feature enabled, the systems within a
+    /// stage are run in parallel using `rayon`. The commands and despawns
+    /// recorded by the systems of a stage are applied after the stage, in the
+    /// order in which the systems were given, either way.
+    ///
+    /// The borrows of all systems are validated before any system is run.
+    #[must_use = "no system is run if any of their borrows are invalid"]
+    pub fn execute_systems(&mut self, systems: &[&dyn AnySystem]) -> Result<(), BorrowError> {
+        let mut stages: Vec<Vec<(&dyn AnySystem, BorrowToken)>> = vec![Vec::new()];
+        let mut borrows = RuntimeBorrow::new();
+
+        for &system in systems {
+            let borrow = system.register_borrow()?;
+            let token = borrow.validate()?;
+
+            if let Err(borrow) = borrows.try_push(borrow) {
+                borrows.clear();
+                borrows.push(borrow);
+                stages.push(Vec::new());
+            }
+
+            stages.last_mut().unwrap().push((system, token));
+        }
+
+        self.refresh_archetypes();
+        for stage in stages {
+            let ids = stage
+                .iter()
+                .map(|(system, _)| system.system_id())
+                .collect::<Vec<_>>();
+
+            self.execute_stage(stage);
+            for id in ids {
+                self.system_ticks.insert(id, self.change_tick);
+            }
+
+            self.advance_tick();
+            self.apply_commands();
+            self.apply_despawns();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the current tick of the world.
+    ///
+    /// The tick starts at `1`, and is advanced after every system executed by
+    /// [`Things::execute_system`] or [`Things::execute_stateful_system`], and
+    /// after every stage of systems executed by [`Things::execute_systems`], so
+    /// it never decreases. Systems can read it through the [`Tick`] query
+    /// element.
+    pub fn tick(&self) -> u64 {
+        self.change_tick as u64
+    }
+
+    #[cfg(feature = "parallel")]
+    fn execute_stage(&mut self, stage: Vec<(&dyn AnySystem, BorrowToken)>) {
+        use rayon::prelude::*;
+
+        // Every system records its commands and despawns into a lane of its
+        // own, so they are applied in the order of the systems.
+        self.queues.open_lanes(stage.len());
+        let this = &*self;
+
+        // The borrows of the systems within a stage are disjoint, so no
+        // component is written by one thread while being accessed by another.
+        stage
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(lane, (system, token))| {
+                let last_run = this.last_run(system.system_id());
+                command::with_lane(Some(lane), || system.run(this, last_run, token));
+            });
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn execute_stage(&mut self, stage: Vec<(&dyn AnySystem, BorrowToken)>) {
+        for (system, token) in stage {
+            system.run(self, self.last_run(system.system_id()), token);
+        }
+    }
+
+    /// Returns the tick at which the system with the given type ID last ran, or
+    /// `0` if it never ran.
+    fn last_run(&self, id: TypeId) -> usize {
+        self.system_ticks.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Advances the current tick, so changes made from now on can be told apart
+    /// from the changes seen by the systems that already ran.
+    fn advance_tick(&mut self) {
+        self.change_tick += 1;
+
+        for store in self.component_stores.values_mut() {
+            store.set_tick(self.change_tick);
+        }
+    }
+
+    /// Applies all structural changes recorded in the [`Commands`] buffer.
+    ///
+    /// This is done automatically after running a system, but has to be called
+    /// manually when using [`Commands`] in an ad-hoc [`Things::query`].
+    pub fn apply_commands(&mut self) {
+        for command in self.queues.drain_commands() {
+            command(self);
+        }
+    }
+
+    /// Runs a function or closure as a system. See [`IntoSystem`] for details.
+    pub fn run<Q, S: IntoSystem<Q>>(&mut self, system: S) -> Result<(), BorrowError> {
+        system.run(self)
+    }
+
+    /// Runs a query against the stored components, without having to define a
+    /// [`System`], and returns an iterator over the matching rows.
+    ///
+    /// The borrows of the query are validated in the same way as they are for
+    /// systems. The returned iterator keeps `self` mutably borrowed for as long
+    /// as it is alive, which guarantees no other query can alias its
+    /// components during iteration.
+    pub fn query<'a, Q>(&'a mut self) -> Result<Q::Iter, BorrowError>
+    where
+        Q: Query<'a>,
+        Q::Borrow: RegisterBorrow,
+    {
+        let token = borrow::validate::<Q::Borrow>()?;
+        self.refresh_archetypes();
+
+        Ok(Q::iter(self, token))
+    }
+
+    /// Runs a query in the same way as [`Things::query`], and calls the closure
+    /// with the items of each matching row.
+    ///
+    /// This is the shortest way to apply a one-off transformation, without
+    /// defining a [`System`]:
+    ///
+    /// ```
+    /// # use things::{Component, Read, Things, Write};
+    /// # #[derive(Component, Debug)]
+    /// # struct Velocity(u8);
+    /// # #[derive(Component, Debug)]
+    /// # struct Position(u32);
+    /// let mut ecs = Things::new();
+    /// let entity = ecs.create_entity((Velocity(1), Position(10)));
+    ///
+    /// ecs.for_each::<(Read<Velocity>, Write<Position>), _>(|(velocity, position)| {
+    ///     position.0 += u32::from(velocity.0);
+    /// })
+    /// .unwrap();
+    ///
+    /// assert_eq!(ecs.get::<Position>(entity).unwrap().0, 11);
+    /// ```
+    ///
+    /// As with [`Things::query`], commands recorded by the closure are not
+    /// applied until [`Things::apply_commands`] is called.
+    pub fn for_each<'a, Q, F>(&'a mut self, f: F) -> Result<(), BorrowError>
+    where
+        Q: Query<'a>,
+        Q::Borrow: RegisterBorrow,
+        Q::Iter: Iterator,
+        F: FnMut(<Q::Iter as Iterator>::Item),
+    {
+        self.query::<Q>()?.for_each(f);
         Ok(())
     }
 
-    pub fn create_entity<CC: ComponentCollection>(&mut self, components: CC) {
-        let entity = Entity::from(self.entities.insert(()));
-        let result = components.store(&mut self.component_stores, self.component_cursor);
+    /// Calls the closure with a mutable reference to every component of the
+    /// given type, such as to decay all timers once per frame.
+    ///
+    /// This is the fastest way to update a single component type: the store is
+    /// looked up once, and walked directly, without the archetypes or the
+    /// other stores a query visits. As the world is borrowed exclusively, no
+    /// borrows have to be validated. Every component is recorded as changed.
+    ///
+    /// ```
+    /// # use things::{Component, Things};
+    /// #[derive(Component, Debug)]
+    /// struct Cooldown(u32);
+    ///
+    /// let mut ecs = Things::new();
+    /// let entity = ecs.create_entity((Cooldown(3),));
+    ///
+    /// ecs.write_all(|cooldown: &mut Cooldown| cooldown.0 = cooldown.0.saturating_sub(1));
+    /// assert_eq!(ecs.get::<Cooldown>(entity).unwrap().0, 2);
+    /// ```
+    pub fn write_all<C, F>(&mut self, f: F)
+    where
+        C: Component,
+        F: FnMut(&mut C),
+    {
+        let store = match self.component_stores.get_mut(&TypeId::of::<C>()) {
+            Some(store) => store.expect_mut_store::<C>(),
+            None => return,
+        };
+
+        // This is safe, because the store is exclusively borrowed through
+        // `&mut self`, and every position is visited only once.
+        unsafe { store.iter_mut() }.flatten().for_each(f);
+    }
+
+    /// Runs a query in the same way as [`Things::query`], and yields the
+    /// handle of the entity owning each matching row along with its items.
+    ///
+    /// This is the same as adding [`Entity`] as the first element of the query,
+    /// except the items of the query are kept together:
+    ///
+    /// ```
+    /// # use things::{Component, Read, Things, Write};
+    /// # #[derive(Component, Debug)]
+    /// # struct Velocity(u8);
+    /// # #[derive(Component, Debug)]
+    /// # struct Position(u32);
+    /// let mut ecs = Things::new();
+    /// let entity = ecs.create_entity((Velocity(1), Position(10)));
+    ///
+    /// let query = ecs.query_entities::<(Read<Velocity>, Write<Position>)>();
+    /// for (owner, (velocity, position)) in query.unwrap() {
+    ///     assert_eq!(owner, entity);
+    ///     position.0 += u32::from(velocity.0);
+    /// }
+    /// ```
+    pub fn query_entities<'a, Q>(&'a mut self) -> Result<QueryIter<'a, (Entity, Q)>, BorrowError>
+    where
+        Q: Query<'a> + QueryElement<'a, Kind = Data>,
+        Q::Borrow: RegisterBorrow,
+    {
+        let token = borrow::validate::<Q::Borrow>()?;
+        self.refresh_archetypes();
+
+        Ok(<(Entity, Q)>::iter(self, token))
+    }
+
+    /// Returns the number of rows matching a read-only query, such as
+    /// `(With<Enemy>, Without<Dead>)`, without handing out their items.
+    ///
+    /// A read-only query never conflicts with itself, or with any other reader,
+    /// so it is run through a shared reference, and never fails. Rows of
+    /// entities that can't match the query are skipped without visiting the
+    /// stores, which makes counting entities by the components they hold
+    /// cheap. To count the rows of a query that writes components, iterate it
+    /// through [`Things::query`] instead.
+    ///
+    /// An entity holding multiple components of the same type spans multiple
+    /// rows, so it can be counted more than once.
+    pub fn count<'a, Q>(&'a self) -> usize
+    where
+        Q: QueryElement<'a> + ReadOnly,
+    {
+        QueryIter::<Q>::new(self).count()
+    }
+
+    /// Creates a new entity from a collection of components, and returns the
+    /// [`Entity`] handle that can be used to reference it later on.
+    ///
+    /// The components are stored in the rows of a despawned entity if they fit,
+    /// so entities are not necessarily stored in the order they are created.
+    ///
+    /// A collection can hold multiple components of the same type. The
+    /// entity then spans multiple rows, where the `n`th row holds the `n`th
+    /// component of each type. Queries are resolved row by row, so an entity
+    /// with two `Weapon`s and a single `Owner` only matches
+    /// `(Read<Weapon>, Read<Owner>)` on its first row. Use [`Things::get_all`]
+    /// to reach all components of a single type of an entity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the generations of the entities overflow, see
+    /// [`Things::try_create_entity`].
+    pub fn create_entity<CC: ComponentCollection>(&mut self, components: CC) -> Entity {
+        self.try_create_entity(components)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new entity from a collection of components, or returns an
+    /// error if no entity can be created without being mistaken for another.
+    ///
+    /// See [`Things::create_entity`] for details. If an error is returned, the
+    /// components are dropped.
+    pub fn try_create_entity<CC: ComponentCollection>(
+        &mut self,
+        components: CC,
+    ) -> Result<Entity, SpawnError> {
+        let entity = self.alloc_entity()?;
+        let cursor = self
+            .take_free_rows(CC::rows())
+            .unwrap_or(self.component_cursor);
+
+        let result = components.store(&mut self.component_stores, cursor, self.change_tick);
+        self.place_entity(entity, result);
+
+        Ok(entity)
+    }
+
+    /// Returns an [`EntityBuilder`] to create a new entity by adding its
+    /// components one at a time, such as when some of them depend on
+    /// conditions:
+    ///
+    /// ```
+    /// # use things::{Component, Things};
+    /// # #[derive(Component, Debug)]
+    /// # struct Position(u32);
+    /// # #[derive(Component, Debug)]
+    /// # struct Weapon;
+    /// let mut ecs = Things::new();
+    /// let has_weapon = true;
+    ///
+    /// let mut builder = ecs.spawn().with(Position(0));
+    /// if has_weapon {
+    ///     builder = builder.with(Weapon);
+    /// }
+    ///
+    /// let entity = builder.build();
+    /// assert!(ecs.has_component::<Weapon>(entity));
+    /// ```
+    ///
+    /// The entity is created by [`EntityBuilder::build`], with the same layout
+    /// in the stores as if its components were passed to
+    /// [`Things::create_entity`] as a tuple, in the order they were added.
+    pub fn spawn(&mut self) -> EntityBuilder<'_> {
+        EntityBuilder::new(self)
+    }
 
-        self.component_cursor += result.len;
+    /// Records the rows at which the components of a new entity were stored,
+    /// and pads the other stores to keep them aligned.
+    pub(crate) fn place_entity(&mut self, entity: Entity, result: StoreResult) {
+        self.component_cursor = core::cmp::max(self.component_cursor, result.position + result.len);
         self.entity_component_references
             .insert(entity, (result.position, result.len));
+        self.update_archetype(result.position, result.len);
+
+        // Stores that did not receive a component for this entity are padded
+        // with `None`s, to keep all stores aligned to the component cursor.
+        for store in self.component_stores.values_mut() {
+            store.pad(self.component_cursor);
+        }
+
+        let rows = result.position..result.position + result.len;
+        self.hooks.added_rows(&self.component_stores, entity, rows);
+    }
+
+    /// Creates an entity for every collection of components in the batch, and
+    /// returns their handles in the same order.
+    ///
+    /// This is faster than calling [`Things::create_entity`] in a loop, as the
+    /// store of each component type is looked up once for the whole batch, and
+    /// the stores are only padded after all entities are created. The entities
+    /// are laid out the same as if they were created one at a time, reusing
+    /// the rows of despawned entities where they fit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the generations of the entities overflow, see
+    /// [`Things::try_create_entity`].
+    pub fn spawn_batch<CC, I>(&mut self, batch: I) -> Vec<Entity>
+    where
+        CC: ComponentCollection,
+        I: IntoIterator<Item = CC>,
+    {
+        let len = CC::rows();
+        let free_rows = &mut self.free_rows;
+        let component_cursor = &mut self.component_cursor;
+        let cursor = || {
+            take_free_rows(free_rows, len).unwrap_or_else(|| {
+                *component_cursor += len;
+                *component_cursor - len
+            })
+        };
+
+        let results = CC::store_batch(batch, &mut self.component_stores, cursor, self.change_tick);
+
+        for store in self.component_stores.values_mut() {
+            store.pad(self.component_cursor);
+        }
+
+        self.entities.reserve(results.len());
+        self.entity_component_references.reserve(results.len());
+
+        // Every collection of the batch holds the same component types, so the
+        // entities all share the same archetype.
+        let mut archetype = None;
+
+        results
+            .into_iter()
+            .map(|result| {
+                let entity = self.alloc_entity().unwrap_or_else(|err| panic!("{}", err));
+                self.entity_component_references
+                    .insert(entity, (result.position, result.len));
+
+                let rows = result.position..result.position + result.len;
+                let id = match archetype {
+                    Some(id) => id,
+                    None => *archetype.insert(self.archetype_of(rows.clone())),
+                };
+                self.archetypes.assign(rows.clone(), id);
+
+                self.hooks.added_rows(&self.component_stores, entity, rows);
+
+                entity
+            })
+            .collect()
+    }
+
+    /// Moves all entities of another world into this one, and returns the
+    /// handles of the moved entities, paired with their handles in the other
+    /// world.
+    ///
+    /// The entities get new handles, and their components are stored after
+    /// those of all other entities, in the same order as in the other world.
+    /// The components are recorded as added at the current tick, and the hooks
+    /// of this world are called for them, as if the entities were spawned.
+    /// Resources, events, hooks and systems of the other world are dropped.
+    ///
+    /// Components of the relation types registered in this world are made to
+    /// refer to the new handles of their targets, see
+    /// [`Things::register_relation`]. Those referring to an entity that is not
+    /// alive in the other world are dropped, without calling any hooks.
+    /// Handles stored in components of other types are not changed, so they
+    /// still refer to the entities of the other world.
+    ///
+    /// Components of the same type are always kept in the same type of store,
+    /// the [`Component::Storage`] of the type, so the stores of both worlds are
+    /// concatenated per component type. Stores are created for the types this
+    /// world has no store for yet, but the capacity reserved by the stores of
+    /// the other world is not carried over.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the generations of the entities overflow, see
+    /// [`Things::try_create_entity`].
+    pub fn merge(&mut self, mut other: Things) -> Vec<(Entity, Entity)> {
+        let offset = self.component_cursor;
+        self.component_cursor += other.component_cursor;
+
+        for (id, store) in other.component_stores.iter_mut() {
+            let tick = self.change_tick;
+            self.component_stores
+                .get_or_insert_with(*id, || {
+                    let mut empty = store.empty();
+                    empty.set_tick(tick);
+                    empty
+                })
+                .append(&mut **store, offset);
+        }
+
+        for store in self.component_stores.values_mut() {
+            store.pad(self.component_cursor);
+        }
+
+        let free_rows = other.free_rows.iter();
+        self.free_rows
+            .extend(free_rows.map(|&(position, len)| (offset + position, len)));
+
+        let entities = other
+            .entities()
+            .map(|entity| {
+                let merged = self.alloc_entity().unwrap_or_else(|err| panic!("{}", err));
+                (merged, entity)
+            })
+            .collect::<Vec<_>>();
+
+        // The relation components still hold the handles of the other world,
+        // which may equal those of unrelated entities of this world, so they
+        // are retargeted before they are indexed by the hooks.
+        let handles = entities
+            .iter()
+            .map(|&(merged, entity)| (entity, merged))
+            .collect::<HashMap<_, _>>();
+        self.hooks.relations.retarget(
+            &mut self.component_stores,
+            offset..self.component_cursor,
+            &handles,
+        );
+
+        for &(merged, entity) in &entities {
+            let (position, len) = other.entity_component_references[&entity];
+
+            let position = offset + position;
+            self.entity_component_references
+                .insert(merged, (position, len));
+            self.update_archetype(position, len);
+
+            let rows = position..position + len;
+            self.hooks.added_rows(&self.component_stores, merged, rows);
+        }
+
+        entities
+    }
+
+    /// Removes an entity, and drops all of its components.
+    ///
+    /// Returns `false` if the entity was already despawned.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if self.entities.remove(entity.index()).is_none() {
+            return false;
+        }
+
+        if let Some((position, len)) = self.entity_component_references.remove(&entity) {
+            let rows = position..position + len;
+            self.hooks
+                .removed_rows(&self.component_stores, entity, rows);
+
+            for store in self.component_stores.values_mut() {
+                for position in position..position + len {
+                    store.discard(position);
+                }
+            }
+
+            self.archetypes.assign(position..position + len, 0);
+            self.free_rows.push((position, len));
+        }
+
+        self.unlink(entity);
+        true
+    }
+
+    /// Removes an entity, and returns all of its components.
+    ///
+    /// Returns `None` if the entity was already despawned.
+    pub fn despawn_take(&mut self, entity: Entity) -> Option<ComponentsBundle> {
+        self.entities.remove(entity.index())?;
+
+        let mut bundle = ComponentsBundle::default();
+        if let Some((position, len)) = self.entity_component_references.remove(&entity) {
+            let rows = position..position + len;
+            self.hooks
+                .removed_rows(&self.component_stores, entity, rows);
+
+            for (&id, store) in self.component_stores.iter_mut() {
+                for position in position..position + len {
+                    if let Some(component) = store.take(position) {
+                        bundle.insert(id, component);
+                    }
+                }
+            }
+
+            self.archetypes.assign(position..position + len, 0);
+            self.free_rows.push((position, len));
+        }
+
+        self.unlink(entity);
+        Some(bundle)
+    }
+
+    /// Registers a component type referring to other entities, whose
+    /// components are removed from their owners once the entity they refer to
+    /// is despawned, so no handle to a despawned entity is left behind.
+    ///
+    /// The components are removed in the same way as by
+    /// [`Things::remove_component`], so the hook set by
+    /// [`Things::set_on_remove`] is called for each of them, which allows
+    /// reacting to the loss of the referred entity.
+    ///
+    /// The components are indexed by the entity they refer to, so a despawn
+    /// only removes the components referring to the despawned entity. The
+    /// target is read when a component is added, or replaced by
+    /// [`Things::replace_component`]. A target changed in place, such as
+    /// through [`Things::get_mut`] or a [`Write`] query, is read again before
+    /// the next despawn, by visiting the components changed since the previous
+    /// one.
+    ///
+    /// Components merged from another world by [`Things::merge`] are made to
+    /// refer to the new handles of their targets before they are indexed.
+    ///
+    /// Handles stored in components of other types can still refer to a
+    /// despawned entity, and can be checked using [`Things::entity`].
+    pub fn register_relation<C: Relation>(&mut self) {
+        self.hooks.relations.register::<C>(unlink::<C>);
+        self.hooks.relations.index(
+            &self.component_stores,
+            &self.entity_component_references,
+            self.change_tick,
+        );
+    }
+
+    /// Removes the components of all registered relation types that refer to
+    /// the despawned entity.
+    fn unlink(&mut self, entity: Entity) {
+        self.hooks.relations.refresh(
+            &self.component_stores,
+            &self.entity_component_references,
+            self.change_tick,
+        );
+
+        let owners = match self.hooks.relations.take(entity) {
+            Some(owners) => owners,
+            None => return,
+        };
+
+        for (id, owner) in owners {
+            let unlink = self.hooks.relations.unlink(&id);
+            unlink(self, owner, entity);
+        }
+    }
+
+    /// Marks an entity to be despawned by the next call to
+    /// [`Things::apply_despawns`], which is also made after every system.
+    ///
+    /// Unlike [`Things::despawn`], this only requires a shared reference, so
+    /// entities can be marked while the world is being read, without
+    /// collecting them first:
+    ///
+    /// ```
+    /// # use things::{Component, Things};
+    /// # #[derive(Component, Debug)]
+    /// # struct Health(u8);
+    /// let mut ecs = Things::new();
+    /// ecs.create_entity((Health(0),));
+    /// ecs.create_entity((Health(10),));
+    ///
+    /// for entity in ecs.entities() {
+    ///     if ecs.get::<Health>(entity).is_some_and(|health| health.0 == 0) {
+    ///         ecs.mark_despawn(entity);
+    ///     }
+    /// }
+    ///
+    /// ecs.apply_despawns();
+    /// assert_eq!(ecs.entity_count(), 1);
+    /// ```
+    ///
+    /// An entity that is marked more than once, or that is no longer alive by
+    /// the time the marks are applied, is skipped. Systems mark entities
+    /// through the [`Despawns`] query element instead.
+    pub fn mark_despawn(&self, entity: Entity) {
+        self.queues.despawns().mark(entity);
+    }
+
+    /// Despawns the entities marked by [`Things::mark_despawn`], in the order
+    /// in which they were marked.
+    pub fn apply_despawns(&mut self) {
+        for entity in self.queues.drain_despawns() {
+            self.despawn(entity);
+        }
+    }
+
+    /// Adds a component to an existing entity.
+    ///
+    /// If the entity already holds a component of the same type, the new
+    /// component is stored next to it, as entities can hold multiple components
+    /// of the same type. Use [`Things::replace_component`] to overwrite the
+    /// existing component instead. Returns `false` if the entity is no longer
+    /// alive, in which case the component is dropped.
+    pub fn add_component<C: Component>(&mut self, entity: Entity, component: C) -> bool {
+        let (position, len) = match self.references(entity) {
+            Some(references) => references,
+            None => return false,
+        };
+
+        let cursor = self.component_cursor;
+        let store = store_mut::<C>(&mut self.component_stores, self.change_tick);
+        Store::pad(store, cursor);
+
+        // If one of the rows of the entity does not yet contain a component of
+        // this type, it is stored in that row, without affecting any other
+        // entity.
+        if let Some(row) = (position..position + len).find(|&row| store.get(row).is_none()) {
+            let row = store.push(row, component);
+            self.hooks.added(entity, store.get(row).unwrap());
+            self.update_archetype(position, len);
+            return true;
+        }
+
+        // Otherwise the entity needs an extra row. Since the rows after it can
+        // belong to other entities, all components of this entity are moved to
+        // the end of the stores, leaving empty rows in their old location.
+        for store in self.component_stores.values_mut() {
+            for offset in 0..len {
+                store.relocate(position + offset, cursor + offset);
+            }
+        }
+        self.free_rows.push((position, len));
+
+        let store = store_mut::<C>(&mut self.component_stores, self.change_tick);
+        let row = store.push(cursor + len, component);
+        self.hooks.added(entity, store.get(row).unwrap());
+
+        self.component_cursor += len + 1;
+        self.entity_component_references
+            .insert(entity, (cursor, len + 1));
+        self.update_archetype(cursor, len + 1);
+
+        for store in self.component_stores.values_mut() {
+            store.pad(self.component_cursor);
+        }
+
+        true
     }
 
-    fn borrow_and_validate<Borrow: RegisterBorrow>(&self) -> Result<(), BorrowError> {
-        let mut borrow = self.runtime_borrow.lock();
-        borrow.push_access::<Borrow>()?;
-        borrow.validate()
+    /// Replaces a component of an existing entity, and returns the component it
+    /// held before.
+    ///
+    /// If the entity holds multiple components of the same type, the first one
+    /// is replaced, the same one [`Things::get`] returns. The component is
+    /// overwritten in place, which is recorded as a change rather than as an
+    /// addition, and does not call any hooks. A replaced [`Relation`]
+    /// component refers to the target of the new component from then on, see
+    /// [`Things::register_relation`].
+    ///
+    /// If the entity does not hold a component of the given type, the
+    /// component is added as by [`Things::add_component`], and `None` is
+    /// returned. If the entity is no longer alive, the component is dropped.
+    pub fn replace_component<C: Component>(&mut self, entity: Entity, component: C) -> Option<C> {
+        if !self.has_component::<C>(entity) {
+            self.add_component(entity, component);
+            return None;
+        }
+
+        let id = TypeId::of::<C>();
+        self.hooks.relations.added(entity, id, &component);
+
+        let previous = core::mem::replace(self.get_mut::<C>(entity).unwrap(), component);
+        self.hooks.relations.removed(entity, id, &previous);
+        Some(previous)
+    }
+
+    /// Returns a read-only view of a live entity, or `None` if the entity is
+    /// not alive.
+    ///
+    /// This resolves handles stored in components, such as a parent or a
+    /// target, which may refer to an entity that was despawned since.
+    #[doc(alias = "resolve")]
+    pub fn entity(&self, entity: Entity) -> Option<EntityRef<'_>> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        Some(EntityRef::new(self, entity))
+    }
+
+    /// Returns a mutable view of a live entity, which groups the operations on
+    /// that entity, or `None` if the entity is not alive.
+    pub fn entity_mut(&mut self, entity: Entity) -> Option<EntityMut<'_>> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        Some(EntityMut::new(self, entity))
+    }
+
+    /// Returns `true` if the entity holds a component of the given type.
+    ///
+    /// Returns `false` if the entity is not alive.
+    pub fn has_component<C: Component>(&self, entity: Entity) -> bool {
+        let (position, len) = match self.references(entity) {
+            Some(references) => references,
+            None => return false,
+        };
+
+        self.component_stores
+            .get(&TypeId::of::<C>())
+            .is_some_and(|store| (position..position + len).any(|row| store.contains(row)))
+    }
+
+    /// Returns a reference to a component of the entity.
+    ///
+    /// If the entity holds multiple components of the same type, the first one
+    /// is returned. Returns `None` if the entity is not alive, or does not hold
+    /// a component of the given type.
+    pub fn get<C: Component>(&self, entity: Entity) -> Option<&C> {
+        let (position, len) = self.references(entity)?;
+        let store = self.component_stores.get(&TypeId::of::<C>())?;
+
+        let store = store.as_store::<C>()?;
+        (position..position + len).find_map(|row| store.get(row))
+    }
+
+    /// Returns a reference to a component of the entity, the same as
+    /// [`Things::get`], but tells a stale handle apart from an entity that does
+    /// not hold a component of the given type.
+    ///
+    /// Returns an error if the entity is not alive, and `Ok(None)` if it is
+    /// alive but does not hold the component:
+    ///
+    /// ```
+    /// # use things::{Component, EntityError, Things};
+    /// # #[derive(Component, Debug, PartialEq)]
+    /// # struct Health(u8);
+    /// let mut ecs = Things::new();
+    /// let entity = ecs.create_entity((Health(10),));
+    /// assert_eq!(ecs.try_get::<Health>(entity), Ok(Some(&Health(10))));
+    ///
+    /// ecs.despawn(entity);
+    /// assert_eq!(ecs.try_get::<Health>(entity), Err(EntityError::Dead(entity)));
+    /// ```
+    pub fn try_get<C: Component>(&self, entity: Entity) -> Result<Option<&C>, EntityError> {
+        if !self.is_alive(entity) {
+            return Err(EntityError::Dead(entity));
+        }
+
+        Ok(self.get(entity))
+    }
+
+    /// Returns a reference to a component of each of the given entities, in the
+    /// same order, without scanning the rest of the store.
+    ///
+    /// See [`Things::get`] for details.
+    pub fn get_many<C: Component>(&self, entities: &[Entity]) -> Vec<Option<&C>> {
+        let store = self
+            .component_stores
+            .get(&TypeId::of::<C>())
+            .and_then(|store| store.as_store::<C>());
+
+        entities
+            .iter()
+            .map(|&entity| {
+                let (position, len) = self.references(entity)?;
+                (position..position + len).find_map(|row| store?.get(row))
+            })
+            .collect()
+    }
+
+    /// Returns a mutable reference to a component of the entity.
+    ///
+    /// See [`Things::get`] for details.
+    pub fn get_mut<C: Component>(&mut self, entity: Entity) -> Option<&mut C> {
+        let (position, len) = self.references(entity)?;
+        let store = self.component_stores.get_mut(&TypeId::of::<C>())?;
+
+        let store = store.as_mut_store::<C>()?;
+        let row = (position..position + len).find(|&row| store.get(row).is_some())?;
+        store.get_mut_exclusive(row)
+    }
+
+    /// Returns mutable references to components of two different types of the
+    /// entity at once.
+    ///
+    /// Returns `None` if the entity is not alive, or does not hold a component
+    /// of either type. See [`Things::get`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `A` and `B` are the same type.
+    pub fn get_mut2<A, B>(&mut self, entity: Entity) -> Option<(&mut A, &mut B)>
+    where
+        A: Component,
+        B: Component,
+    {
+        assert_ne!(
+            TypeId::of::<A>(),
+            TypeId::of::<B>(),
+            "`{}` is borrowed mutably twice",
+            core::any::type_name::<A>()
+        );
+
+        let (position, len) = self.references(entity)?;
+        let (a, b) = self
+            .component_stores
+            .get_pair_mut(&TypeId::of::<A>(), &TypeId::of::<B>())?;
+        let (a, b) = (a.as_mut_store::<A>()?, b.as_mut_store::<B>()?);
+
+        let mut rows = position..position + len;
+        let row_a = rows.clone().find(|&row| a.get(row).is_some())?;
+        let row_b = rows.find(|&row| b.get(row).is_some())?;
+        Some((a.get_mut_exclusive(row_a)?, b.get_mut_exclusive(row_b)?))
+    }
+
+    /// Calls the closure with mutable references to every pair of components
+    /// of the given type, such as to apply forces between every two bodies.
+    ///
+    /// Only positions holding a component are paired, and each pair is visited
+    /// once, as `(i, j)` with the position `i` before the position `j`, in
+    /// ascending order of `i`, then of `j`. Every component is recorded as
+    /// changed.
+    ///
+    /// An iterator can't yield the pairs, as it would hand out a reference to
+    /// the same component in more than one pair at a time, so the references
+    /// only live for the duration of each call:
+    ///
+    /// ```
+    /// # use things::{Component, Things};
+    /// #[derive(Component, Debug)]
+    /// struct Body {
+    ///     mass: u32,
+    ///     pull: u32,
+    /// }
+    ///
+    /// let mut ecs = Things::new();
+    /// let bodies = ecs.spawn_batch((1..=3).map(|mass| (Body { mass, pull: 0 },)));
+    ///
+    /// ecs.for_each_pair_mut(|a: &mut Body, b: &mut Body| {
+    ///     a.pull += b.mass;
+    ///     b.pull += a.mass;
+    /// });
+    ///
+    /// assert_eq!(ecs.get::<Body>(bodies[0]).unwrap().pull, 5);
+    /// assert_eq!(ecs.get::<Body>(bodies[2]).unwrap().pull, 3);
+    /// ```
+    #[doc(alias = "pairwise_mut")]
+    pub fn for_each_pair_mut<C, F>(&mut self, mut f: F)
+    where
+        C: Component,
+        F: FnMut(&mut C, &mut C),
+    {
+        let store = match self.component_stores.get_mut(&TypeId::of::<C>()) {
+            Some(store) => store.expect_mut_store::<C>(),
+            None => return,
+        };
+
+        let rows = (0..Store::slots(store))
+            .filter(|&row| store.get(row).is_some())
+            .collect::<Vec<_>>();
+
+        for (i, &a) in rows.iter().enumerate() {
+            for &b in &rows[i + 1..] {
+                let (a, b) = store.get_pair_mut(a, b);
+                f(a.unwrap(), b.unwrap());
+            }
+        }
+    }
+
+    /// Returns an iterator over all components of the given type held by the
+    /// entity, in the order in which they were stored.
+    ///
+    /// The iterator is empty if the entity is not alive, or does not hold a
+    /// component of the given type.
+    pub fn get_all<C: Component>(&self, entity: Entity) -> impl Iterator<Item = &C> {
+        let (position, len) = self.references(entity).unwrap_or_default();
+        let store = self
+            .component_stores
+            .get(&TypeId::of::<C>())
+            .and_then(|store| store.as_store::<C>());
+
+        (position..position + len).filter_map(move |row| store?.get(row))
+    }
+
+    /// Returns an iterator over mutable references to all components of the
+    /// given type held by the entity.
+    ///
+    /// See [`Things::get_all`] for details.
+    pub fn get_all_mut<C: Component>(&mut self, entity: Entity) -> impl Iterator<Item = &mut C> {
+        let (position, len) = self.references(entity).unwrap_or_default();
+        let store = self
+            .component_stores
+            .get_mut(&TypeId::of::<C>())
+            .and_then(|store| store.as_mut_store::<C>())
+            .map(|store| &*store);
+
+        // This is safe, because the store is exclusively borrowed through
+        // `&mut self`, and every row is visited only once, so no two references
+        // to the same component can exist.
+        (position..position + len).filter_map(move |row| unsafe { store?.get_mut(row) })
+    }
+
+    /// Returns an iterator over every position of the store of the given
+    /// component type, along with the component at that position, if any.
+    ///
+    /// Positions without a component are included, so the index of each item
+    /// is the position of its row, which is shared by the components of the
+    /// same entity in every store. For a grid spawned into an empty world as a
+    /// single tile entity per cell, the index is the index of the cell, until
+    /// entities are despawned and their rows are reused by other entities.
+    /// Positions also change when entities are moved, see
+    /// [`Things::add_component`] and [`Things::compact`]. Yields no items if no
+    /// component of the type was ever stored.
+    pub fn iter_positions<C: Component>(&self) -> impl Iterator<Item = (usize, Option<&C>)> {
+        self.store::<C>().into_iter().flat_map(Store::iter_indexed)
+    }
+
+    /// Returns the store of the given component type, or `None` if no
+    /// component of that type was ever stored.
+    ///
+    /// The positions of the store are the rows of the world, so the components
+    /// can be processed in bulk, such as through [`DefaultStore::as_slice`],
+    /// without defining a [`System`].
+    pub fn store<C: Component>(&self) -> Option<&C::Storage> {
+        self.component_stores
+            .get(&TypeId::of::<C>())
+            .and_then(|store| store.as_store::<C>())
+    }
+
+    /// Returns the mutable store of the given component type.
+    ///
+    /// See [`Things::store`] for details. Components must not be moved to other
+    /// positions, or pushed to positions beyond the end of the store, as that
+    /// would assign them to other entities. Since components may be added or
+    /// removed through the store, the next query visits every row to find out
+    /// which entities hold which components.
+    pub fn store_mut<C: Component>(&mut self) -> Option<&mut C::Storage> {
+        self.archetypes.invalidate();
+        self.component_stores
+            .get_mut(&TypeId::of::<C>())
+            .and_then(|store| store.as_mut_store::<C>())
+    }
+
+    /// Removes a component from an entity, and returns it.
+    ///
+    /// If the entity holds multiple components of the same type, the one
+    /// stored last is removed. Other entities are never moved, the slot of the
+    /// removed component is left empty instead. Returns `None` if the entity is
+    /// not alive, or does not hold a component of the given type.
+    pub fn remove_component<C: Component>(&mut self, entity: Entity) -> Option<C> {
+        let (position, len) = self.references(entity)?;
+
+        let store = self
+            .component_stores
+            .get_mut(&TypeId::of::<C>())?
+            .expect_mut_store::<C>();
+
+        let row = (position..position + len)
+            .rev()
+            .find(|&row| store.get(row).is_some())?;
+
+        let component = store.remove(row)?;
+        self.hooks.removed(entity, &component);
+
+        // If the last row of the entity no longer holds any components, the
+        // entity shrinks by one row, to keep its count of components per type
+        // accurate.
+        let last = position + len - 1;
+        if len > 1 && self.component_stores.values().all(|s| !s.contains(last)) {
+            self.entity_component_references
+                .insert(entity, (position, len - 1));
+            self.archetypes.assign(last..last + 1, 0);
+            self.free_rows.push((last, 1));
+            self.update_archetype(position, len - 1);
+        } else {
+            self.update_archetype(position, len);
+        }
+
+        Some(component)
+    }
+
+    /// Removes every component of the given type from all entities, such as
+    /// when the system using it is disabled.
+    ///
+    /// Entities keep their other components, and are kept alive even if they
+    /// no longer hold any components. The hook set by
+    /// [`Things::set_on_remove`] is called for every removed component. The
+    /// store itself is kept empty, so it does not have to be created again
+    /// when components of the type are stored later on.
+    pub fn clear_component<C: Component>(&mut self) {
+        self.remove_where::<C, _, _>(|_| true, |_, _| {});
+    }
+
+    /// Removes every component of the given type from all entities, and
+    /// returns them along with the entities that held them.
+    ///
+    /// This consumes components that are only meant to be handled once, such
+    /// as pickups, without first reading and then removing them:
+    ///
+    /// ```
+    /// # use things::{Component, Things};
+    /// #[derive(Component, Debug)]
+    /// struct Pickup(u32);
+    ///
+    /// let mut ecs = Things::new();
+    /// let player = ecs.create_entity((Pickup(10),));
+    /// ecs.add_component(player, Pickup(5));
+    ///
+    /// let score: u32 = ecs.drain::<Pickup>().map(|(_, pickup)| pickup.0).sum();
+    /// assert_eq!(score, 15);
+    /// assert!(!ecs.has_component::<Pickup>(player));
+    /// ```
+    ///
+    /// The components are yielded in ascending position, see
+    /// [`QueryIter`](crate::QueryIter#iteration-order), and are all removed
+    /// before the first one is yielded, in the same way as by
+    /// [`Things::clear_component`].
+    pub fn drain<C: Component>(&mut self) -> impl Iterator<Item = (Entity, C)> {
+        let mut components = Vec::new();
+        self.remove_where::<C, _, _>(
+            |_| true,
+            |entity, component| components.push((entity, component)),
+        );
+        components.into_iter()
+    }
+
+    /// Removes every component of the given type that matches the predicate,
+    /// and hands each of them to the closure along with the entity that held
+    /// it, after calling the hook.
+    fn remove_where<C, P, F>(&mut self, mut predicate: P, mut f: F)
+    where
+        C: Component,
+        P: FnMut(&C) -> bool,
+        F: FnMut(Entity, C),
+    {
+        let id = TypeId::of::<C>();
+        if !self.component_stores.contains_key(&id) {
+            return;
+        }
+
+        let mut references = self
+            .entity_component_references
+            .iter()
+            .map(|(&entity, &references)| (entity, references))
+            .collect::<Vec<_>>();
+        references.sort_unstable_by_key(|&(_, (position, _))| position);
+
+        for (entity, references) in references {
+            self.remove_from(entity, references, &mut predicate, &mut f);
+        }
+    }
+
+    /// Removes the components of the given type held by a single entity that
+    /// match the predicate, the same as `remove_where`.
+    fn remove_from<C, P, F>(
+        &mut self,
+        entity: Entity,
+        (position, len): (usize, usize),
+        mut predicate: P,
+        mut f: F,
+    ) where
+        C: Component,
+        P: FnMut(&C) -> bool,
+        F: FnMut(Entity, C),
+    {
+        let store = match self.component_stores.get_mut(&TypeId::of::<C>()) {
+            Some(store) => store.expect_mut_store::<C>(),
+            None => return,
+        };
+
+        let mut removed = false;
+        for row in position..position + len {
+            if !store.get(row).is_some_and(&mut predicate) {
+                continue;
+            }
+
+            let component = store.remove(row).unwrap();
+            self.hooks.removed(entity, &component);
+            f(entity, component);
+            removed = true;
+        }
+
+        if !removed {
+            return;
+        }
+
+        // Trailing rows of the entity that no longer hold any components are
+        // dropped, the same as by `remove_component`.
+        let mut new_len = len;
+        while new_len > 1
+            && self
+                .component_stores
+                .values()
+                .all(|store| !store.contains(position + new_len - 1))
+        {
+            new_len -= 1;
+        }
+
+        if new_len < len {
+            self.entity_component_references
+                .insert(entity, (position, new_len));
+            self.archetypes
+                .assign(position + new_len..position + len, 0);
+            self.free_rows.push((position + new_len, len - new_len));
+        }
+        self.update_archetype(position, new_len);
+    }
+
+    /// Sets the hook that is called whenever a component of the given type is
+    /// added to an entity, replacing any previous hook for the same type.
+    ///
+    /// The hook is called with the entity and the added component, right after
+    /// the component is stored, whether it is added by creating the entity, or
+    /// by [`Things::add_component`]. Hooks are not called for components
+    /// restored by loading a saved world.
+    ///
+    /// ```
+    /// # use things::{Component, Things};
+    /// # #[derive(Component, Debug)]
+    /// # struct RigidBody(f32);
+    /// let mut ecs = Things::new();
+    /// ecs.set_on_add(|entity, body: &RigidBody| println!("{:?} weighs {}", entity, body.0));
+    /// ecs.create_entity((RigidBody(80.0),));
+    /// ```
+    pub fn set_on_add<C, F>(&mut self, hook: F)
+    where
+        C: Component,
+        F: FnMut(Entity, &C) + Send + Sync + 'static,
+    {
+        self.hooks.set_on_add(hook);
+    }
+
+    /// Sets the hook that is called whenever a component of the given type is
+    /// removed from an entity, replacing any previous hook for the same type.
+    ///
+    /// The hook is called with the entity and the removed component, right
+    /// before the component is dropped or returned, whether it is removed by
+    /// [`Things::remove_component`], or by despawning or clearing entities.
+    pub fn set_on_remove<C, F>(&mut self, hook: F)
+    where
+        C: Component,
+        F: FnMut(Entity, &C) + Send + Sync + 'static,
+    {
+        self.hooks.set_on_remove(hook);
+    }
+
+    /// Inserts a resource, replacing any existing resource of the same type.
+    pub fn insert_resource<R: Send + Sync + 'static>(&mut self, resource: R) {
+        self.resources.insert(resource);
+    }
+
+    /// Returns a reference to the resource of type `R`, if it exists.
+    pub fn get_resource<R: 'static>(&self) -> Option<&R> {
+        self.resources.get::<R>()
+    }
+
+    /// Returns a mutable reference to the resource of type `R`, if it exists.
+    pub fn get_resource_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut::<R>()
+    }
+
+    /// Sends an event, to be read by systems using an [`EventReader`] during
+    /// the next frame.
+    pub fn send_event<E: Send + Sync + 'static>(&mut self, event: E) {
+        self.events.send(event);
+    }
+
+    /// Marks the end of a frame, making the events sent during it readable,
+    /// and dropping the events sent two frames before, see [`EventReader`].
+    ///
+    /// This is done automatically when running a [`Schedule`], but has to be
+    /// called manually once per frame when executing systems individually.
+    pub fn update_events(&mut self) {
+        self.events.update(self.change_tick);
+    }
+
+    /// Removes all entities, and drops all of their components.
+    ///
+    /// The stores keep their allocated capacity, so the world can be filled
+    /// again without having to grow them from scratch. Resources and events are
+    /// kept. Handles to the removed entities are never considered alive again.
+    pub fn clear(&mut self) {
+        if self.hooks.has_removed() {
+            for (&entity, &(position, len)) in &self.entity_component_references {
+                let rows = position..position + len;
+                self.hooks
+                    .removed_rows(&self.component_stores, entity, rows);
+            }
+        }
+
+        self.entities.clear();
+        self.entity_component_references.clear();
+        self.component_cursor = 0;
+        self.free_rows.clear();
+        self.archetypes.clear();
+        self.hooks.relations.clear();
+
+        for store in self.component_stores.values_mut() {
+            store.clear();
+        }
+    }
+
+    /// Returns `true` if the entity is still alive.
+    ///
+    /// Because entities are stored in a generational arena, a handle to a
+    /// despawned entity will never be considered alive, even if its slot has
+    /// since been reused by a newer entity.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.contains(entity.index())
+    }
+
+    /// Returns the number of live entities.
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns `true` if there are no live entities.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Returns the type name, the number of stored components, and the number
+    /// of slots of the store of every component type, ordered by type name.
+    ///
+    /// Slots without a component still take up memory in a [`DefaultStore`],
+    /// so a component type with far fewer components than slots is better off
+    /// in a [`SparseStore`].
+    pub fn component_stats(&self) -> Vec<(&'static str, usize, usize)> {
+        let mut stats = self
+            .component_stores
+            .values()
+            .map(|store| store.stats())
+            .collect::<Vec<_>>();
+
+        stats.sort_unstable_by_key(|&(name, ..)| name);
+        stats
+    }
+
+    /// Returns an iterator over all live entities, regardless of the
+    /// components they hold.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().map(|(index, _)| Entity::from(index))
+    }
+
+    /// Inserts a new entity into the arena, unless its generation overflowed.
+    pub(crate) fn alloc_entity(&mut self) -> Result<Entity, SpawnError> {
+        let index = self.entities.insert(());
+        let (_, generation) = index.into_raw_parts();
+
+        if generation < self.generation {
+            self.entities.remove(index);
+            return Err(SpawnError::GenerationOverflow);
+        }
+
+        self.generation = generation;
+        Ok(Entity::from(index))
+    }
+
+    /// Returns the archetype of the components held in the given rows.
+    fn archetype_of(&mut self, rows: Range<usize>) -> usize {
+        let types = self
+            .component_stores
+            .iter()
+            .filter(|(_, store)| rows.clone().any(|row| store.contains(row)))
+            .map(|(&id, _)| id)
+            .collect();
+
+        self.archetypes.insert(types)
+    }
+
+    /// Assigns the rows of an entity to the archetype of its components.
+    fn update_archetype(&mut self, position: usize, len: usize) {
+        let rows = position..position + len;
+        let archetype = self.archetype_of(rows.clone());
+        self.archetypes.assign(rows, archetype);
+    }
+
+    /// Rebuilds the archetypes of all entities, if components may have been
+    /// added or removed without updating them, see [`Things::store_mut`].
+    pub(crate) fn refresh_archetypes(&mut self) {
+        if !self.archetypes.is_stale() {
+            return;
+        }
+
+        self.archetypes.clear();
+        let references = self
+            .entity_component_references
+            .values()
+            .copied()
+            .collect::<Vec<_>>();
+
+        for (position, len) in references {
+            self.update_archetype(position, len);
+        }
+    }
+
+    /// Takes the position of `len` vacated rows out of the free list, if any
+    /// vacated range is large enough to hold them.
+    ///
+    /// A range of exactly `len` rows is preferred, to avoid splitting larger
+    /// ranges that other entities may need. Otherwise only the rows that are
+    /// needed are taken from a larger range, leaving the rest of it free.
+    pub(crate) fn take_free_rows(&mut self, len: usize) -> Option<usize> {
+        take_free_rows(&mut self.free_rows, len)
+    }
+
+    /// Returns the position and length of the rows of a live entity.
+    fn references(&self, entity: Entity) -> Option<(usize, usize)> {
+        match self.entity_component_references.get(&entity) {
+            Some(&references) if self.is_alive(entity) => Some(references),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of slots of every store.
+    ///
+    /// Queries rely on every store holding exactly `component_cursor` slots, so
+    /// each position maps to the same row in every store.
+    #[cfg(test)]
+    pub(crate) fn store_lengths(&self) -> HashMap<TypeId, usize> {
+        self.component_stores
+            .iter()
+            .map(|(&id, store)| (id, store.slots()))
+            .collect()
+    }
+}
+
+/// Takes the position of `len` vacated rows out of the free list, see
+/// [`Things::take_free_rows`].
+fn take_free_rows(free_rows: &mut Vec<(usize, usize)>, len: usize) -> Option<usize> {
+    let index = free_rows
+        .iter()
+        .rposition(|&(_, free)| free == len)
+        .or_else(|| free_rows.iter().rposition(|&(_, free)| free > len))?;
+    let (position, free) = free_rows[index];
+
+    if free == len {
+        free_rows.swap_remove(index);
+    } else {
+        free_rows[index] = (position + len, free - len);
+    }
+
+    Some(position)
+}
+
+/// Removes the components of a relation type held by the owner that refer to
+/// the despawned entity, see [`Things::register_relation`].
+fn unlink<C: Relation>(things: &mut Things, owner: Entity, despawned: Entity) {
+    if let Some(references) = things.references(owner) {
+        things.remove_from::<C, _, _>(
+            owner,
+            references,
+            |component| component.target() == despawned,
+            |_, _| {},
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component, Debug)]
+    struct A;
+
+    #[derive(Component, Debug)]
+    struct B;
+
+    #[derive(Component, Debug)]
+    #[component(storage = "SparseStore")]
+    struct C;
+
+    /// Asserts that every store holds exactly one slot per row.
+    fn assert_aligned(ecs: &Things) {
+        for (id, len) in ecs.store_lengths() {
+            assert_eq!(len, ecs.component_cursor, "store {:?} is misaligned", id);
+        }
+    }
+
+    #[test]
+    fn test_stores_aligned_to_cursor() {
+        let mut ecs = Things::new();
+        ecs.create_entity((A, B));
+        ecs.create_entity((A,));
+        ecs.create_entity((C, C));
+
+        assert_eq!(ecs.component_cursor, 4);
+        for store in ecs.component_stores.values() {
+            assert_eq!(store.slots(), ecs.component_cursor);
+        }
+    }
+
+    #[test]
+    fn test_stores_stay_aligned() {
+        #[derive(Component, Debug)]
+        struct D(usize);
+
+        let mut ecs = Things::new();
+        let mut entities = ecs.spawn_batch((0..8).map(|i| (D(i), A)));
+        entities.push(ecs.create_entity((C, C, B)));
+        assert_aligned(&ecs);
+
+        for (i, &entity) in entities.iter().enumerate() {
+            match i % 4 {
+                0 => drop(ecs.add_component(entity, D(i))),
+                1 => drop(ecs.add_component(entity, B)),
+                2 => drop(ecs.remove_component::<A>(entity)),
+                _ => drop(ecs.replace_component(entity, D(i * 10))),
+            }
+            assert_aligned(&ecs);
+        }
+
+        for &entity in entities.iter().step_by(3) {
+            ecs.despawn(entity);
+            assert_aligned(&ecs);
+        }
+
+        ecs.create_entity((D(100), B, C));
+        ecs.spawn_batch((0..3).map(|i| (A, D(i), D(i))));
+        assert_aligned(&ecs);
+
+        ecs.clear_component::<B>();
+        assert_aligned(&ecs);
+        assert_eq!(ecs.drain::<A>().count(), 7);
+        assert_aligned(&ecs);
+
+        ecs.compact();
+        assert_aligned(&ecs);
+        assert_eq!(ecs.store_lengths().len(), 4);
+        assert_eq!(ecs.get::<D>(entities[1]).map(|d| d.0), Some(1));
+        assert_eq!(ecs.get::<D>(entities[7]).map(|d| d.0), Some(70));
+    }
+
+    #[test]
+    fn test_clear_resets_cursor() {
+        let mut ecs = Things::new();
+        ecs.create_entity((A, B));
+        ecs.create_entity((C, C));
+
+        ecs.clear();
+        assert_eq!(ecs.component_cursor, 0);
+
+        ecs.create_entity((A,));
+        assert_eq!(ecs.component_cursor, 1);
+        for store in ecs.component_stores.values() {
+            assert_eq!(store.slots(), ecs.component_cursor);
+        }
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut ecs = Things::with_capacity(8);
+        assert!(ecs.entities.capacity() >= 8);
+
+        ecs.create_entity((A,));
+        ecs.reserve(100);
+        assert!(ecs.entities.capacity() >= 101);
+        assert!(ecs.entity_component_references.capacity() >= 101);
+    }
+
+    #[test]
+    fn test_compact() {
+        #[derive(Component, Debug, PartialEq)]
+        struct D(usize);
+
+        let mut ecs = Things::new();
+        let entities = (0..6)
+            .map(|i| ecs.create_entity((D(i), A)))
+            .collect::<Vec<_>>();
+        let c = ecs.create_entity((C, C));
+        ecs.add_component(entities[1], D(10));
+
+        for &entity in &entities[2..5] {
+            ecs.despawn(entity);
+        }
+
+        let rows = ecs.query::<(Read<D>, With<A>)>().unwrap().count();
+        ecs.shrink_to_fit();
+        assert_eq!(ecs.component_cursor, 10);
+
+        ecs.compact();
+        assert_eq!(ecs.component_cursor, 6);
+        assert!(ecs.free_rows.is_empty());
+        for store in ecs.component_stores.values() {
+            assert_eq!(store.slots(), ecs.component_cursor);
+        }
+
+        assert_eq!(ecs.get(entities[0]), Some(&D(0)));
+        assert_eq!(ecs.get(entities[5]), Some(&D(5)));
+        assert_eq!(ecs.get_all::<D>(entities[1]).count(), 2);
+        assert_eq!(ecs.get_all::<C>(c).count(), 2);
+
+        assert_eq!(ecs.query::<(Read<D>, With<A>)>().unwrap().count(), rows);
+    }
+
+    #[test]
+    fn test_generation_overflow() {
+        let mut ecs = Things::new();
+        let a = ecs.try_create_entity((A,)).unwrap();
+        ecs.despawn(a);
+        assert!(ecs.try_create_entity((A,)).is_ok());
+
+        // Pretend the generations wrapped around after this entity.
+        ecs.generation = u64::MAX;
+
+        let err = ecs.try_create_entity((A, B)).unwrap_err();
+        assert_eq!(err, SpawnError::GenerationOverflow);
+        assert_eq!(ecs.entity_count(), 1);
+        assert_eq!(ecs.component_cursor, 1);
+    }
+
+    #[test]
+    fn test_despawned_rows_reused() {
+        let mut ecs = Things::new();
+        let mut a = ecs.create_entity((A, B));
+        let mut b = ecs.create_entity((C, C));
+        ecs.create_entity((A,));
+
+        for _ in 0..10 {
+            ecs.despawn(a);
+            ecs.despawn(b);
+            a = ecs.create_entity((A, B));
+            b = ecs.create_entity((C, C));
+        }
+        assert_eq!(ecs.component_cursor, 4);
+
+        // The vacated row is reused by an entity with other components, while a
+        // collection spanning two rows does not fit in it.
+        ecs.despawn(a);
+        let d = ecs.create_entity((C, C));
+        let e = ecs.create_entity((C,));
+
+        assert_eq!(ecs.references(d), Some((4, 2)));
+        assert_eq!(ecs.references(e), Some((0, 1)));
+        assert!(!ecs.has_component::<A>(e));
+        assert_eq!(ecs.get_all::<C>(d).count(), 2);
+        for store in ecs.component_stores.values() {
+            assert_eq!(store.slots(), ecs.component_cursor);
+        }
+    }
+
+    #[test]
+    fn test_shrunk_rows_reused() {
+        let mut ecs = Things::new();
+        let a = ecs.create_entity((C, C));
+        let b = ecs.create_entity((A, C, C));
+        assert_eq!(ecs.component_cursor, 4);
+
+        // Removing a component shrinks the entity, and the vacated row is
+        // reused by the next entity that fits in it.
+        ecs.remove_component::<C>(a);
+        let c = ecs.create_entity((B,));
+        assert_eq!(ecs.references(a), Some((0, 1)));
+        assert_eq!(ecs.references(c), Some((1, 1)));
+
+        ecs.clear_component::<C>();
+        let d = ecs.create_entity((B,));
+        assert_eq!(ecs.references(b), Some((2, 1)));
+        assert_eq!(ecs.references(d), Some((3, 1)));
+        assert_eq!(ecs.component_cursor, 4);
+        assert_aligned(&ecs);
+    }
+
+    #[test]
+    fn test_spawn_batch_layout() {
+        #[derive(Component, Debug, PartialEq)]
+        struct D(usize);
+
+        let mut looped = Things::new();
+        let mut batched = Things::new();
+
+        for ecs in [&mut looped, &mut batched] {
+            let a = ecs.create_entity((A, D(0), D(0)));
+            ecs.create_entity((B,));
+            let b = ecs.create_entity((D(0),));
+            let c = ecs.create_entity((C, C, C));
+            ecs.create_entity((A,));
+            ecs.despawn(a);
+            ecs.despawn(b);
+            ecs.despawn(c);
+        }
+
+        let batch = |i| (D(i), B, D(i + 10));
+        let a = (0..4)
+            .map(|i| looped.create_entity(batch(i)))
+            .collect::<Vec<_>>();
+        let b = batched.spawn_batch((0..4).map(batch));
+
+        for (&a, &b) in a.iter().zip(&b) {
+            assert_eq!(looped.references(a), batched.references(b));
+            assert_eq!(
+                batched.get_all::<D>(b).collect::<Vec<_>>(),
+                looped.get_all::<D>(a).collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(batched.references(b[0]), Some((0, 2)));
+        assert_eq!(batched.references(b[1]), Some((4, 2)));
+        assert_eq!(looped.free_rows, batched.free_rows);
+        assert_eq!(looped.component_cursor, batched.component_cursor);
+        assert_eq!(looped.store_lengths(), batched.store_lengths());
+        assert_aligned(&batched);
+    }
+
+    #[test]
+    fn test_stale_entity_misses_recycled_slot() {
+        let mut ecs = Things::new();
+        let stale = ecs.create_entity((A,));
+        ecs.despawn(stale);
+
+        let live = ecs.create_entity((B,));
+        let (stale_slot, stale_generation) = stale.index().into_raw_parts();
+        let (live_slot, live_generation) = live.index().into_raw_parts();
+        assert_eq!(stale_slot, live_slot);
+        assert_ne!(stale_generation, live_generation);
+
+        // The stale handle is no longer a key of the references, so it can't
+        // resolve to the rows of the entity now occupying its slot.
+        assert!(!ecs.entity_component_references.contains_key(&stale));
+        assert_eq!(ecs.references(stale), None);
+        assert_eq!(ecs.references(live), Some((0, 1)));
+
+        assert!(!ecs.is_alive(stale));
+        assert!(ecs.get::<B>(stale).is_none());
+        assert!(!ecs.has_component::<B>(stale));
+        assert!(ecs.entity(stale).is_none());
+        assert!(!ecs.add_component(stale, A));
+        assert!(ecs.remove_component::<B>(stale).is_none());
+        assert!(!ecs.despawn(stale));
+
+        assert!(ecs.has_component::<B>(live));
+        assert!(!ecs.has_component::<A>(live));
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Things>();
+        assert_send_sync::<Schedule>();
     }
 }
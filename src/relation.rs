@@ -0,0 +1,278 @@
+use crate::{store::ComponentStores, Entity, HashMap, Relation, Things};
+use alloc::vec::Vec;
+use core::{any::{Any, TypeId},
+           hash::Hash,
+           ops::Range};
+
+/// Reads the entity a relation component refers to, with the component type
+/// erased.
+type Target = fn(&dyn Any) -> Entity;
+
+/// Makes a relation component refer to another entity, with the component type
+/// erased.
+type SetTarget = fn(&mut dyn Any, Entity);
+
+/// Removes the components of a relation type held by an owner that refer to
+/// the despawned entity.
+pub(crate) type Unlink = fn(&mut Things, Entity, Entity);
+
+/// `Relations` indexes the components of the registered [`Relation`] types by
+/// the entity they refer to, so the components referring to a despawned entity
+/// are found without visiting those referring to other entities.
+///
+/// The index is kept up to date along with the hooks, so the target of a
+/// component is read when it is added or removed. Components whose target is
+/// changed in place are found by their ticks, and indexed again by
+/// [`Relations::refresh`] before the owners of a despawned entity are taken.
+#[derive(Default)]
+pub(crate) struct Relations {
+    types: HashMap<TypeId, (Target, SetTarget, Unlink)>,
+    index: Index,
+
+    /// indexed is the tick of the last pass over the components changed in
+    /// place.
+    indexed: usize,
+}
+
+#[derive(Default)]
+struct Index {
+    /// owners lists the relation type and the owner of every component
+    /// referring to an entity, in the order in which they were added. An owner
+    /// is listed once for each of its components.
+    owners: HashMap<Entity, Vec<(TypeId, Entity)>>,
+
+    /// targets lists the entities referred to by the components of each
+    /// relation type held by an owner, as they were last read.
+    targets: HashMap<(TypeId, Entity), Vec<Entity>>,
+}
+
+impl Relations {
+    pub(crate) fn register<C: Relation>(&mut self, unlink: Unlink) {
+        let target: Target = |component| component.downcast_ref::<C>().unwrap().target();
+        let set_target: SetTarget = |component, target| {
+            component.downcast_mut::<C>().unwrap().set_target(target);
+        };
+        self.types
+            .insert(TypeId::of::<C>(), (target, set_target, unlink));
+    }
+
+    /// Rebuilds the index from all components in the stores, such as after a
+    /// relation type is registered, or after a world is loaded.
+    pub(crate) fn index(
+        &mut self,
+        stores: &ComponentStores,
+        references: &HashMap<Entity, (usize, usize)>,
+        tick: usize,
+    ) {
+        self.clear();
+        self.indexed = tick;
+
+        let mut references = references
+            .iter()
+            .map(|(&entity, &references)| (entity, references))
+            .collect::<Vec<_>>();
+        references.sort_unstable_by_key(|&(_, (position, _))| position);
+
+        for (owner, (position, len)) in references {
+            self.added_rows(stores, owner, position..position + len);
+        }
+    }
+
+    /// Makes the components stored in the given rows refer to the handles their
+    /// targets are mapped to, such as after the entities of another world are
+    /// merged into the rows. Components whose target is not mapped are dropped,
+    /// without being indexed.
+    pub(crate) fn retarget(
+        &self,
+        stores: &mut ComponentStores,
+        rows: Range<usize>,
+        handles: &HashMap<Entity, Entity>,
+    ) {
+        for (id, &(target, set_target, _)) in &self.types {
+            let store = match stores.get_mut(id) {
+                Some(store) => store,
+                None => continue,
+            };
+
+            for row in rows.clone() {
+                let handle = match store.any(row) {
+                    Some(component) => handles.get(&target(component)).copied(),
+                    None => continue,
+                };
+
+                match handle {
+                    Some(handle) => set_target(store.any_mut(row).unwrap(), handle),
+                    None => store.discard(row),
+                }
+            }
+        }
+    }
+
+    /// Indexes a single component, after it was added to the owner.
+    pub(crate) fn added(&mut self, owner: Entity, id: TypeId, component: &dyn Any) {
+        if let Some(&(target, ..)) = self.types.get(&id) {
+            self.index.list(target(component), id, owner);
+        }
+    }
+
+    /// Drops a single component from the index, before it is removed from the
+    /// owner.
+    pub(crate) fn removed(&mut self, owner: Entity, id: TypeId, component: &dyn Any) {
+        if let Some(&(target, ..)) = self.types.get(&id) {
+            self.index.unlist(target(component), id, owner);
+        }
+    }
+
+    /// Indexes the components stored in the given rows of the owner.
+    pub(crate) fn added_rows(
+        &mut self,
+        stores: &ComponentStores,
+        owner: Entity,
+        rows: Range<usize>,
+    ) {
+        for (&id, &(target, ..)) in &self.types {
+            let store = match stores.get(&id) {
+                Some(store) => store,
+                None => continue,
+            };
+
+            for component in rows.clone().filter_map(|row| store.any(row)) {
+                self.index.list(target(component), id, owner);
+            }
+        }
+    }
+
+    /// Drops the components stored in the given rows of the owner from the
+    /// index.
+    pub(crate) fn removed_rows(
+        &mut self,
+        stores: &ComponentStores,
+        owner: Entity,
+        rows: Range<usize>,
+    ) {
+        for (&id, &(target, ..)) in &self.types {
+            let store = match stores.get(&id) {
+                Some(store) => store,
+                None => continue,
+            };
+
+            for component in rows.clone().filter_map(|row| store.any(row)) {
+                self.index.unlist(target(component), id, owner);
+            }
+        }
+    }
+
+    /// Indexes again the components whose target may have been changed in
+    /// place since the last pass, that is those changed at or after its tick,
+    /// along with the components of owners that are no longer alive.
+    pub(crate) fn refresh(
+        &mut self,
+        stores: &ComponentStores,
+        references: &HashMap<Entity, (usize, usize)>,
+        tick: usize,
+    ) {
+        let rows = |owner| {
+            references
+                .get(&owner)
+                .map(|&(position, len)| position..position + len)
+        };
+
+        let indexed = self.indexed;
+        let mut changed = self
+            .index
+            .targets
+            .keys()
+            .copied()
+            .filter(|&(id, owner)| match (stores.get(&id), rows(owner)) {
+                (Some(store), Some(mut rows)) => {
+                    rows.any(|row| store.changed(row).is_some_and(|changed| changed >= indexed))
+                },
+                _ => true,
+            })
+            .collect::<Vec<_>>();
+        changed.sort_unstable_by_key(|&(id, owner)| (rows(owner).map(|rows| rows.start), id));
+
+        for (id, owner) in changed {
+            for target in self.index.targets.remove(&(id, owner)).unwrap() {
+                self.index.unlist(target, id, owner);
+            }
+
+            let target = self.types[&id].0;
+            if let (Some(store), Some(rows)) = (stores.get(&id), rows(owner)) {
+                for component in rows.filter_map(|row| store.any(row)) {
+                    self.index.list(target(component), id, owner);
+                }
+            }
+        }
+
+        self.indexed = tick;
+    }
+
+    /// Takes the relation types and owners of the components referring to the
+    /// despawned entity out of the index.
+    pub(crate) fn take(&mut self, despawned: Entity) -> Option<Vec<(TypeId, Entity)>> {
+        let owners = self.index.owners.remove(&despawned)?;
+
+        for &(id, owner) in &owners {
+            remove_one(&mut self.index.targets, (id, owner), despawned);
+        }
+
+        Some(owners)
+    }
+
+    /// Returns the function unlinking the components of a registered relation
+    /// type.
+    pub(crate) fn unlink(&self, id: &TypeId) -> Unlink {
+        self.types[id].2
+    }
+
+    /// Drops all components from the index, keeping the registered types.
+    pub(crate) fn clear(&mut self) {
+        self.index.owners.clear();
+        self.index.targets.clear();
+    }
+}
+
+impl Index {
+    fn list(&mut self, target: Entity, id: TypeId, owner: Entity) {
+        self.owners.entry(target).or_default().push((id, owner));
+        self.targets.entry((id, owner)).or_default().push(target);
+    }
+
+    fn unlist(&mut self, target: Entity, id: TypeId, owner: Entity) {
+        // The owners of a despawned entity are taken out of the index before
+        // their components are removed, and a component whose target was
+        // changed in place is listed under its previous target until the next
+        // refresh.
+        if remove_one(&mut self.owners, target, (id, owner)) {
+            remove_one(&mut self.targets, (id, owner), target);
+        }
+    }
+}
+
+/// Removes a single occurrence of the value from the list under the key,
+/// dropping the list once it is empty, and returns whether it was found.
+fn remove_one<K, V>(lists: &mut HashMap<K, Vec<V>>, key: K, value: V) -> bool
+where
+    K: Eq + Hash,
+    V: PartialEq,
+{
+    let list = match lists.get_mut(&key) {
+        Some(list) => list,
+        None => return false,
+    };
+
+    let found = match list.iter().position(|entry| *entry == value) {
+        Some(index) => {
+            list.remove(index);
+            true
+        },
+        None => false,
+    };
+
+    if list.is_empty() {
+        lists.remove(&key);
+    }
+
+    found
+}
@@ -0,0 +1,198 @@
+use crate::{borrow::BorrowToken,
+            system::{Data, Query, QueryElement},
+            HashMap, Things};
+use alloc::boxed::Box;
+use core::{any::{type_name, Any, TypeId},
+           cell::UnsafeCell,
+           marker::PhantomData,
+           ops::{Deref, DerefMut},
+           sync::atomic::{AtomicBool, Ordering}};
+
+/// `Resources` stores global data that does not belong to any entity, such as
+/// the frame delta time, or the state of the input devices.
+///
+/// Only a single resource of each type can be stored at any given time.
+#[derive(Default)]
+pub(crate) struct Resources(HashMap<TypeId, Box<dyn Any + Send + Sync>>);
+
+/// `Resource` holds a single resource, along with a flag that is set while the
+/// resource is mutably borrowed through a [`ResMut`] query element.
+pub struct Resource<R> {
+    value: UnsafeCell<R>,
+    borrowed: AtomicBool,
+}
+
+// This is safe, because the value is only mutated through a shared reference
+// by a `ResGuard`, which requires the borrow flag to be set, and shared
+// references are only handed out while it is not.
+unsafe impl<R: Send + Sync> Sync for Resource<R> {}
+
+impl<R> Resource<R> {
+    fn get(&self) -> &R {
+        assert!(
+            !self.borrowed.load(Ordering::Acquire),
+            "resource `{}` is already borrowed mutably",
+            type_name::<R>()
+        );
+
+        unsafe { &*self.value.get() }
+    }
+
+    fn borrow_mut(&self) -> ResGuard<'_, R> {
+        assert!(
+            !self.borrowed.swap(true, Ordering::Acquire),
+            "resource `{}` is already borrowed mutably",
+            type_name::<R>()
+        );
+
+        ResGuard {
+            resource: self,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl Resources {
+    pub(crate) fn insert<R: Send + Sync + 'static>(&mut self, resource: R) {
+        let resource = Resource {
+            value: UnsafeCell::new(resource),
+            borrowed: AtomicBool::new(false),
+        };
+
+        self.0.insert(TypeId::of::<R>(), Box::new(resource));
+    }
+
+    pub(crate) fn get<R: 'static>(&self) -> Option<&R> {
+        self.resource::<R>().map(Resource::get)
+    }
+
+    pub(crate) fn get_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.0
+            .get_mut(&TypeId::of::<R>())
+            .and_then(|resource| resource.downcast_mut::<Resource<R>>())
+            .map(|resource| resource.value.get_mut())
+    }
+
+    fn resource<R: 'static>(&self) -> Option<&Resource<R>> {
+        self.0
+            .get(&TypeId::of::<R>())
+            .and_then(|resource| resource.downcast_ref::<Resource<R>>())
+    }
+}
+
+/// `Res` requests read-only access to a resource within a `Query`.
+///
+/// The same resource reference is yielded for every row of the query. If the
+/// resource does not exist, the query yields no rows.
+pub struct Res<R: 'static>(R);
+
+impl<'a, R: 'static> QueryElement<'a> for Res<R> {
+    type Item = &'a R;
+    type Kind = Data;
+    type Slots = Option<&'a R>;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        things.resources.get::<R>()
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        *slots
+    }
+
+    fn skip(_slots: &mut Self::Slots, _rows: usize) {}
+}
+
+/// `ResMut` requests mutable access to a resource, as a [`Query`] of its own.
+///
+/// The query yields a single [`ResGuard`], which dereferences to the resource,
+/// or `None` if the resource does not exist. Unlike [`Res`], it is not an
+/// element of a query yielded for every row, as the rows of a query can be
+/// alive at the same time, such as when they are collected, and only one
+/// mutable reference to the resource can exist. It is combined with the
+/// queries of components through a [`QuerySet`](crate::QuerySet) instead:
+///
+/// ```
+/// # use things::{Component, QuerySet, Read, ResMut, Things};
+/// # #[derive(Component, Debug)]
+/// # struct Coins(u32);
+/// struct Score(u32);
+///
+/// let mut ecs = Things::new();
+/// ecs.insert_resource(Score(0));
+/// ecs.create_entity((Coins(10),));
+/// ecs.create_entity((Coins(20),));
+///
+/// let (score, coins) = ecs.query::<QuerySet<(ResMut<Score>, Read<Coins>)>>().unwrap();
+/// let coins = coins.collect::<Vec<_>>();
+///
+/// score.unwrap().0 += coins.iter().map(|coins| coins.0).sum::<u32>();
+/// assert_eq!(ecs.get_resource::<Score>().unwrap().0, 30);
+/// ```
+pub struct ResMut<R: 'static>(R);
+
+impl<'a, R: 'static> Query<'a> for ResMut<R> {
+    type Borrow = Self;
+    type Iter = Option<ResGuard<'a, R>>;
+
+    fn iter(things: &'a Things, _: BorrowToken) -> Self::Iter {
+        things.resources.resource::<R>().map(Resource::borrow_mut)
+    }
+}
+
+/// `ResGuard` is a mutable borrow of a resource, yielded by a [`ResMut`]
+/// query.
+pub struct ResGuard<'a, R> {
+    resource: &'a Resource<R>,
+
+    /// marker keeps the guard from being sent to, or shared with other threads.
+    marker: PhantomData<*mut R>,
+}
+
+impl<R> Deref for ResGuard<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        unsafe { &*self.resource.value.get() }
+    }
+}
+
+impl<R> DerefMut for ResGuard<'_, R> {
+    fn deref_mut(&mut self) -> &mut R {
+        unsafe { &mut *self.resource.value.get() }
+    }
+}
+
+impl<R> Drop for ResGuard<'_, R> {
+    fn drop(&mut self) {
+        self.resource.borrowed.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_res_guard() {
+        let mut resources = Resources::default();
+        resources.insert(1usize);
+
+        let resource = resources.resource::<usize>().unwrap();
+        let mut guard = resource.borrow_mut();
+        *guard += 1;
+        drop(guard);
+
+        assert_eq!(resources.get::<usize>(), Some(&2));
+    }
+
+    #[test]
+    #[should_panic(expected = "already borrowed mutably")]
+    fn test_res_guard_twice() {
+        let mut resources = Resources::default();
+        resources.insert(1usize);
+
+        let resource = resources.resource::<usize>().unwrap();
+        let _guard = resource.borrow_mut();
+        resource.borrow_mut();
+    }
+}
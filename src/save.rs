@@ -0,0 +1,454 @@
+use crate::{entity::Entity,
+            store::{ComponentStore, ComponentStores, Store},
+            Component, HashMap, Things};
+use serde::{de::{self, DeserializeOwned, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+            ser::{SerializeMap, SerializeTuple},
+            Deserialize, Deserializer, Serialize, Serializer};
+use std::{any::{type_name, TypeId},
+          fmt};
+
+type SerializeFn = fn(&dyn ComponentStore) -> Box<dyn erased_serde::Serialize + '_>;
+type DeserializeFn = fn(
+    &mut dyn erased_serde::Deserializer<'_>,
+    usize,
+) -> Result<Box<dyn ComponentStore>, erased_serde::Error>;
+
+/// `Registry` holds the (de)serialization functions of the component types
+/// that are saved along with the world.
+///
+/// Since the stores are type-erased, the concrete component type of a store can
+/// only be recovered through the functions registered for its type ID.
+#[derive(Default)]
+pub(crate) struct Registry(HashMap<TypeId, Registration>);
+
+struct Registration {
+    /// name identifies the component type in the serialized world.
+    name: &'static str,
+    serialize: SerializeFn,
+    deserialize: DeserializeFn,
+}
+
+impl Registry {
+    fn insert<C>(&mut self)
+    where
+        C: Component + Serialize + DeserializeOwned,
+    {
+        let registration = Registration {
+            name: type_name::<C>(),
+            serialize: serialize_store::<C>,
+            deserialize: deserialize_store::<C>,
+        };
+
+        self.0.insert(TypeId::of::<C>(), registration);
+    }
+
+    fn find(&self, name: &str) -> Option<(TypeId, &Registration)> {
+        self.0
+            .iter()
+            .find(|(_, registration)| registration.name == name)
+            .map(|(&id, registration)| (id, registration))
+    }
+}
+
+fn serialize_store<C>(store: &dyn ComponentStore) -> Box<dyn erased_serde::Serialize + '_>
+where
+    C: Component + Serialize,
+{
+    Box::new(Components(store.expect_store::<C>()))
+}
+
+fn deserialize_store<C>(
+    deserializer: &mut dyn erased_serde::Deserializer<'_>,
+    tick: usize,
+) -> Result<Box<dyn ComponentStore>, erased_serde::Error>
+where
+    C: Component + DeserializeOwned,
+{
+    let components: Vec<Option<C>> = erased_serde::deserialize(deserializer)?;
+    let slots = components.len();
+
+    let mut store = C::Storage::default();
+    Store::set_tick(&mut store, tick);
+
+    for (position, component) in components.into_iter().enumerate() {
+        if let Some(component) = component {
+            store.push(position, component);
+        }
+    }
+
+    Store::pad(&mut store, slots);
+    Ok(Box::new(store))
+}
+
+/// Serializes all positions of a store, including the empty ones, to keep the
+/// components aligned to their entities.
+struct Components<'a, S>(&'a S);
+
+impl<S> Serialize for Components<'_, S>
+where
+    S: Store,
+    S::Item: Serialize,
+{
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+/// Serializes the stores of all registered component types, keyed by the name
+/// of their type.
+struct Stores<'a>(&'a Things);
+
+impl Serialize for Stores<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Stores(things) = self;
+        let mut map = serializer.serialize_map(None)?;
+
+        for (id, store) in things.component_stores.iter() {
+            if let Some(registration) = things.registry.0.get(id) {
+                map.serialize_entry(registration.name, &(registration.serialize)(store))?;
+            }
+        }
+
+        map.end()
+    }
+}
+
+/// The state of a deserialized world, before it replaces the current one.
+struct World {
+    entities: generational_arena::Arena<()>,
+    references: Vec<(Entity, (usize, usize))>,
+    cursor: usize,
+    tick: usize,
+    stores: ComponentStores,
+}
+
+struct WorldSeed<'a> {
+    registry: &'a Registry,
+}
+
+impl<'de> DeserializeSeed<'de> for WorldSeed<'_> {
+    type Value = World;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<World, D::Error> {
+        deserializer.deserialize_tuple(5, self)
+    }
+}
+
+impl<'de> Visitor<'de> for WorldSeed<'_> {
+    type Value = World;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a world of things")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<World, A::Error> {
+        let missing = |index| de::Error::invalid_length(index, &self);
+
+        let entities = seq.next_element()?.ok_or_else(|| missing(0))?;
+        let references = seq.next_element()?.ok_or_else(|| missing(1))?;
+        let cursor = seq.next_element()?.ok_or_else(|| missing(2))?;
+        let tick = seq.next_element()?.ok_or_else(|| missing(3))?;
+        let stores = seq
+            .next_element_seed(StoresSeed {
+                registry: self.registry,
+                tick,
+            })?
+            .ok_or_else(|| missing(4))?;
+
+        Ok(World {
+            entities,
+            references,
+            cursor,
+            tick,
+            stores,
+        })
+    }
+}
+
+struct StoresSeed<'a> {
+    registry: &'a Registry,
+    tick: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for StoresSeed<'_> {
+    type Value = ComponentStores;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for StoresSeed<'_> {
+    type Value = ComponentStores;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of component stores")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut stores = ComponentStores::default();
+
+        while let Some(name) = map.next_key::<String>()? {
+            let (id, registration) = self.registry.find(&name).ok_or_else(|| {
+                de::Error::custom(format!("unregistered component type `{}`", name))
+            })?;
+
+            let store = map.next_value_seed(StoreSeed {
+                registration,
+                tick: self.tick,
+            })?;
+
+            stores.insert(id, store);
+        }
+
+        Ok(stores)
+    }
+}
+
+struct StoreSeed<'a> {
+    registration: &'a Registration,
+    tick: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for StoreSeed<'_> {
+    type Value = Box<dyn ComponentStore>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let mut deserializer = <dyn erased_serde::Deserializer>::erase(deserializer);
+
+        (self.registration.deserialize)(&mut deserializer, self.tick).map_err(de::Error::custom)
+    }
+}
+
+/// Entities are serialized as a `(slot, generation)` pair, the raw parts of
+/// their generational index.
+///
+/// The entities of a world are saved along with the slots and generations they
+/// occupy, and loaded into the same slots with the same generations, so
+/// entities referenced by the fields of components keep resolving to the same
+/// entities after loading.
+impl Serialize for Entity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.index().into_raw_parts().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Entity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (slot, generation) = Deserialize::deserialize(deserializer)?;
+        Ok(Entity::from(generational_arena::Index::from_raw_parts(
+            slot, generation,
+        )))
+    }
+}
+
+impl Things {
+    /// Registers a component type to be saved and loaded along with the world.
+    ///
+    /// The components are identified by the name of their type, so a world can
+    /// only be loaded by a build of the program that registered the same types.
+    pub fn register_serializable<C>(&mut self)
+    where
+        C: Component + Serialize + DeserializeOwned,
+    {
+        self.registry.insert::<C>();
+    }
+
+    /// Saves the entities, and their components of all registered types, using
+    /// the given serializer.
+    ///
+    /// Components of types that are not registered are left out. The tick of
+    /// the world is saved, but resources, events, and the ticks at which the
+    /// components were added or changed are not. The generations of despawned
+    /// entities are not kept either, so handles to entities that were despawned
+    /// before saving should not be used after loading.
+    pub fn save<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let references = self.entity_component_references.iter().collect::<Vec<_>>();
+
+        let mut tuple = serializer.serialize_tuple(5)?;
+        tuple.serialize_element(&self.entities)?;
+        tuple.serialize_element(&references)?;
+        tuple.serialize_element(&self.component_cursor)?;
+        tuple.serialize_element(&self.change_tick)?;
+        tuple.serialize_element(&Stores(self))?;
+        tuple.end()
+    }
+
+    /// Replaces all entities and components with those of a world saved using
+    /// [`Things::save`].
+    ///
+    /// The component types of the saved world have to be registered before
+    /// loading it. The tick of the saved world is restored, and the loaded
+    /// components are recorded as added at that tick. Systems are considered
+    /// not to have run yet, so they see every loaded component as added. If
+    /// loading fails, the world is left unchanged.
+    pub fn load<'de, D: Deserializer<'de>>(&mut self, deserializer: D) -> Result<(), D::Error> {
+        let seed = WorldSeed {
+            registry: &self.registry,
+        };
+
+        let World {
+            entities,
+            references,
+            cursor,
+            tick,
+            mut stores,
+        } = seed.deserialize(deserializer)?;
+
+        if let Some(&(_, (position, len))) = references
+            .iter()
+            .find(|(_, (position, len))| position + len > cursor)
+        {
+            return Err(de::Error::custom(format!(
+                "components at {}..{} are out of bounds",
+                position,
+                position + len
+            )));
+        }
+
+        for store in stores.values_mut() {
+            store.pad(cursor);
+        }
+
+        // The rows between the loaded entities were vacated by despawned
+        // entities, so they are free to be reused.
+        let mut ranges = references
+            .iter()
+            .map(|&(_, range)| range)
+            .collect::<Vec<_>>();
+        ranges.sort_unstable();
+
+        let mut free_rows = Vec::new();
+        let mut row = 0;
+        for (position, len) in ranges.into_iter().chain(Some((cursor, 0))) {
+            if position > row {
+                free_rows.push((row, position - row));
+            }
+            row = std::cmp::max(row, position + len);
+        }
+
+        self.entities = entities;
+        self.free_rows = free_rows;
+        self.entity_component_references = references.into_iter().collect();
+        self.component_cursor = cursor;
+
+        // The generations of the loaded entities are unrelated to those of the
+        // entities they replace.
+        self.generation = 0;
+        self.component_stores = stores;
+        self.change_tick = tick;
+        self.system_ticks.clear();
+        self.hooks.relations.index(
+            &self.component_stores,
+            &self.entity_component_references,
+            tick,
+        );
+
+        self.archetypes.invalidate();
+        self.refresh_archetypes();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Read, SparseStore};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Component, Debug, PartialEq, Serialize, Deserialize)]
+    struct Position(i32, i32);
+
+    #[derive(Component, Debug, PartialEq, Serialize, Deserialize)]
+    #[component(storage = "SparseStore")]
+    struct Name(String);
+
+    #[derive(Component, Debug)]
+    struct Cache;
+
+    #[derive(Component, Debug, PartialEq, Serialize, Deserialize)]
+    struct Target(Entity);
+
+    fn world() -> Things {
+        let mut ecs = Things::new();
+        ecs.register_serializable::<Position>();
+        ecs.register_serializable::<Name>();
+        ecs
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut ecs = world();
+        let a = ecs.create_entity((Position(1, 2), Name("a".to_owned())));
+        let b = ecs.create_entity((Position(3, 4), Cache));
+        let c = ecs.create_entity((Name("c".to_owned()),));
+        ecs.despawn(b);
+        ecs.advance_tick();
+
+        let json = ecs.save(serde_json::value::Serializer).unwrap();
+
+        let mut loaded = world();
+        loaded.load(json).unwrap();
+
+        assert_eq!(loaded.tick(), 2);
+        assert!(loaded.is_alive(a));
+        assert!(!loaded.is_alive(b));
+        assert!(loaded.is_alive(c));
+        assert_eq!(loaded.get::<Position>(a), Some(&Position(1, 2)));
+        assert_eq!(loaded.get::<Name>(c), Some(&Name("c".to_owned())));
+        assert_eq!(loaded.get::<Position>(c), None);
+
+        let positions = loaded
+            .query::<Read<Position>>()
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![&Position(1, 2)]);
+
+        let d = loaded.create_entity((Cache,));
+        assert_ne!(d, a);
+        assert_ne!(d, c);
+        assert_eq!(loaded.component_cursor, 3);
+    }
+
+    #[test]
+    fn test_save_and_load_entity_references() {
+        let mut ecs = world();
+        ecs.register_serializable::<Target>();
+
+        let a = ecs.create_entity((Position(1, 2),));
+        let b = ecs.create_entity((Position(3, 4),));
+        ecs.despawn(a);
+        let c = ecs.create_entity((Position(5, 6), Target(b)));
+        let d = ecs.create_entity((Target(c),));
+
+        let (slot, generation) = c.index().into_raw_parts();
+        assert_eq!(
+            serde_json::to_value(c).unwrap(),
+            serde_json::json!([slot, generation])
+        );
+
+        let json = ecs.save(serde_json::value::Serializer).unwrap();
+        let mut loaded = world();
+        loaded.register_serializable::<Target>();
+        loaded.load(json).unwrap();
+
+        let Target(target) = *loaded.get::<Target>(d).unwrap();
+        assert_eq!(target, c);
+        assert_eq!(loaded.get::<Position>(target), Some(&Position(5, 6)));
+
+        let Target(target) = *loaded.get::<Target>(target).unwrap();
+        assert_eq!(target, b);
+        assert_eq!(loaded.get::<Position>(target), Some(&Position(3, 4)));
+    }
+
+    #[test]
+    fn test_load_unregistered_component() {
+        let mut ecs = world();
+        ecs.create_entity((Position(1, 2),));
+        let json = ecs.save(serde_json::value::Serializer).unwrap();
+
+        let mut loaded = Things::new();
+        let err = loaded.load(json).unwrap_err();
+        assert!(err.to_string().contains("unregistered component type"));
+    }
+}
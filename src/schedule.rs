@@ -0,0 +1,217 @@
+use crate::{borrow::{self, BorrowResult, RegisterBorrow},
+            BorrowConflict, BorrowError, Query, StatefulSystem, System, Things};
+use alloc::{boxed::Box, vec::Vec};
+use core::any::type_name;
+
+type RunSystem = Box<dyn FnMut(&mut Things) -> Result<(), BorrowError> + Send + Sync>;
+
+struct ScheduledSystem {
+    /// name is the type name of the system, used to report conflicts.
+    name: &'static str,
+
+    /// register_borrow registers the borrows of the query of the system.
+    register_borrow: fn() -> BorrowResult,
+
+    run: RunSystem,
+}
+
+/// `Schedule` holds a list of systems, to be executed in the order in which
+/// they were added.
+///
+/// Systems are registered once, after which the whole schedule can be run as
+/// often as needed, such as once every frame:
+///
+/// ```
+/// # use things::{Component, Query, Read, Schedule, System, Things, Write};
+/// # #[derive(Component, Debug)]
+/// # struct Velocity(u8);
+/// # #[derive(Component, Debug)]
+/// # struct Position(u32);
+/// struct Movement;
+/// impl<'a> System<'a> for Movement {
+///     type Query = (Read<Velocity>, Write<Position>);
+///
+///     fn update(components: <Self::Query as Query<'a>>::Iter) {
+///         for (velocity, position) in components {
+///             position.0 += u32::from(velocity.0);
+///         }
+///     }
+/// }
+///
+/// let mut ecs = Things::new();
+/// ecs.create_entity((Velocity(1), Position(10)));
+///
+/// let mut schedule = Schedule::new();
+/// schedule.add_system(Movement);
+/// schedule.run(&mut ecs).unwrap();
+/// ```
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<ScheduledSystem>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a system to the end of the schedule.
+    pub fn add_system<S>(&mut self, _: S) -> &mut Self
+    where
+        S: for<'a> System<'a> + 'static,
+        for<'a> <<S as System<'a>>::Query as Query<'a>>::Borrow: RegisterBorrow,
+    {
+        self.systems.push(ScheduledSystem {
+            name: type_name::<S>(),
+            register_borrow: borrow::register::<<<S as System>::Query as Query>::Borrow>,
+            run: Box::new(|things: &mut Things| things.execute_system::<S>()),
+        });
+        self
+    }
+
+    /// Adds a system to the end of the schedule, which is only executed if the
+    /// condition holds.
+    ///
+    /// The condition is evaluated every time the schedule reaches the system.
+    /// If it returns `false`, the system is skipped, including the validation
+    /// of its borrows. This allows systems to depend on the state of the game,
+    /// such as a resource:
+    ///
+    /// ```
+    /// # use things::{Component, Query, Schedule, System, Things, Write};
+    /// # #[derive(Component, Debug)]
+    /// # struct Position(u32);
+    /// # struct Movement;
+    /// # impl<'a> System<'a> for Movement {
+    /// #     type Query = Write<Position>;
+    /// #     fn update(components: <Self::Query as Query<'a>>::Iter) {
+    /// #         components.for_each(|position| position.0 += 1);
+    /// #     }
+    /// # }
+    /// #[derive(PartialEq)]
+    /// enum GameState {
+    ///     Running,
+    ///     Paused,
+    /// }
+    ///
+    /// let mut ecs = Things::new();
+    /// let entity = ecs.create_entity((Position(10),));
+    /// ecs.insert_resource(GameState::Paused);
+    ///
+    /// let mut schedule = Schedule::new();
+    /// schedule.add_system_with_condition(Movement, |things: &Things| {
+    ///     things.get_resource::<GameState>() == Some(&GameState::Running)
+    /// });
+    ///
+    /// schedule.run(&mut ecs).unwrap();
+    /// assert_eq!(ecs.get::<Position>(entity).unwrap().0, 10);
+    ///
+    /// ecs.insert_resource(GameState::Running);
+    /// schedule.run(&mut ecs).unwrap();
+    /// assert_eq!(ecs.get::<Position>(entity).unwrap().0, 11);
+    /// ```
+    pub fn add_system_with_condition<S, F>(&mut self, _: S, condition: F) -> &mut Self
+    where
+        S: for<'a> System<'a> + 'static,
+        for<'a> <<S as System<'a>>::Query as Query<'a>>::Borrow: RegisterBorrow,
+        F: Fn(&Things) -> bool + Send + Sync + 'static,
+    {
+        self.systems.push(ScheduledSystem {
+            name: type_name::<S>(),
+            register_borrow: borrow::register::<<<S as System>::Query as Query>::Borrow>,
+            run: Box::new(move |things: &mut Things| {
+                if !condition(things) {
+                    return Ok(());
+                }
+
+                things.execute_system::<S>()
+            }),
+        });
+        self
+    }
+
+    /// Adds a system that keeps state between runs to the end of the schedule.
+    ///
+    /// The schedule owns the system, so its state is kept for as long as the
+    /// schedule is.
+    pub fn add_stateful_system<S>(&mut self, mut system: S) -> &mut Self
+    where
+        S: for<'a> StatefulSystem<'a> + Send + Sync + 'static,
+        for<'a> <<S as StatefulSystem<'a>>::Query as Query<'a>>::Borrow: RegisterBorrow,
+    {
+        self.systems.push(ScheduledSystem {
+            name: type_name::<S>(),
+            register_borrow: borrow::register::<<<S as StatefulSystem>::Query as Query>::Borrow>,
+            run: Box::new(move |things: &mut Things| {
+                things.execute_stateful_system::<S>(&mut system)
+            }),
+        });
+        self
+    }
+
+    /// Executes all systems in the order in which they were added.
+    ///
+    /// Each run is considered a single frame, so the events sent since the
+    /// previous run become readable before the systems are executed. See
+    /// [`Things::update_events`].
+    ///
+    /// If the borrows of a system are invalid, the error is returned, and the
+    /// remaining systems are not executed.
+    pub fn run(&mut self, things: &mut Things) -> Result<(), BorrowError> {
+        things.update_events();
+        self.systems
+            .iter_mut()
+            .try_for_each(|system| (system.run)(things))
+    }
+
+    /// Validates the borrows of all systems, without executing any of them.
+    ///
+    /// Unlike [`Schedule::run`], which stops at the first system whose borrows
+    /// are invalid, every conflict is reported: those within the borrows of a
+    /// single system, and those between every pair of systems, along with the
+    /// type names of the systems and of the conflicting component.
+    ///
+    /// The systems of a schedule are executed one after another, so conflicts
+    /// between two systems don't prevent the schedule from running, but do
+    /// prevent the systems from running at the same time, see
+    /// [`Things::execute_systems`].
+    ///
+    /// ```
+    /// # use things::{Component, Query, Read, Schedule, System, Write};
+    /// # #[derive(Component, Debug)]
+    /// # struct Velocity(u8);
+    /// # #[derive(Component, Debug)]
+    /// # struct Position(u32);
+    /// # struct Movement;
+    /// # impl<'a> System<'a> for Movement {
+    /// #     type Query = (Read<Velocity>, Write<Position>);
+    /// #     fn update(_: <Self::Query as Query<'a>>::Iter) {}
+    /// # }
+    /// # struct Render;
+    /// # impl<'a> System<'a> for Render {
+    /// #     type Query = Read<Position>;
+    /// #     fn update(_: <Self::Query as Query<'a>>::Iter) {}
+    /// # }
+    /// let mut schedule = Schedule::new();
+    /// schedule.add_system(Movement).add_system(Render);
+    ///
+    /// let conflicts = schedule.validate().unwrap_err();
+    /// assert_eq!(conflicts.len(), 1);
+    /// assert!(conflicts[0].systems.0.ends_with("Movement"));
+    /// assert!(conflicts[0].systems.1.ends_with("Render"));
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<BorrowConflict>> {
+        let borrows = self
+            .systems
+            .iter()
+            .map(|system| (system.name, (system.register_borrow)()))
+            .collect::<Vec<_>>();
+
+        let conflicts = borrow::conflicts(&borrows);
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(conflicts)
+        }
+    }
+}
@@ -1,7 +1,13 @@
-use crate::Component;
-use core::fmt::Debug;
+use crate::{Component, HashMap};
+use alloc::{boxed::Box, vec::Vec};
+use core::{any::{type_name, Any, TypeId},
+           cell::UnsafeCell,
+           fmt::Debug,
+           iter::{Enumerate, Flatten},
+           marker::PhantomData,
+           mem,
+           ptr::{self, NonNull}};
 use downcast_rs::{impl_downcast, Downcast};
-use std::cell::UnsafeCell;
 
 /// `Store` must be implemented by any object that wants to store
 /// [`Component`]s.
@@ -13,119 +19,1332 @@ use std::cell::UnsafeCell;
 /// performance for different use-cases.
 ///
 /// This library provides several store implementations to start with, but more
-/// specialised implementations can be used when required.
+/// specialised implementations can be used when required:
+///
+/// * [`DefaultStore`] keeps one slot per position, and is the best fit for
+///   components held by most entities.
+/// * [`SparseStore`] only keeps the components that are actually stored, and is
+///   the best fit for components held by few entities.
+/// * [`TagStore`] only records which positions hold a component, and is used
+///   for zero-sized marker components.
 ///
 /// [s]: https://en.wikipedia.org/wiki/AOS_and_SOA
-pub trait Store: Sized + Default + Debug {
+pub trait Store: Sized + Default + Debug + Send + Sync {
     type Item: Component;
 
-    /// `new` initialises a new (empty) store.
-    fn new() -> Self;
+    /// `new` initialises a new (empty) store.
+    fn new() -> Self;
+
+    /// `push` takes a single component, and adds it to the store, recording
+    /// the component as added at the current tick.
+    ///
+    /// The component is stored in the first empty slot at or after the given
+    /// position, padding the store if needed. The `usize` value returned by the
+    /// method indicates the position in the store at which the component is
+    /// stored. This can be used to later retrieve a specific component from the
+    /// store.
+    fn push(&mut self, position: usize, component: Self::Item) -> usize;
+
+    /// `pad` appends `None`s to the store until it holds at least `len` slots.
+    ///
+    /// This is used to keep all stores aligned to the same length, so that
+    /// every position in one store maps to the same entity in another store.
+    fn pad(&mut self, len: usize);
+
+    /// `remove` takes the component out of the store at the given position,
+    /// leaving a `None` in its place.
+    ///
+    /// Returns `None` if there is no component stored at the position.
+    fn remove(&mut self, position: usize) -> Option<Self::Item>;
+
+    /// `swap_remove` takes the component out of the store at the given
+    /// position, and moves the last slot of the store into its place, which
+    /// shrinks the store by one slot.
+    ///
+    /// Unlike [`Store::remove`], which leaves a `None` behind and keeps every
+    /// other component in place, this keeps the store compact, at the cost of
+    /// moving the last component. Both run in constant time. Returns the
+    /// removed component, together with the former position of the
+    /// component that was moved into its place, if any, so references to it
+    /// can be updated.
+    ///
+    /// [`Things`](crate::Things) keeps the positions of all stores aligned to
+    /// the same entities, so it only ever uses `remove` itself.
+    ///
+    /// Returns `None` if there is no component stored at the position, in which
+    /// case the store is left unchanged.
+    fn swap_remove(&mut self, position: usize) -> Option<(Self::Item, Option<usize>)>;
+
+    /// `clear` removes all components and slots from the store, while keeping
+    /// its allocated capacity.
+    fn clear(&mut self);
+
+    /// `reserve` reserves capacity for at least `additional` more components,
+    /// to avoid reallocating while they are pushed.
+    fn reserve(&mut self, additional: usize);
+
+    /// `shrink_to_fit` releases the capacity the store holds beyond what its
+    /// components and slots need. Unlike [`Store::truncate`], the slots
+    /// themselves are kept.
+    fn shrink_to_fit(&mut self);
+
+    /// `truncate` drops every component at or after the given position, and
+    /// shrinks the store to at most `len` slots, while keeping its allocated
+    /// capacity.
+    fn truncate(&mut self, len: usize);
+
+    /// `slots` returns the number of positions in the store, including any
+    /// padding.
+    fn slots(&self) -> usize;
+
+    /// `get` returns a reference to the component at the given position.
+    ///
+    /// Since the store allows for "padding" to be inserted, to allow components
+    /// of the same entity to be aligned across different stores, a `None`
+    /// indicates that the entity stored in that position does not contain the
+    /// component type of this store.
+    fn get(&self, position: usize) -> Option<&Self::Item>;
+
+    /// `get_mut` returns a mutable reference to the component at the given
+    /// position, and records the component as changed at the current tick.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that no other references to the component
+    /// exist for as long as the returned reference is alive. Within the
+    /// library, this is guaranteed by validating the borrows of a query before
+    /// iterating it.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn get_mut(&self, position: usize) -> Option<&mut Self::Item>;
+
+    /// `get_mut_exclusive` returns a mutable reference to the component at the
+    /// given position, the same as [`Store::get_mut`], through an exclusive
+    /// reference to the store, which rules out any other references to the
+    /// component.
+    fn get_mut_exclusive(&mut self, position: usize) -> Option<&mut Self::Item> {
+        // This is safe, because the store is exclusively borrowed for as long
+        // as the returned reference is alive.
+        unsafe { self.get_mut(position) }
+    }
+
+    /// `get_pair_mut` returns mutable references to the components at two
+    /// distinct positions, the same as [`Store::get_mut_exclusive`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if both positions are the same.
+    fn get_pair_mut(
+        &mut self,
+        a: usize,
+        b: usize,
+    ) -> (Option<&mut Self::Item>, Option<&mut Self::Item>) {
+        assert_ne!(a, b, "positions must be distinct");
+
+        // This is safe, because the store is exclusively borrowed for as long
+        // as the returned references are alive, and the positions are
+        // distinct, so the references never overlap.
+        unsafe { (self.get_mut(a), self.get_mut(b)) }
+    }
+
+    /// `set_tick` sets the current tick, at which pushed and mutably accessed
+    /// components are recorded as added and changed.
+    fn set_tick(&mut self, tick: usize);
+
+    /// `ticks` returns when the component at the given position was added, and
+    /// when it was last changed.
+    fn ticks(&self, position: usize) -> Option<Ticks>;
+
+    /// `set_ticks` overwrites the ticks of the component at the given
+    /// position, which allows keeping them when a component is moved.
+    fn set_ticks(&mut self, position: usize, ticks: Ticks);
+
+    /// `iter` returns an iterator over all positions of the store, yielding
+    /// `None` for positions that do not contain a component.
+    fn iter(&self) -> Iter<'_, Self> {
+        Iter {
+            store: self,
+            position: 0,
+        }
+    }
+
+    /// `iter_indexed` returns an iterator over all positions of the store, the
+    /// same as [`Store::iter`], along with the index of each position.
+    fn iter_indexed(&self) -> Enumerate<Iter<'_, Self>> {
+        self.iter().enumerate()
+    }
+
+    /// `iter_present` returns an iterator over the stored components, skipping
+    /// the positions that do not contain a component.
+    fn iter_present(&self) -> Flatten<Iter<'_, Self>> {
+        self.iter().flatten()
+    }
+
+    /// `len` returns the number of stored components, which unlike
+    /// [`Store::slots`] does not include empty positions.
+    fn len(&self) -> usize {
+        self.iter_present().count()
+    }
+
+    /// `is_empty` returns `true` if the store does not contain any components.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `iter_mut` returns an iterator over all positions of the store, yielding
+    /// mutable references to the components.
+    ///
+    /// # Safety
+    ///
+    /// See [`Store::get_mut`].
+    unsafe fn iter_mut(&self) -> IterMut<'_, Self> {
+        IterMut {
+            store: self,
+            position: 0,
+        }
+    }
+}
+
+/// `Ticks` records when a component was added to its store, and when it was
+/// last mutably accessed.
+///
+/// The ticks are used to filter queries on changed components, see
+/// [`Changed`](crate::Changed) and [`Added`](crate::Added).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Ticks {
+    pub added: usize,
+    pub changed: usize,
+}
+
+impl Ticks {
+    fn new(tick: usize) -> Self {
+        Ticks {
+            added: tick,
+            changed: tick,
+        }
+    }
+}
+
+/// An iterator over the positions of a [`Store`].
+///
+/// This `struct` is created by [`Store::iter`].
+#[derive(Debug)]
+pub struct Iter<'a, S: Store> {
+    store: &'a S,
+    position: usize,
+}
+
+impl<S: Store> Iter<'_, S> {
+    /// Skips the given number of positions, without visiting them.
+    pub(crate) fn advance(&mut self, positions: usize) {
+        self.position += positions;
+    }
+}
+
+impl<'a, S: Store> Iterator for Iter<'a, S> {
+    type Item = Option<&'a S::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.store.slots() {
+            return None;
+        }
+
+        self.position += 1;
+        Some(self.store.get(self.position - 1))
+    }
+}
+
+/// A mutable iterator over the positions of a [`Store`].
+///
+/// This `struct` is created by [`Store::iter_mut`].
+#[derive(Debug)]
+pub struct IterMut<'a, S: Store> {
+    store: &'a S,
+    position: usize,
+}
+
+impl<S: Store> IterMut<'_, S> {
+    /// Skips the given number of positions, without visiting them, which keeps
+    /// the skipped components from being marked as changed.
+    pub(crate) fn advance(&mut self, positions: usize) {
+        self.position += positions;
+    }
+}
+
+impl<'a, S: Store> Iterator for IterMut<'a, S> {
+    type Item = Option<&'a mut S::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.store.slots() {
+            return None;
+        }
+
+        // This is safe, because every position is visited once, and the caller
+        // of `Store::iter_mut` guarantees no other references exist.
+        self.position += 1;
+        Some(unsafe { self.store.get_mut(self.position - 1) })
+    }
+}
+
+/// `ComponentStore` is the type-erased counterpart of [`Store`], allowing
+/// stores of different component types to be kept in a single collection.
+pub trait ComponentStore: Downcast + Send + Sync {
+    /// Returns the number of slots in the store, including any padding.
+    fn slots(&self) -> usize;
+
+    /// Appends `None`s to the store until it holds at least `len` slots.
+    fn pad(&mut self, len: usize);
+
+    /// Removes and drops the component at the given position, if any.
+    fn discard(&mut self, position: usize);
+
+    /// Removes the component at the given position, if any, and returns it as
+    /// a type-erased box.
+    fn take(&mut self, position: usize) -> Option<Box<dyn Any + Send + Sync>>;
+
+    /// Moves the component at position `from`, if any, to position `to`.
+    fn relocate(&mut self, from: usize, to: usize);
+
+    /// Returns `true` if a component is stored at the given position.
+    fn contains(&self, position: usize) -> bool;
+
+    /// Returns the component at the given position, if any, for debugging
+    /// purposes.
+    fn debug(&self, position: usize) -> Option<&dyn Debug>;
+
+    /// Returns the component at the given position, if any, with its type
+    /// erased.
+    fn any(&self, position: usize) -> Option<&dyn Any>;
+
+    /// Returns the component at the given position mutably, if any, with its
+    /// type erased, recording it as changed.
+    fn any_mut(&mut self, position: usize) -> Option<&mut dyn Any>;
+
+    /// Returns the type name of the stored components, the number of stored
+    /// components, and the number of slots, including any padding.
+    fn stats(&self) -> (&'static str, usize, usize);
+
+    /// Removes all components and slots, keeping the allocated capacity.
+    fn clear(&mut self);
+
+    /// Reserves capacity for at least `additional` more components.
+    fn reserve(&mut self, additional: usize);
+
+    /// Releases the capacity held beyond what the store needs.
+    fn shrink_to_fit(&mut self);
+
+    /// Drops every component at or after the given position, and shrinks the
+    /// store to at most `len` slots.
+    fn truncate(&mut self, len: usize);
+
+    /// Sets the tick at which components are recorded as added and changed.
+    fn set_tick(&mut self, tick: usize);
+
+    /// Returns the tick at which the component at the given position was last
+    /// changed, if any.
+    fn changed(&self, position: usize) -> Option<usize>;
+
+    /// Returns a new, empty store of the same type.
+    fn empty(&self) -> Box<dyn ComponentStore>;
+
+    /// Moves all components of `other` into this store, shifting their
+    /// positions by `offset`, and recording them as added at the current tick.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` is not a store of the same type.
+    fn append(&mut self, other: &mut dyn ComponentStore, offset: usize);
+}
+impl_downcast!(ComponentStore);
+
+impl<S: Store + 'static> ComponentStore for S {
+    fn slots(&self) -> usize {
+        Store::slots(self)
+    }
+
+    fn pad(&mut self, len: usize) {
+        Store::pad(self, len)
+    }
+
+    fn discard(&mut self, position: usize) {
+        Store::remove(self, position);
+    }
+
+    fn take(&mut self, position: usize) -> Option<Box<dyn Any + Send + Sync>> {
+        Store::remove(self, position).map(|component| Box::new(component) as Box<_>)
+    }
+
+    fn relocate(&mut self, from: usize, to: usize) {
+        let ticks = self.ticks(from);
+
+        if let Some(component) = Store::remove(self, from) {
+            let position = Store::push(self, to, component);
+            if let Some(ticks) = ticks {
+                self.set_ticks(position, ticks);
+            }
+        }
+    }
+
+    fn contains(&self, position: usize) -> bool {
+        self.get(position).is_some()
+    }
+
+    fn debug(&self, position: usize) -> Option<&dyn Debug> {
+        self.get(position).map(|component| component as &dyn Debug)
+    }
+
+    fn any(&self, position: usize) -> Option<&dyn Any> {
+        self.get(position).map(|component| component as &dyn Any)
+    }
+
+    fn any_mut(&mut self, position: usize) -> Option<&mut dyn Any> {
+        self.get_mut_exclusive(position)
+            .map(|component| component as &mut dyn Any)
+    }
+
+    fn stats(&self) -> (&'static str, usize, usize) {
+        (type_name::<S::Item>(), Store::len(self), Store::slots(self))
+    }
+
+    fn clear(&mut self) {
+        Store::clear(self)
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Store::reserve(self, additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        Store::shrink_to_fit(self)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        Store::truncate(self, len)
+    }
+
+    fn set_tick(&mut self, tick: usize) {
+        Store::set_tick(self, tick)
+    }
+
+    fn changed(&self, position: usize) -> Option<usize> {
+        Store::ticks(self, position).map(|ticks| ticks.changed)
+    }
+
+    fn empty(&self) -> Box<dyn ComponentStore> {
+        Box::new(S::default())
+    }
+
+    fn append(&mut self, other: &mut dyn ComponentStore, offset: usize) {
+        let other = other
+            .downcast_mut::<S>()
+            .expect("stores of different types");
+
+        for position in 0..Store::slots(other) {
+            if let Some(component) = Store::remove(other, position) {
+                Store::push(self, offset + position, component);
+            }
+        }
+
+        Store::pad(self, offset + Store::slots(other));
+    }
+}
+
+impl dyn ComponentStore {
+    /// Downcasts to the [`Component::Storage`] of the given component type.
+    pub fn as_store<C: Component>(&self) -> Option<&C::Storage> {
+        self.downcast_ref::<C::Storage>()
+    }
+
+    /// Downcasts to the mutable [`Component::Storage`] of the given component
+    /// type.
+    pub fn as_mut_store<C: Component>(&mut self) -> Option<&mut C::Storage> {
+        self.downcast_mut::<C::Storage>()
+    }
+
+    /// Downcasts to the [`Component::Storage`] of the given component type, for
+    /// a store kept under the type ID of that component type.
+    ///
+    /// Every store is created from the `Storage` of its component type, which
+    /// can't change, so the downcast only fails if a store was kept under the
+    /// wrong type ID. That is a bug within the library, which is reported with
+    /// the names of both types, rather than as a failed `unwrap`.
+    pub(crate) fn expect_store<C: Component>(&self) -> &C::Storage {
+        match self.as_store::<C>() {
+            Some(store) => store,
+            None => mismatched_store::<C>(),
+        }
+    }
+
+    /// Downcasts to the mutable [`Component::Storage`] of the given component
+    /// type, see [`ComponentStore::expect_store`].
+    pub(crate) fn expect_mut_store<C: Component>(&mut self) -> &mut C::Storage {
+        match self.as_mut_store::<C>() {
+            Some(store) => store,
+            None => mismatched_store::<C>(),
+        }
+    }
+}
+
+/// `ComponentStores` maps each component type to its store, and visits the
+/// stores in the order in which they were inserted.
+///
+/// The type IDs of the component types differ between builds of a program, so
+/// visiting the stores in the order of a hash map keyed by them would differ
+/// between builds as well. The order of insertion only depends on the order in
+/// which components of each type were first stored, which keeps saved worlds,
+/// debug output, and hooks reproducible, such as between the clients of a
+/// multiplayer game.
+#[derive(Default)]
+pub struct ComponentStores {
+    /// stores holds the type ID of each component type along with its store,
+    /// in the order in which they were inserted.
+    stores: Vec<(TypeId, Box<dyn ComponentStore>)>,
+
+    /// indices maps each component type to the index of its store.
+    indices: HashMap<TypeId, usize>,
+}
+
+impl ComponentStores {
+    pub(crate) fn get(&self, id: &TypeId) -> Option<&dyn ComponentStore> {
+        let &index = self.indices.get(id)?;
+        Some(&*self.stores[index].1)
+    }
+
+    pub(crate) fn get_mut(&mut self, id: &TypeId) -> Option<&mut Box<dyn ComponentStore>> {
+        let &index = self.indices.get(id)?;
+        Some(&mut self.stores[index].1)
+    }
+
+    /// Returns the stores of two different component types at once, or `None`
+    /// if there is no store for either type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if both type IDs are the same.
+    pub(crate) fn get_pair_mut(
+        &mut self,
+        a: &TypeId,
+        b: &TypeId,
+    ) -> Option<(&mut dyn ComponentStore, &mut dyn ComponentStore)> {
+        assert_ne!(a, b, "the same store is borrowed mutably twice");
+
+        let (&a, &b) = (self.indices.get(a)?, self.indices.get(b)?);
+        match pair_mut(&mut self.stores, a, b) {
+            (Some((_, a)), Some((_, b))) => Some((&mut **a, &mut **b)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn contains_key(&self, id: &TypeId) -> bool {
+        self.indices.contains_key(id)
+    }
+
+    /// Inserts the store of a component type, replacing the previous store of
+    /// the same type in place.
+    pub(crate) fn insert(&mut self, id: TypeId, store: Box<dyn ComponentStore>) {
+        match self.indices.get(&id) {
+            Some(&index) => self.stores[index].1 = store,
+            None => {
+                self.indices.insert(id, self.stores.len());
+                self.stores.push((id, store));
+            },
+        }
+    }
+
+    /// Returns the store of a component type, inserting the store returned by
+    /// `f` if there is none yet.
+    pub(crate) fn get_or_insert_with<F>(&mut self, id: TypeId, f: F) -> &mut Box<dyn ComponentStore>
+    where
+        F: FnOnce() -> Box<dyn ComponentStore>,
+    {
+        let index = match self.indices.get(&id) {
+            Some(&index) => index,
+            None => {
+                self.insert(id, f());
+                self.stores.len() - 1
+            },
+        };
+
+        &mut self.stores[index].1
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&TypeId, &dyn ComponentStore)> {
+        self.stores.iter().map(|(id, store)| (id, &**store))
+    }
+
+    pub(crate) fn iter_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&TypeId, &mut Box<dyn ComponentStore>)> {
+        self.stores.iter_mut().map(|(id, store)| (&*id, store))
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &dyn ComponentStore> {
+        self.stores.iter().map(|(_, store)| &**store)
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn ComponentStore>> {
+        self.stores.iter_mut().map(|(_, store)| store)
+    }
+}
+
+impl core::ops::Index<&TypeId> for ComponentStores {
+    type Output = dyn ComponentStore;
+
+    fn index(&self, id: &TypeId) -> &Self::Output {
+        self.get(id).expect("no store for the component type")
+    }
+}
+
+/// Returns mutable references to the elements at two distinct indices of a
+/// slice, or `None` for an index that is out of bounds, by splitting the slice
+/// in between them.
+fn pair_mut<T>(slice: &mut [T], a: usize, b: usize) -> (Option<&mut T>, Option<&mut T>) {
+    let (low, high) = (core::cmp::min(a, b), core::cmp::max(a, b));
+    if high >= slice.len() {
+        let low = slice.get_mut(low);
+        return if a < b { (low, None) } else { (None, low) };
+    }
+
+    let (head, tail) = slice.split_at_mut(high);
+    let (low, high) = (&mut head[low], &mut tail[0]);
+    if a < b {
+        (Some(low), Some(high))
+    } else {
+        (Some(high), Some(low))
+    }
+}
+
+/// Returns a pointer to the element at the given index of a vector that is
+/// mutated through a shared reference, without creating a reference to any of
+/// its elements. This keeps the references handed out earlier for other
+/// elements valid, such as by [`Store::iter_mut`].
+///
+/// # Safety
+///
+/// The index must be in bounds, and the vector must not be resized for as long
+/// as the pointer is used.
+unsafe fn element<T>(vec: &UnsafeCell<Vec<T>>, index: usize) -> *mut T {
+    (*vec.get()).as_mut_ptr().add(index)
+}
+
+#[cold]
+fn mismatched_store<C: Component>() -> ! {
+    panic!(
+        "the store of `{}` is not a `{}`",
+        type_name::<C>(),
+        type_name::<C::Storage>()
+    )
+}
+
+/// `DefaultStore` keeps its components in a single vector, with one slot for
+/// every position in the store.
+///
+/// Lookups are a plain index into the vector, but every position takes up
+/// space, whether it contains a component or not.
+#[derive(Debug)]
+pub struct DefaultStore<C: Component> {
+    components: UnsafeCell<Vec<Option<C>>>,
+
+    /// ticks holds the ticks of each slot in `components`, at the same index.
+    ticks: UnsafeCell<Vec<Ticks>>,
+
+    /// tick is the current tick of the store.
+    tick: usize,
+}
+
+impl<C: Component> DefaultStore<C> {
+    /// Creates an empty store with capacity for at least `capacity` slots.
+    ///
+    /// Every position up to the last component takes up a slot, including the
+    /// `None`s padding the store, so the capacity is counted in slots rather
+    /// than in components. Pushing components at positions below `capacity`
+    /// does not reallocate.
+    ///
+    /// Stores created by [`Things`](crate::Things) reserve their capacity
+    /// through [`Things::reserve`](crate::Things::reserve) instead, such as
+    /// when configured by [`ThingsBuilder::with_capacity`].
+    ///
+    /// [`ThingsBuilder::with_capacity`]: crate::ThingsBuilder::with_capacity
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            components: UnsafeCell::new(Vec::with_capacity(capacity)),
+            ticks: UnsafeCell::new(Vec::with_capacity(capacity)),
+            tick: 0,
+        }
+    }
+
+    /// Returns the number of slots the store can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        let store = unsafe { &(*self.components.get()) };
+        store.capacity()
+    }
+
+    /// Extracts a slice containing the entire vector.
+    pub fn as_slice(&self) -> &[Option<C>] {
+        let store = unsafe { &(*self.components.get()) };
+        store.as_slice()
+    }
+}
+
+// This is safe, because the components are only mutated through a shared
+// reference by `Store::get_mut` and `Store::iter_mut`, whose callers guarantee
+// that no other references to the same components exist. Within the library,
+// this is guaranteed by validating the borrows of every query, which ensures a
+// store written by one thread is never accessed by another at the same time,
+// except by `QueryIter::par_iter`, which splits it into disjoint positions.
+unsafe impl<C: Component> Sync for DefaultStore<C> {}
+
+impl<C: Component> Default for DefaultStore<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Component> Store for DefaultStore<C> {
+    type Item = C;
+
+    fn new() -> Self {
+        Self {
+            components: UnsafeCell::new(Vec::new()),
+            ticks: UnsafeCell::new(Vec::new()),
+            tick: 0,
+        }
+    }
+
+    fn push(&mut self, position: usize, component: C) -> usize {
+        let store = self.components.get_mut();
+
+        // Slots already taken by other components are skipped, this allows
+        // storing multiple components of the same type for a single entity.
+        let position = (position..store.len())
+            .find(|&position| store[position].is_none())
+            .unwrap_or_else(|| core::cmp::max(position, store.len()));
+
+        // A component is either stored in an empty slot, or pushed right after
+        // the last element, or one or more `None`s are pushed before the
+        // actual component is pushed.
+        if let Some(slot) = store.get_mut(position) {
+            *slot = Some(component);
+        } else {
+            // This adds `None`s to all positions except the one where we want
+            // to store the pushed `Component`. This is the same as
+            // `resize(position, None)`, except that requires `Clone`.
+            store.resize_with(position, || None);
+            store.push(Some(component));
+        }
+
+        let ticks = self.ticks.get_mut();
+        ticks.resize_with(store.len(), Ticks::default);
+        ticks[position] = Ticks::new(self.tick);
+
+        position
+    }
+
+    fn pad(&mut self, len: usize) {
+        let store = self.components.get_mut();
+
+        if store.len() < len {
+            store.resize_with(len, || None);
+            self.ticks.get_mut().resize_with(len, Ticks::default);
+        }
+    }
+
+    fn remove(&mut self, position: usize) -> Option<C> {
+        let store = self.components.get_mut();
+        store.get_mut(position).and_then(Option::take)
+    }
+
+    fn swap_remove(&mut self, position: usize) -> Option<(C, Option<usize>)> {
+        let store = self.components.get_mut();
+        store.get(position)?.as_ref()?;
+
+        let component = store.swap_remove(position)?;
+        self.ticks.get_mut().swap_remove(position);
+
+        // The last slot has been moved into the removed one, unless the removed
+        // slot was the last one, or the last slot was empty.
+        let last = store.len();
+        let moved = store.get(position).and_then(Option::as_ref).map(|_| last);
+
+        Some((component, moved))
+    }
+
+    fn clear(&mut self) {
+        self.components.get_mut().clear();
+        self.ticks.get_mut().clear();
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.components.get_mut().reserve(additional);
+        self.ticks.get_mut().reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.components.get_mut().shrink_to_fit();
+        self.ticks.get_mut().shrink_to_fit();
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.components.get_mut().truncate(len);
+        self.ticks.get_mut().truncate(len);
+    }
+
+    fn slots(&self) -> usize {
+        // The length is read without creating a slice of the components, which
+        // would overlap the components handed out by `get_mut`.
+        unsafe { &(*self.components.get()) }.len()
+    }
+
+    fn get(&self, position: usize) -> Option<&C> {
+        self.as_slice().get(position).and_then(Option::as_ref)
+    }
+
+    unsafe fn get_mut(&self, position: usize) -> Option<&mut C> {
+        if position >= Store::slots(self) {
+            return None;
+        }
+
+        let component = (*element(&self.components, position)).as_mut()?;
+        (*element(&self.ticks, position)).changed = self.tick;
+        Some(component)
+    }
+
+    fn get_pair_mut(&mut self, a: usize, b: usize) -> (Option<&mut C>, Option<&mut C>) {
+        assert_ne!(a, b, "positions must be distinct");
+
+        let tick = self.tick;
+        let ticks = self.ticks.get_mut();
+        let (first, second) = pair_mut(self.components.get_mut(), a, b);
+        let (first, second) = (
+            first.and_then(Option::as_mut),
+            second.and_then(Option::as_mut),
+        );
+
+        for (position, component) in [(a, &first), (b, &second)] {
+            if component.is_some() {
+                ticks[position].changed = tick;
+            }
+        }
+
+        (first, second)
+    }
+
+    fn set_tick(&mut self, tick: usize) {
+        self.tick = tick;
+    }
+
+    fn ticks(&self, position: usize) -> Option<Ticks> {
+        self.get(position)?;
+
+        let ticks = unsafe { &(*self.ticks.get()) };
+        ticks.get(position).copied()
+    }
+
+    fn set_ticks(&mut self, position: usize, ticks: Ticks) {
+        if self.get(position).is_some() {
+            self.ticks.get_mut()[position] = ticks;
+        }
+    }
+}
+
+/// `SparseStore` keeps its components in a compact vector, together with a map
+/// from each position to the index of its component in that vector.
+///
+/// Positions without a component take up no space, at the cost of a map lookup
+/// for every access. Prefer it over [`DefaultStore`] for components that are
+/// held by few entities, such as tags or rarely-present state.
+#[derive(Debug)]
+pub struct SparseStore<C: Component> {
+    /// dense holds the stored components, in no particular order.
+    dense: UnsafeCell<Vec<C>>,
+
+    /// ticks holds the ticks of each component in `dense`, at the same index.
+    ticks: UnsafeCell<Vec<Ticks>>,
+
+    /// positions holds the position of each component in `dense`, at the same
+    /// index.
+    positions: Vec<usize>,
+
+    /// indices maps each occupied position to the index of its component in
+    /// `dense`.
+    indices: HashMap<usize, usize>,
+
+    /// slots is the number of positions in the store, including any padding.
+    slots: usize,
+
+    /// tick is the current tick of the store.
+    tick: usize,
+}
+
+// This is safe for the same reasons as it is for `DefaultStore`.
+unsafe impl<C: Component> Sync for SparseStore<C> {}
+
+impl<C: Component> Default for SparseStore<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Component> Store for SparseStore<C> {
+    type Item = C;
+
+    fn new() -> Self {
+        Self {
+            dense: UnsafeCell::new(Vec::new()),
+            ticks: UnsafeCell::new(Vec::new()),
+            positions: Vec::new(),
+            indices: HashMap::default(),
+            slots: 0,
+            tick: 0,
+        }
+    }
+
+    fn push(&mut self, position: usize, component: C) -> usize {
+        // Slots already taken by other components are skipped, this allows
+        // storing multiple components of the same type for a single entity.
+        let indices = &self.indices;
+        let position = (position..)
+            .find(|position| !indices.contains_key(position))
+            .unwrap();
+
+        let dense = self.dense.get_mut();
+        self.indices.insert(position, dense.len());
+        self.positions.push(position);
+        self.ticks.get_mut().push(Ticks::new(self.tick));
+        dense.push(component);
+        self.slots = core::cmp::max(self.slots, position + 1);
+
+        position
+    }
+
+    fn pad(&mut self, len: usize) {
+        self.slots = core::cmp::max(self.slots, len);
+    }
+
+    fn remove(&mut self, position: usize) -> Option<C> {
+        let index = self.indices.remove(&position)?;
+        let dense = self.dense.get_mut();
+
+        // The last component takes the place of the removed one, so its index
+        // needs to be updated.
+        let component = dense.swap_remove(index);
+        self.ticks.get_mut().swap_remove(index);
+        self.positions.swap_remove(index);
+        if let Some(&moved) = self.positions.get(index) {
+            self.indices.insert(moved, index);
+        }
+
+        Some(component)
+    }
+
+    fn swap_remove(&mut self, position: usize) -> Option<(C, Option<usize>)> {
+        let component = self.remove(position)?;
+
+        self.slots -= 1;
+        let last = self.slots;
+        if last == position {
+            return Some((component, None));
+        }
+
+        let moved = self.indices.remove(&last).map(|index| {
+            self.positions[index] = position;
+            self.indices.insert(position, index);
+            last
+        });
+
+        Some((component, moved))
+    }
+
+    fn clear(&mut self) {
+        self.dense.get_mut().clear();
+        self.ticks.get_mut().clear();
+        self.positions.clear();
+        self.indices.clear();
+        self.slots = 0;
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.dense.get_mut().reserve(additional);
+        self.ticks.get_mut().reserve(additional);
+        self.positions.reserve(additional);
+        self.indices.reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.dense.get_mut().shrink_to_fit();
+        self.ticks.get_mut().shrink_to_fit();
+        self.positions.shrink_to_fit();
+        self.indices.shrink_to_fit();
+    }
+
+    fn truncate(&mut self, len: usize) {
+        let truncated = self
+            .positions
+            .iter()
+            .copied()
+            .filter(|&position| position >= len)
+            .collect::<Vec<_>>();
+
+        for position in truncated {
+            self.remove(position);
+        }
 
-    /// `push` takes a single component, and adds it to the store.
-    ///
-    /// The `usize` value returned by the method indicates the position in the
-    /// store at which the component is stored. This can be used to later
-    /// retrieve a specific component from the store.
-    fn push(&mut self, position: usize, component: Self::Item) -> usize;
+        self.slots = core::cmp::min(self.slots, len);
+    }
 
-    /// Extracts a slice containing the entire vector.
-    ///
-    /// Since the store allows for "padding" to be inserted, to allow components
-    /// of the same entity to be aligned across different stores, the returned
-    /// values is of type `Option<Component>`. A `None` indicates that the
-    /// entity stored in that position does not contain the component type of
-    /// this store.
-    fn as_slice(&self) -> &[Option<Self::Item>];
+    fn slots(&self) -> usize {
+        self.slots
+    }
 
-    /// Extracts a mutable slice of the entire vector.
-    ///
-    /// Since the store allows for "padding" to be inserted, to allow components
-    /// of the same entity to be aligned across different stores, the returned
-    /// values is of type `Option<Component>`. A `None` indicates that the
-    /// entity stored in that position does not contain the component type of
-    /// this store.
-    unsafe fn as_mut_slice(&self) -> &mut [Option<Self::Item>];
-}
+    fn len(&self) -> usize {
+        self.positions.len()
+    }
 
-pub trait ComponentStore: Downcast {}
-impl_downcast!(ComponentStore);
+    fn get(&self, position: usize) -> Option<&C> {
+        let dense = unsafe { &(*self.dense.get()) };
+        self.indices.get(&position).map(|&index| &dense[index])
+    }
 
-impl ComponentStore {
-    pub fn as_store<C: Component>(&self) -> Option<&DefaultStore<C>> {
-        self.downcast_ref::<DefaultStore<C>>()
+    unsafe fn get_mut(&self, position: usize) -> Option<&mut C> {
+        let index = *self.indices.get(&position)?;
+
+        (*element(&self.ticks, index)).changed = self.tick;
+        Some(&mut *element(&self.dense, index))
+    }
+
+    fn get_pair_mut(&mut self, a: usize, b: usize) -> (Option<&mut C>, Option<&mut C>) {
+        assert_ne!(a, b, "positions must be distinct");
+
+        let tick = self.tick;
+        let ticks = self.ticks.get_mut();
+        let dense = self.dense.get_mut();
+
+        match (self.indices.get(&a).copied(), self.indices.get(&b).copied()) {
+            (Some(a), Some(b)) => {
+                ticks[a].changed = tick;
+                ticks[b].changed = tick;
+                pair_mut(dense, a, b)
+            },
+            (Some(a), None) => {
+                ticks[a].changed = tick;
+                (Some(&mut dense[a]), None)
+            },
+            (None, Some(b)) => {
+                ticks[b].changed = tick;
+                (None, Some(&mut dense[b]))
+            },
+            (None, None) => (None, None),
+        }
+    }
+
+    fn set_tick(&mut self, tick: usize) {
+        self.tick = tick;
     }
 
-    pub fn as_mut_store<C: Component>(&mut self) -> Option<&mut DefaultStore<C>> {
-        self.downcast_mut::<DefaultStore<C>>()
+    fn ticks(&self, position: usize) -> Option<Ticks> {
+        let ticks = unsafe { &(*self.ticks.get()) };
+        self.indices.get(&position).map(|&index| ticks[index])
+    }
+
+    fn set_ticks(&mut self, position: usize, ticks: Ticks) {
+        if let Some(&index) = self.indices.get(&position) {
+            self.ticks.get_mut()[index] = ticks;
+        }
     }
 }
 
+/// `TagStore` keeps zero-sized components, such as markers like `Player` or
+/// `Enemy`, by recording which positions hold a component in a bitset.
+///
+/// A zero-sized component carries no data, so every position takes up a single
+/// bit, instead of the byte (or more) an `Option<C>` takes up in a
+/// [`DefaultStore`]. The ticks of the stored components are kept aside, the
+/// same way a [`SparseStore`] keeps its components.
+///
+/// Deriving [`Component`] selects this store for structs without fields.
+///
+/// # Panics
+///
+/// Creating a `TagStore` for a component type that is not zero-sized panics.
 #[derive(Debug)]
-pub struct DefaultStore<C: Component>(UnsafeCell<Vec<Option<C>>>);
+pub struct TagStore<C: Component> {
+    /// bits holds one bit for every position, which is set if the position
+    /// holds a component.
+    bits: Vec<u64>,
 
-impl<C: Component> ComponentStore for DefaultStore<C> {}
+    /// ticks holds the ticks of each component, at the index in `indices`.
+    ticks: UnsafeCell<Vec<Ticks>>,
 
-impl<C: Component> Default for DefaultStore<C> {
+    /// positions holds the position of each component in `ticks`, at the same
+    /// index.
+    positions: Vec<usize>,
+
+    /// indices maps each occupied position to the index of its ticks.
+    indices: HashMap<usize, usize>,
+
+    /// slots is the number of positions in the store, including any padding.
+    slots: usize,
+
+    /// tick is the current tick of the store.
+    tick: usize,
+
+    component: PhantomData<C>,
+}
+
+impl<C: Component> TagStore<C> {
+    /// Materializes the positions of the store into a vector, the counterpart
+    /// of [`DefaultStore::as_slice`].
+    pub fn to_vec(&self) -> Vec<Option<&C>> {
+        self.iter().collect()
+    }
+
+    fn is_set(&self, position: usize) -> bool {
+        self.bits
+            .get(position / 64)
+            .is_some_and(|word| word & (1 << (position % 64)) != 0)
+    }
+
+    fn set(&mut self, position: usize, value: bool) {
+        let word = position / 64;
+        if word >= self.bits.len() {
+            self.bits.resize(word + 1, 0);
+        }
+
+        if value {
+            self.bits[word] |= 1 << (position % 64);
+        } else {
+            self.bits[word] &= !(1 << (position % 64));
+        }
+    }
+
+    /// Returns a reference to a stored component.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee the store holds a component. Since `C` is
+    /// zero-sized, any aligned pointer is valid for it, and a stored component
+    /// proves the type is inhabited.
+    unsafe fn component<'a>() -> &'a mut C {
+        &mut *NonNull::dangling().as_ptr()
+    }
+}
+
+// This is safe for the same reasons as it is for `DefaultStore`.
+unsafe impl<C: Component> Sync for TagStore<C> {}
+
+impl<C: Component> Default for TagStore<C> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<C: Component> Store for DefaultStore<C> {
+impl<C: Component> Drop for TagStore<C> {
+    fn drop(&mut self) {
+        Store::clear(self);
+    }
+}
+
+impl<C: Component> Store for TagStore<C> {
     type Item = C;
 
     fn new() -> Self {
-        Self(UnsafeCell::new(Vec::new()))
+        assert_eq!(
+            mem::size_of::<C>(),
+            0,
+            "`{}` is not zero-sized",
+            type_name::<C>()
+        );
+
+        Self {
+            bits: Vec::new(),
+            ticks: UnsafeCell::new(Vec::new()),
+            positions: Vec::new(),
+            indices: HashMap::default(),
+            slots: 0,
+            tick: 0,
+            component: PhantomData,
+        }
     }
 
     fn push(&mut self, position: usize, component: C) -> usize {
-        let store = unsafe { &mut (*self.0.get()) };
+        // Slots already taken by other components are skipped, this allows
+        // storing multiple components of the same type for a single entity.
+        let position = (position..)
+            .find(|&position| !self.is_set(position))
+            .unwrap();
 
-        // A component is either pushed right after the last element, or one or
-        // more `None`s are pushed before the actual component is pushed.
-        if store.len() < position {
-            // This adds `None`s to all positions except the one where we want
-            // to store the pushed `Component`. This is the same as
-            // `resize(position, None)`, except that isn't allowed somehow,
-            // because it requires `Copy`.
-            store.resize_default(position);
+        // The component is dropped again when it is taken out of the store, or
+        // when the store is cleared.
+        mem::forget(component);
+
+        self.set(position, true);
+        self.indices.insert(position, self.positions.len());
+        self.positions.push(position);
+        self.ticks.get_mut().push(Ticks::new(self.tick));
+        self.slots = core::cmp::max(self.slots, position + 1);
+
+        position
+    }
+
+    fn pad(&mut self, len: usize) {
+        self.slots = core::cmp::max(self.slots, len);
+    }
+
+    fn remove(&mut self, position: usize) -> Option<C> {
+        let index = self.indices.remove(&position)?;
+        self.set(position, false);
+
+        // The last ticks take the place of the removed ones, so their index
+        // needs to be updated.
+        self.ticks.get_mut().swap_remove(index);
+        self.positions.swap_remove(index);
+        if let Some(&moved) = self.positions.get(index) {
+            self.indices.insert(moved, index);
         }
 
-        store.push(Some(component));
-        store.len()
+        // This is safe, because the position held the component, which was
+        // forgotten when it was pushed.
+        Some(unsafe { ptr::read(Self::component()) })
     }
 
-    fn as_slice(&self) -> &[Option<C>] {
-        let store = unsafe { &(*self.0.get()) };
-        store.as_slice()
+    fn swap_remove(&mut self, position: usize) -> Option<(C, Option<usize>)> {
+        let component = self.remove(position)?;
+
+        self.slots -= 1;
+        let last = self.slots;
+        if last == position {
+            return Some((component, None));
+        }
+
+        let moved = self.indices.remove(&last).map(|index| {
+            self.set(last, false);
+            self.set(position, true);
+            self.positions[index] = position;
+            self.indices.insert(position, index);
+            last
+        });
+
+        Some((component, moved))
+    }
+
+    fn clear(&mut self) {
+        if mem::needs_drop::<C>() {
+            for _ in 0..self.positions.len() {
+                // This is safe, because every stored component was forgotten
+                // when it was pushed, and is dropped exactly once.
+                drop(unsafe { ptr::read(Self::component()) });
+            }
+        }
+
+        self.bits.clear();
+        self.ticks.get_mut().clear();
+        self.positions.clear();
+        self.indices.clear();
+        self.slots = 0;
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.ticks.get_mut().reserve(additional);
+        self.positions.reserve(additional);
+        self.indices.reserve(additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.bits.shrink_to_fit();
+        self.ticks.get_mut().shrink_to_fit();
+        self.positions.shrink_to_fit();
+        self.indices.shrink_to_fit();
+    }
+
+    fn truncate(&mut self, len: usize) {
+        let truncated = self
+            .positions
+            .iter()
+            .copied()
+            .filter(|&position| position >= len)
+            .collect::<Vec<_>>();
+
+        for position in truncated {
+            self.remove(position);
+        }
+
+        self.bits.truncate(len.div_ceil(64));
+        self.slots = core::cmp::min(self.slots, len);
+    }
+
+    fn slots(&self) -> usize {
+        self.slots
+    }
+
+    fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    fn get(&self, position: usize) -> Option<&C> {
+        if !self.is_set(position) {
+            return None;
+        }
+
+        Some(unsafe { Self::component() })
+    }
+
+    unsafe fn get_mut(&self, position: usize) -> Option<&mut C> {
+        let index = *self.indices.get(&position)?;
+
+        (*element(&self.ticks, index)).changed = self.tick;
+        Some(Self::component())
+    }
+
+    fn set_tick(&mut self, tick: usize) {
+        self.tick = tick;
+    }
+
+    fn ticks(&self, position: usize) -> Option<Ticks> {
+        let ticks = unsafe { &(*self.ticks.get()) };
+        self.indices.get(&position).map(|&index| ticks[index])
     }
 
-    unsafe fn as_mut_slice(&self) -> &mut [Option<C>] {
-        let store = &mut (*self.0.get());
-        store.as_mut_slice()
+    fn set_ticks(&mut self, position: usize, ticks: Ticks) {
+        if let Some(&index) = self.indices.get(&position) {
+            self.ticks.get_mut()[index] = ticks;
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Component;
 
     #[derive(Debug)]
     struct TestStore<C: Component>(UnsafeCell<Vec<Option<C>>>);
-    impl<C: Component> ComponentStore for TestStore<C> {}
 
-    #[derive(Debug)]
+    unsafe impl<C: Component> Sync for TestStore<C> {}
+
+    #[derive(Component, Debug, PartialEq)]
     struct C;
 
+    #[derive(Component, Debug, PartialEq)]
+    struct N(usize);
+
     #[rustfmt::skip]
     impl<C: Component> Store for TestStore<C> {
         type Item = C;
 
         fn new() -> Self { TestStore(UnsafeCell::new(Vec::new())) }
         fn push(&mut self, _: usize, _: C) -> usize { 0 }
-        fn as_slice(&self) -> &[Option<C>] { unsafe { &(*self.0.get()) }.as_slice() }
-        unsafe fn as_mut_slice(&self) -> &mut [Option<C>] { { &mut (*self.0.get()) }.as_mut_slice() }
+        fn pad(&mut self, _: usize) {}
+        fn remove(&mut self, _: usize) -> Option<C> { None }
+        fn swap_remove(&mut self, _: usize) -> Option<(C, Option<usize>)> { None }
+        fn clear(&mut self) {}
+        fn reserve(&mut self, _: usize) {}
+        fn shrink_to_fit(&mut self) {}
+        fn truncate(&mut self, _: usize) {}
+        fn slots(&self) -> usize { unsafe { &(*self.0.get()) }.len() }
+        fn get(&self, _: usize) -> Option<&C> { None }
+        unsafe fn get_mut(&self, _: usize) -> Option<&mut C> { None }
+        fn set_tick(&mut self, _: usize) {}
+        fn ticks(&self, _: usize) -> Option<Ticks> { None }
+        fn set_ticks(&mut self, _: usize, _: Ticks) {}
      }
 
     #[rustfmt::skip]
@@ -139,4 +1358,492 @@ mod tests {
 
         assert_eq!(cs.push(0, C), 0);
     }
+
+    #[test]
+    fn test_default_store_pad() {
+        let mut store = DefaultStore::<C>::new();
+        store.push(1, C);
+        Store::pad(&mut store, 4);
+
+        assert_eq!(store.as_slice().len(), 4);
+        assert!(store.as_slice()[1].is_some());
+        assert!(store.as_slice()[3].is_none());
+
+        Store::pad(&mut store, 2);
+        assert_eq!(store.as_slice().len(), 4);
+    }
+
+    #[test]
+    fn test_default_store_push_into_gap() {
+        let mut store = DefaultStore::<N>::new();
+        assert_eq!(store.push(2, N(1)), 2);
+        assert_eq!(store.push(0, N(2)), 0);
+        assert_eq!(store.push(0, N(3)), 1);
+        assert_eq!(store.push(0, N(4)), 3);
+        assert_eq!(
+            store.as_slice(),
+            &[Some(N(2)), Some(N(3)), Some(N(1)), Some(N(4))]
+        );
+    }
+
+    #[test]
+    fn test_default_store_remove() {
+        let mut store = DefaultStore::<N>::new();
+        store.push(0, N(1));
+        store.push(1, N(2));
+
+        assert_eq!(store.remove(0), Some(N(1)));
+        assert_eq!(store.remove(0), None);
+        assert_eq!(store.remove(5), None);
+        assert_eq!(store.as_slice(), &[None, Some(N(2))]);
+    }
+
+    #[test]
+    fn test_default_store_iter() {
+        let mut store = DefaultStore::<N>::new();
+        store.push(1, N(1));
+        Store::pad(&mut store, 3);
+
+        assert_eq!(
+            store.iter().collect::<Vec<_>>(),
+            vec![None, Some(&N(1)), None]
+        );
+    }
+
+    #[test]
+    fn test_sparse_store_push_into_gap() {
+        let mut store = SparseStore::<N>::new();
+        assert_eq!(store.push(2, N(1)), 2);
+        assert_eq!(store.push(0, N(2)), 0);
+        assert_eq!(store.push(0, N(3)), 1);
+        assert_eq!(store.push(0, N(4)), 3);
+
+        let components = store.iter().collect::<Vec<_>>();
+        assert_eq!(
+            components,
+            vec![Some(&N(2)), Some(&N(3)), Some(&N(1)), Some(&N(4))]
+        );
+    }
+
+    #[test]
+    fn test_sparse_store_pad() {
+        let mut store = SparseStore::<C>::new();
+        store.push(1, C);
+        Store::pad(&mut store, 4);
+
+        assert_eq!(Store::slots(&store), 4);
+        assert!(store.get(1).is_some());
+        assert!(store.get(3).is_none());
+    }
+
+    #[test]
+    fn test_default_store_swap_remove() {
+        let mut store = DefaultStore::<N>::new();
+        store.push(0, N(1));
+        store.push(2, N(2));
+        store.push(3, N(3));
+
+        assert_eq!(store.swap_remove(0), Some((N(1), Some(3))));
+        assert_eq!(store.as_slice(), &[Some(N(3)), None, Some(N(2))]);
+
+        assert_eq!(store.swap_remove(1), None);
+        assert_eq!(store.swap_remove(2), Some((N(2), None)));
+        assert_eq!(store.as_slice(), &[Some(N(3)), None]);
+
+        assert_eq!(store.swap_remove(0), Some((N(3), None)));
+        assert_eq!(store.as_slice(), &[None]);
+    }
+
+    #[test]
+    fn test_default_store_iter_indexed() {
+        let mut store = DefaultStore::<N>::new();
+        store.push(1, N(1));
+        store.push(3, N(2));
+
+        let positions = store.iter_indexed().collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            vec![(0, None), (1, Some(&N(1))), (2, None), (3, Some(&N(2)))]
+        );
+    }
+
+    #[test]
+    fn test_default_store_len() {
+        let mut store = DefaultStore::<N>::new();
+        assert!(store.is_empty());
+
+        store.push(1, N(1));
+        store.push(3, N(2));
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.iter_present().collect::<Vec<_>>(), vec![&N(1), &N(2)]);
+    }
+
+    #[test]
+    fn test_default_store_clear() {
+        let mut store = DefaultStore::<N>::new();
+        store.push(3, N(1));
+        Store::clear(&mut store);
+
+        assert_eq!(Store::slots(&store), 0);
+        assert!(store.components.get_mut().capacity() >= 4);
+
+        store.push(0, N(2));
+        assert_eq!(store.as_slice(), &[Some(N(2))]);
+    }
+
+    #[test]
+    fn test_default_store_reserve() {
+        let mut store = DefaultStore::<N>::new();
+        Store::reserve(&mut store, 10);
+
+        assert!(store.components.get_mut().capacity() >= 10);
+        assert!(store.ticks.get_mut().capacity() >= 10);
+        assert_eq!(Store::slots(&store), 0);
+    }
+
+    #[test]
+    fn test_default_store_shrink_to_fit() {
+        let mut store = DefaultStore::<N>::new();
+        Store::reserve(&mut store, 100);
+        store.push(2, N(1));
+        Store::shrink_to_fit(&mut store);
+
+        assert!(store.components.get_mut().capacity() < 100);
+        assert_eq!(store.as_slice(), &[None, None, Some(N(1))]);
+    }
+
+    #[test]
+    fn test_default_store_truncate() {
+        let mut store = DefaultStore::<N>::new();
+        store.push(1, N(1));
+        store.push(3, N(2));
+        Store::truncate(&mut store, 2);
+
+        assert_eq!(store.as_slice(), &[None, Some(N(1))]);
+        assert_eq!(store.ticks.get_mut().len(), 2);
+    }
+
+    #[test]
+    fn test_sparse_store_truncate() {
+        let mut store = SparseStore::<N>::new();
+        store.push(1, N(1));
+        store.push(3, N(2));
+        store.push(4, N(3));
+        Store::truncate(&mut store, 2);
+        Store::shrink_to_fit(&mut store);
+
+        assert_eq!(Store::slots(&store), 2);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(1), Some(&N(1)));
+        assert_eq!(store.get(3), None);
+    }
+
+    #[test]
+    fn test_sparse_store_len() {
+        let mut store = SparseStore::<N>::new();
+        store.push(2, N(1));
+        store.push(5, N(2));
+        store.remove(2);
+
+        assert_eq!(store.len(), 1);
+        assert!(!store.is_empty());
+        assert_eq!(store.iter_present().collect::<Vec<_>>(), vec![&N(2)]);
+    }
+
+    #[test]
+    fn test_sparse_store_clear() {
+        let mut store = SparseStore::<N>::new();
+        store.push(3, N(1));
+        store.push(5, N(2));
+        Store::clear(&mut store);
+
+        assert_eq!(Store::slots(&store), 0);
+        assert_eq!(store.get(3), None);
+
+        store.push(0, N(3));
+        let components = store.iter().collect::<Vec<_>>();
+        assert_eq!(components, vec![Some(&N(3))]);
+    }
+
+    #[test]
+    fn test_sparse_store_swap_remove() {
+        let mut store = SparseStore::<N>::new();
+        store.push(1, N(1));
+        store.push(2, N(2));
+        store.push(4, N(3));
+
+        assert_eq!(store.swap_remove(1), Some((N(1), Some(4))));
+        assert_eq!(store.get(1), Some(&N(3)));
+        assert_eq!(store.get(4), None);
+        assert_eq!(Store::slots(&store), 4);
+
+        assert_eq!(store.swap_remove(0), None);
+        assert_eq!(store.swap_remove(2), Some((N(2), None)));
+        assert_eq!(Store::slots(&store), 3);
+
+        let components = store.iter().collect::<Vec<_>>();
+        assert_eq!(components, vec![None, Some(&N(3)), None]);
+    }
+
+    #[test]
+    fn test_sparse_store_remove() {
+        let mut store = SparseStore::<N>::new();
+        store.push(0, N(1));
+        store.push(5, N(2));
+        store.push(9, N(3));
+
+        assert_eq!(store.remove(0), Some(N(1)));
+        assert_eq!(store.remove(0), None);
+        assert_eq!(store.remove(4), None);
+        assert_eq!(store.get(5), Some(&N(2)));
+        assert_eq!(store.get(9), Some(&N(3)));
+        assert_eq!(Store::slots(&store), 10);
+    }
+
+    #[test]
+    fn test_sparse_store_iter_mut() {
+        let mut store = SparseStore::<N>::new();
+        store.push(1, N(1));
+        store.push(3, N(2));
+
+        for component in unsafe { store.iter_mut() }.flatten() {
+            component.0 *= 10;
+        }
+
+        let components = store.iter().collect::<Vec<_>>();
+        assert_eq!(components, vec![None, Some(&N(10)), None, Some(&N(20))]);
+    }
+
+    #[test]
+    fn test_default_store_ticks() {
+        let mut store = DefaultStore::<N>::new();
+        Store::set_tick(&mut store, 2);
+        store.push(0, N(1));
+        store.push(1, N(2));
+
+        Store::set_tick(&mut store, 5);
+        unsafe { store.get_mut(1) }.unwrap().0 += 1;
+
+        assert_eq!(
+            store.ticks(0),
+            Some(Ticks {
+                added: 2,
+                changed: 2
+            })
+        );
+        assert_eq!(
+            store.ticks(1),
+            Some(Ticks {
+                added: 2,
+                changed: 5
+            })
+        );
+        assert_eq!(store.ticks(2), None);
+    }
+
+    #[test]
+    fn test_default_store_with_capacity() {
+        let mut store = DefaultStore::<N>::with_capacity(8);
+        let capacity = store.capacity();
+        let ptr = store.as_slice().as_ptr();
+        assert!(capacity >= 8);
+
+        store.push(5, N(5));
+        for position in 0..5 {
+            store.push(position, N(position));
+        }
+        store.push(7, N(7));
+
+        assert_eq!(Store::slots(&store), 8);
+        assert_eq!(store.capacity(), capacity);
+        assert_eq!(store.as_slice().as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_default_store_get_mut_exclusive() {
+        let mut store = DefaultStore::<N>::new();
+        store.push(1, N(10));
+
+        Store::set_tick(&mut store, 3);
+        store.get_mut_exclusive(1).unwrap().0 += 1;
+
+        assert_eq!(store.get(1), Some(&N(11)));
+        assert_eq!(
+            store.ticks(1),
+            Some(Ticks {
+                added: 0,
+                changed: 3
+            })
+        );
+        assert!(store.get_mut_exclusive(0).is_none());
+        assert!(store.get_mut_exclusive(2).is_none());
+    }
+
+    #[test]
+    fn test_get_pair_mut() {
+        fn swap<S: Store<Item = N>>(mut store: S) {
+            store.push(0, N(1));
+            store.push(2, N(3));
+            store.push(3, N(4));
+            Store::set_tick(&mut store, 5);
+
+            let (a, b) = store.get_pair_mut(3, 0);
+            core::mem::swap(a.unwrap(), b.unwrap());
+            assert_eq!(store.get(0), Some(&N(4)));
+            assert_eq!(store.get(3), Some(&N(1)));
+            assert_eq!(store.ticks(0).unwrap().changed, 5);
+
+            let (a, b) = store.get_pair_mut(1, 2);
+            assert!(a.is_none());
+            assert_eq!(b, Some(&mut N(3)));
+            assert_eq!(store.get_pair_mut(2, 9), (Some(&mut N(3)), None));
+        }
+
+        swap(DefaultStore::new());
+        swap(SparseStore::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "positions must be distinct")]
+    fn test_get_pair_mut_same_position() {
+        let mut store = DefaultStore::<N>::new();
+        store.push(0, N(1));
+        let _ = store.get_pair_mut(0, 0);
+    }
+
+    #[test]
+    fn test_sparse_store_ticks() {
+        let mut store = SparseStore::<N>::new();
+        Store::set_tick(&mut store, 3);
+        store.push(4, N(1));
+
+        Store::set_tick(&mut store, 4);
+        for component in unsafe { store.iter_mut() }.flatten() {
+            component.0 += 1;
+        }
+        store.set_ticks(
+            7,
+            Ticks {
+                added: 1,
+                changed: 1,
+            },
+        );
+
+        assert_eq!(
+            store.ticks(4),
+            Some(Ticks {
+                added: 3,
+                changed: 4
+            })
+        );
+        assert_eq!(store.ticks(0), None);
+        assert_eq!(store.ticks(7), None);
+    }
+
+    #[test]
+    fn test_tag_store() {
+        let mut store = TagStore::<C>::new();
+        assert_eq!(store.push(1, C), 1);
+        assert_eq!(store.push(1, C), 2);
+        assert_eq!(store.push(70, C), 70);
+        Store::pad(&mut store, 72);
+
+        assert_eq!(Store::slots(&store), 72);
+        assert_eq!(store.len(), 3);
+        assert!(store.get(0).is_none());
+        assert!(store.get(70).is_some());
+        assert_eq!(store.to_vec()[..3], [None, Some(&C), Some(&C)]);
+
+        assert!(store.remove(1).is_some());
+        assert!(store.remove(1).is_none());
+        assert!(store.get(2).is_some());
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn test_tag_store_swap_remove() {
+        let mut store = TagStore::<C>::new();
+        store.push(0, C);
+        store.push(3, C);
+
+        assert!(matches!(store.swap_remove(0), Some((C, Some(3)))));
+        assert!(store.get(0).is_some());
+        assert!(store.get(3).is_none());
+        assert_eq!(Store::slots(&store), 3);
+    }
+
+    #[test]
+    fn test_tag_store_drop() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Component, Debug)]
+        #[component(storage = "TagStore")]
+        struct D;
+
+        impl Drop for D {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut store = TagStore::<D>::new();
+        store.push(0, D);
+        store.push(1, D);
+        store.push(2, D);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+
+        drop(store.remove(1));
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+
+        Store::truncate(&mut store, 2);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+        assert_eq!(Store::slots(&store), 2);
+
+        drop(store);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_tag_store_ticks() {
+        let mut store = TagStore::<C>::new();
+        Store::set_tick(&mut store, 3);
+        store.push(4, C);
+
+        Store::set_tick(&mut store, 4);
+        unsafe { store.get_mut(4) }.unwrap();
+
+        assert_eq!(
+            store.ticks(4),
+            Some(Ticks {
+                added: 3,
+                changed: 4
+            })
+        );
+        assert_eq!(store.ticks(0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not zero-sized")]
+    fn test_tag_store_sized_component() {
+        TagStore::<N>::new();
+    }
+
+    #[test]
+    fn test_component_stores_order() {
+        let ids = [TypeId::of::<u64>(), TypeId::of::<u8>(), TypeId::of::<u32>()];
+
+        let mut stores = ComponentStores::default();
+        for &id in &ids {
+            stores.insert(id, Box::new(TagStore::<C>::new()));
+        }
+
+        stores.get_or_insert_with(ids[1], || unreachable!());
+        stores.insert(ids[0], Box::new(TagStore::<C>::new()));
+
+        assert!(stores.iter().map(|(id, _)| *id).eq(ids.iter().copied()));
+        assert!(stores.contains_key(&ids[2]));
+        assert!(!stores.contains_key(&TypeId::of::<u16>()));
+    }
 }
@@ -1,8 +1,13 @@
-use crate::{component::Component,
-            store::{ComponentStore, Store}};
+use crate::{borrow::{self, Borrow, BorrowToken, RegisterBorrow},
+            component::Component,
+            store::{Iter, IterMut, Store},
+            BorrowError, Entity, Things};
+use alloc::{vec, vec::Vec};
 use core::{any::TypeId,
-           iter::{FilterMap, Zip}};
-use rustc_hash::FxHashMap as HashMap;
+           fmt,
+           ops::{Range, RangeFrom}};
+#[cfg(feature = "std")]
+use std::error;
 
 /// System must be implemented by any object that wants to interact with
 /// components. Objects implementing `System` are used to manipulate, and act on
@@ -13,118 +18,1610 @@ pub trait System<'a> {
     fn update(components: <Self::Query as Query<'a>>::Iter);
 }
 
+/// `StatefulSystem` is a [`System`] that keeps state between runs, such as a
+/// timer or an accumulator.
+///
+/// Unlike `System`, it is updated through a mutable reference to the system,
+/// which is kept by the caller, or by the [`Schedule`](crate::Schedule) it was
+/// added to:
+///
+/// ```
+/// # use things::{Component, Query, Read, StatefulSystem, Things};
+/// # #[derive(Component, Debug)]
+/// # struct Position(u32);
+/// #[derive(Default)]
+/// struct Distance {
+///     total: u32,
+/// }
+///
+/// impl<'a> StatefulSystem<'a> for Distance {
+///     type Query = Read<Position>;
+///
+///     fn update(&mut self, components: <Self::Query as Query<'a>>::Iter) {
+///         self.total += components.map(|position| position.0).sum::<u32>();
+///     }
+/// }
+///
+/// let mut ecs = Things::new();
+/// ecs.create_entity((Position(10),));
+///
+/// let mut distance = Distance::default();
+/// ecs.execute_stateful_system(&mut distance).unwrap();
+/// ecs.execute_stateful_system(&mut distance).unwrap();
+/// assert_eq!(distance.total, 20);
+/// ```
+pub trait StatefulSystem<'a> {
+    type Query: Query<'a>;
+
+    fn update(&mut self, components: <Self::Query as Query<'a>>::Iter);
+}
+
+/// `AnySystem` is the type-erased counterpart of [`System`], allowing systems
+/// of different types to be executed together using
+/// [`Things::execute_systems`].
+///
+/// It is implemented for the values of every `System` type that can be shared
+/// between threads, such as the unit structs systems are usually defined as.
+pub trait AnySystem: Send + Sync {
+    /// Returns the type ID of the system, which is used to keep track of when
+    /// the system last ran.
+    fn system_id(&self) -> TypeId;
+
+    /// Returns the borrows of the query of the system.
+    fn register_borrow(&self) -> Result<Borrow, BorrowError>;
+
+    /// Runs the system, yielding the changes made since the given tick. The
+    /// borrows of the system were validated when the token was created.
+    fn run(&self, things: &Things, last_run: usize, token: BorrowToken);
+}
+
+impl<S> AnySystem for S
+where
+    S: for<'a> System<'a> + Send + Sync + 'static,
+    for<'a> <<S as System<'a>>::Query as Query<'a>>::Borrow: RegisterBorrow,
+{
+    fn system_id(&self) -> TypeId {
+        TypeId::of::<S>()
+    }
+
+    fn register_borrow(&self) -> Result<Borrow, BorrowError> {
+        <<<S as System>::Query as Query>::Borrow>::register_borrow()
+    }
+
+    fn run(&self, things: &Things, last_run: usize, token: BorrowToken) {
+        <S as System>::update(<S as System>::Query::iter_since(things, last_run, token));
+    }
+}
+
+/// `IntoSystem` allows plain functions and closures to be used as systems.
+///
+/// Any function taking a single [`QueryIter`] argument implements this trait.
+/// The query of the system is inferred from the type of that argument, and its
+/// borrows are registered and validated in the same way as they are for types
+/// implementing [`System`]. Unlike those systems, functions don't keep track of
+/// when they last ran, so [`Changed`] and [`Added`] match every component:
+///
+/// ```
+/// # use things::{Component, QueryIter, Read, Things, Write};
+/// #[derive(Component, Debug)]
+/// struct Velocity(u8);
+///
+/// #[derive(Component, Debug)]
+/// struct Position(u32);
+///
+/// fn movement(components: QueryIter<(Read<Velocity>, Write<Position>)>) {
+///     for (velocity, position) in components {
+///         position.0 += u32::from(velocity.0);
+///     }
+/// }
+///
+/// let mut ecs = Things::new();
+/// ecs.create_entity((Velocity(1), Position(10)));
+/// ecs.run(movement).unwrap();
+/// ```
+pub trait IntoSystem<Q> {
+    fn run(self, things: &mut Things) -> Result<(), BorrowError>;
+}
+
+impl<F, Q> IntoSystem<Q> for F
+where
+    F: for<'a> FnOnce(QueryIter<'a, Q>),
+    Q: for<'a> QueryElement<'a> + RegisterBorrow,
+{
+    fn run(self, things: &mut Things) -> Result<(), BorrowError> {
+        borrow::validate::<Q>()?;
+        things.refresh_archetypes();
+
+        self(QueryIter::new(things));
+        things.apply_commands();
+        things.apply_despawns();
+        Ok(())
+    }
+}
+
 /// A `Query` is a group of statements that determine on which set of components
 /// the system will operate.
 pub trait Query<'a> {
     type Borrow;
-    type Iter: Iterator;
 
-    fn iter(store: &'a HashMap<TypeId, Box<ComponentStore>>) -> Self::Iter;
+    /// The value handed to the system, which is a [`QueryIter`] for a single
+    /// query, and a tuple of them for a [`QuerySet`].
+    type Iter;
+
+    /// `iter` returns the iterator of the query. The token proves the borrows
+    /// of the query have been validated, see [`BorrowToken`].
+    fn iter(things: &'a Things, token: BorrowToken) -> Self::Iter;
+
+    /// `iter_since` returns the same iterator as `iter`, for a system that last
+    /// ran at the given tick. See [`Changed`] for details.
+    fn iter_since(things: &'a Things, _last_run: usize, token: BorrowToken) -> Self::Iter {
+        Self::iter(things, token)
+    }
+}
+
+/// A `QueryElement` is a single statement within a [`Query`], such as a `Read`
+/// or `Write` of a component type.
+///
+/// Components of a single entity are aligned across their respective stores,
+/// which means a query is resolved row by row: every element of the query
+/// advances its `Slots` by one row in lockstep, and the row is only yielded if
+/// every element matched it.
+pub trait QueryElement<'a> {
+    /// The value yielded for this element, for every matching row.
+    type Item;
+
+    /// Either [`Data`] for elements that contribute their item to the rows
+    /// yielded by the query, or [`Filter`] for elements that only determine
+    /// which rows match.
+    type Kind;
+
+    /// The state used to walk the rows of the stores, one row at a time.
+    type Slots;
+
+    /// `slots` prepares the state of this element before iteration starts.
+    fn slots(things: &'a Things) -> Self::Slots;
+
+    /// `slots_since` prepares the state of this element for a system that last
+    /// ran at the given tick. Only elements that depend on the changes made
+    /// since then, such as [`Changed`], have to implement it.
+    fn slots_since(things: &'a Things, _last_run: usize) -> Self::Slots {
+        Self::slots(things)
+    }
+
+    /// `slots_range` prepares the state of this element for the given rows
+    /// only, such as for a chunk of a parallel query. The slots start at the
+    /// first of the rows, and are never advanced past the last. Elements whose
+    /// slots are costly to prepare for all rows, such as [`Entity`], should
+    /// implement it.
+    fn slots_range(things: &'a Things, last_run: usize, rows: Range<usize>) -> Self::Slots {
+        let mut slots = Self::slots_since(things, last_run);
+        Self::skip(&mut slots, rows.start);
+        slots
+    }
+
+    /// `fetch` advances the slots by exactly one row, and returns the item of
+    /// that row, or `None` if the row does not match this element.
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item>;
+
+    /// `skip` advances the slots by the given number of rows, without fetching
+    /// their items, so skipped components are not marked as changed. Elements
+    /// yielding the same item for every row have nothing to advance.
+    fn skip(slots: &mut Self::Slots, rows: usize);
+
+    /// `matches` returns `false` if no row of an entity holding exactly the
+    /// given, sorted, component types can match this element, which allows the
+    /// query to skip the rows of such entities without fetching them. Elements
+    /// that require a component to be present should implement it.
+    fn matches(_types: &[TypeId]) -> bool {
+        true
+    }
+}
+
+/// Marks a [`QueryElement`] that contributes its item to the yielded rows.
+pub enum Data {}
+
+/// Marks a [`QueryElement`] that filters rows, without contributing an item to
+/// the yielded rows.
+pub enum Filter {}
+
+/// `ReadOnly` marks the query elements that never access a component mutably,
+/// such as [`Read`] and [`With`], along with the tuples made up of them.
+///
+/// The borrows of a read-only query can't conflict, so it can be run through a
+/// shared reference to [`Things`], without validating its borrows, see
+/// [`Things::count`].
+///
+/// # Safety
+///
+/// Fetching the rows of the element must not create mutable references to
+/// any component, nor record a component as changed.
+pub unsafe trait ReadOnly {}
+
+unsafe impl<C: Component> ReadOnly for Read<C> {}
+unsafe impl<C: Component> ReadOnly for Option<Read<C>> {}
+unsafe impl<C: Component> ReadOnly for With<C> {}
+unsafe impl<C: Component> ReadOnly for Without<C> {}
+unsafe impl<C: Component> ReadOnly for Changed<C> {}
+unsafe impl<C: Component> ReadOnly for Added<C> {}
+unsafe impl ReadOnly for Entity {}
+unsafe impl ReadOnly for Tick {}
+unsafe impl ReadOnly for World {}
+
+macro_rules! impl_read_only {
+    ($($element:ident),+) => {
+        unsafe impl<$($element: ReadOnly),+> ReadOnly for ($($element,)+) {}
+    };
 }
 
-/// A `Reader` defines a single `Read` action for a component type. Its `find`
-/// operation returns an iterator over all immutable references of the given
-/// [`Component`].
-pub trait Reader<'a> {
-    type Component: Component;
-    type Iter: ExactSizeIterator<Item = &'a Option<Self::Component>>;
+impl_read_only!(A);
+impl_read_only!(A, B);
+impl_read_only!(A, B, C);
+impl_read_only!(A, B, C, D);
+impl_read_only!(A, B, C, D, E);
+impl_read_only!(A, B, C, D, E, F);
+impl_read_only!(A, B, C, D, E, F, G);
+impl_read_only!(A, B, C, D, E, F, G, H);
 
-    fn find(store: &'a HashMap<TypeId, Box<ComponentStore>>) -> Self::Iter;
+/// `Append` adds the item of a single query element to the end of a tuple of
+/// items, based on the kind of the element. Items of [`Filter`] elements are
+/// discarded, which keeps filters out of the rows yielded by a query.
+pub trait Append<T, K> {
+    type Output;
+
+    fn append(self, item: T) -> Self::Output;
 }
 
-/// A `Writer` defines a single `Write` action for a component type. Its `find`
-/// operation returns an iterator over all mutable references of the given
-/// [`Component`].
-pub trait Writer<'a> {
-    type Component: Component;
-    type Iter: ExactSizeIterator<Item = &'a mut Option<Self::Component>>;
+impl<R, T> Append<T, Filter> for R {
+    type Output = R;
 
-    fn find(store: &'a HashMap<TypeId, Box<ComponentStore>>) -> Self::Iter;
+    fn append(self, _: T) -> Self::Output {
+        self
+    }
 }
 
+macro_rules! impl_append {
+    ($($item:ident),*) => {
+        impl<$($item,)* T> Append<T, Data> for ($($item,)*) {
+            type Output = ($($item,)* T,);
+
+            #[allow(non_snake_case)]
+            fn append(self, item: T) -> Self::Output {
+                let ($($item,)*) = self;
+                ($($item,)* item,)
+            }
+        }
+    };
+}
+
+impl_append!();
+impl_append!(A);
+impl_append!(A, B);
+impl_append!(A, B, C);
+impl_append!(A, B, C, D);
+impl_append!(A, B, C, D, E);
+impl_append!(A, B, C, D, E, F);
+impl_append!(A, B, C, D, E, F, G);
+
 /// `Read` marks a `Component` within a `Query` as read-only.
 pub struct Read<C: Component>(C);
 
 /// `Write` marks a `Component` within a `Query` as read-and-write.
 pub struct Write<C: Component>(C);
 
-impl<'a, C: Component> Reader<'a> for Read<C> {
-    type Component = C;
-    type Iter = std::slice::Iter<'a, Option<Self::Component>>;
+/// `With` filters a `Query` to rows that contain the `Component`, without
+/// reading it.
+pub struct With<C: Component>(C);
+
+/// `Without` filters a `Query` to rows that do not contain the `Component`.
+pub struct Without<C: Component>(C);
 
-    fn find(store: &'a HashMap<TypeId, Box<ComponentStore>>) -> Self::Iter {
+impl<'a, C: Component> QueryElement<'a> for Read<C> {
+    type Item = &'a C;
+    type Kind = Data;
+    type Slots = Option<Iter<'a, C::Storage>>;
+
+    fn slots(things: &'a Things) -> Self::Slots {
         let id = TypeId::of::<C>();
-        match store.get(&id) {
-            Some(store) => store.as_store::<C>().unwrap().as_slice().iter(),
-            None => (&[]).iter(),
+        let store = things.component_stores.get(&id)?;
+        Some(store.expect_store::<C>().iter())
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        slots.as_mut()?.next().and_then(|component| component)
+    }
+
+    fn skip(slots: &mut Self::Slots, rows: usize) {
+        if let Some(slots) = slots {
+            slots.advance(rows);
         }
     }
+
+    fn matches(types: &[TypeId]) -> bool {
+        types.binary_search(&TypeId::of::<C>()).is_ok()
+    }
 }
 
-// FIXME: This implementation requires a runtime borrow-checker to make sure
-//        only one mutable reference can be fetched to any given [`Component`]
-//        at any given time.
-impl<'a, C: Component> Writer<'a> for Write<C> {
-    type Component = C;
-    type Iter = std::slice::IterMut<'a, Option<Self::Component>>;
+// The runtime borrow-checker guarantees that only one mutable reference can be
+// fetched to any given [`Component`] at any given time. This is validated
+// before a query is iterated, as its iterator can only be created with the
+// `BorrowToken` handed out by a successful validation.
+impl<'a, C: Component> QueryElement<'a> for Write<C> {
+    type Item = &'a mut C;
+    type Kind = Data;
+    type Slots = Option<IterMut<'a, C::Storage>>;
 
-    fn find(store: &'a HashMap<TypeId, Box<ComponentStore>>) -> Self::Iter {
+    fn slots(things: &'a Things) -> Self::Slots {
         let id = TypeId::of::<C>();
-        match store.get(&id) {
-            Some(store) => unsafe { store.as_store::<C>().unwrap().as_mut_slice() }.iter_mut(),
-            None => (&mut []).iter_mut(),
+        let store = things.component_stores.get(&id)?;
+        Some(unsafe { store.expect_store::<C>().iter_mut() })
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        slots.as_mut()?.next().and_then(|component| component)
+    }
+
+    fn skip(slots: &mut Self::Slots, rows: usize) {
+        if let Some(slots) = slots {
+            slots.advance(rows);
         }
     }
+
+    fn matches(types: &[TypeId]) -> bool {
+        types.binary_search(&TypeId::of::<C>()).is_ok()
+    }
 }
 
-// TODO: This is a temporary stub implementation for a `Query` consisting of a
-//       `Read`, and a `Write`. In the future, this will be implemented for A..Z
-//       using a macro.
-impl<'a, A, B> Query<'a> for (Read<A>, Write<B>)
-where
-    A: Component,
-    B: Component,
-{
-    type Borrow = Self;
-    type Iter = FilterMap<
-        Zip<<Read<A> as Reader<'a>>::Iter, <Write<B> as Writer<'a>>::Iter>,
-        fn((&'a Option<A>, &'a mut Option<B>)) -> Option<(&'a A, &'a mut B)>,
-    >;
-
-    fn iter(store: &'a HashMap<TypeId, Box<ComponentStore>>) -> Self::Iter {
-        let a = <Read<A> as Reader<'a>>::find(store);
-        let b = <Write<B> as Writer<'a>>::find(store);
-
-        a.zip(b)
-            .filter_map(|(x, y)| match (x.as_ref(), y.as_mut()) {
-                (Some(x), Some(y)) => Some((x, y)),
-                _ => None,
+/// An optional `Read` yields `Some` reference to the component for rows that
+/// contain the component, and `None` for rows that do not, instead of skipping
+/// the row altogether.
+impl<'a, C: Component> QueryElement<'a> for Option<Read<C>> {
+    type Item = Option<&'a C>;
+    type Kind = Data;
+    type Slots = <Read<C> as QueryElement<'a>>::Slots;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        <Read<C> as QueryElement<'a>>::slots(things)
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        Some(<Read<C> as QueryElement<'a>>::fetch(slots))
+    }
+
+    fn skip(slots: &mut Self::Slots, rows: usize) {
+        <Read<C> as QueryElement<'a>>::skip(slots, rows)
+    }
+}
+
+/// An optional `Write` yields `Some` mutable reference to the component for
+/// rows that contain the component, and `None` for rows that do not, instead
+/// of skipping the row altogether.
+impl<'a, C: Component> QueryElement<'a> for Option<Write<C>> {
+    type Item = Option<&'a mut C>;
+    type Kind = Data;
+    type Slots = <Write<C> as QueryElement<'a>>::Slots;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        <Write<C> as QueryElement<'a>>::slots(things)
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        Some(<Write<C> as QueryElement<'a>>::fetch(slots))
+    }
+
+    fn skip(slots: &mut Self::Slots, rows: usize) {
+        <Write<C> as QueryElement<'a>>::skip(slots, rows)
+    }
+}
+
+impl<'a, C: Component> QueryElement<'a> for With<C> {
+    type Item = ();
+    type Kind = Filter;
+    type Slots = <Read<C> as QueryElement<'a>>::Slots;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        <Read<C> as QueryElement<'a>>::slots(things)
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        <Read<C> as QueryElement<'a>>::fetch(slots).map(|_| ())
+    }
+
+    fn skip(slots: &mut Self::Slots, rows: usize) {
+        <Read<C> as QueryElement<'a>>::skip(slots, rows)
+    }
+
+    fn matches(types: &[TypeId]) -> bool {
+        <Read<C> as QueryElement<'a>>::matches(types)
+    }
+}
+
+// `Without` does not skip the rows of entities holding the component, as an
+// entity spanning multiple rows may not hold it on every row.
+impl<'a, C: Component> QueryElement<'a> for Without<C> {
+    type Item = ();
+    type Kind = Filter;
+    type Slots = <Read<C> as QueryElement<'a>>::Slots;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        <Read<C> as QueryElement<'a>>::slots(things)
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        match <Read<C> as QueryElement<'a>>::fetch(slots) {
+            Some(_) => None,
+            None => Some(()),
+        }
+    }
+
+    fn skip(slots: &mut Self::Slots, rows: usize) {
+        <Read<C> as QueryElement<'a>>::skip(slots, rows)
+    }
+}
+
+/// `Changed` filters a `Query` to rows that contain the `Component`, and whose
+/// component was added or mutably accessed since the system last ran.
+///
+/// Writing to a component through [`Write`] or [`Things::get_mut`] marks it as
+/// changed, whether or not its value is actually modified. Reading it through
+/// [`Read`] never does.
+pub struct Changed<C: Component>(C);
+
+/// `Added` filters a `Query` to rows that contain the `Component`, and whose
+/// component was added since the system last ran.
+pub struct Added<C: Component>(C);
+
+impl<'a, C: Component> QueryElement<'a> for Changed<C> {
+    type Item = ();
+    type Kind = Filter;
+    type Slots = (Option<&'a C::Storage>, RangeFrom<usize>, usize);
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        Self::slots_since(things, 0)
+    }
+
+    fn slots_since(things: &'a Things, last_run: usize) -> Self::Slots {
+        let store = things.component_stores.get(&TypeId::of::<C>());
+        (store.and_then(|store| store.as_store::<C>()), 0.., last_run)
+    }
+
+    fn fetch((store, rows, last_run): &mut Self::Slots) -> Option<Self::Item> {
+        let ticks = store.as_ref()?.ticks(rows.next()?)?;
+        (ticks.changed > *last_run).then_some(())
+    }
+
+    fn skip((_, positions, _): &mut Self::Slots, rows: usize) {
+        positions.start += rows;
+    }
+
+    fn matches(types: &[TypeId]) -> bool {
+        <Read<C> as QueryElement<'a>>::matches(types)
+    }
+}
+
+impl<'a, C: Component> QueryElement<'a> for Added<C> {
+    type Item = ();
+    type Kind = Filter;
+    type Slots = <Changed<C> as QueryElement<'a>>::Slots;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        <Changed<C> as QueryElement<'a>>::slots(things)
+    }
+
+    fn slots_since(things: &'a Things, last_run: usize) -> Self::Slots {
+        <Changed<C> as QueryElement<'a>>::slots_since(things, last_run)
+    }
+
+    fn fetch((store, rows, last_run): &mut Self::Slots) -> Option<Self::Item> {
+        let ticks = store.as_ref()?.ticks(rows.next()?)?;
+        (ticks.added > *last_run).then_some(())
+    }
+
+    fn skip(slots: &mut Self::Slots, rows: usize) {
+        <Changed<C> as QueryElement<'a>>::skip(slots, rows)
+    }
+
+    fn matches(types: &[TypeId]) -> bool {
+        <Read<C> as QueryElement<'a>>::matches(types)
+    }
+}
+
+/// `Or` filters a `Query` to rows that match at least one of a tuple of
+/// filters, such as `Or<(With<Sprite>, With<Mesh>)>`.
+///
+/// Like any other filter, `Or` does not contribute an item to the rows of the
+/// query, so only [`Filter`] elements can be combined, and the components of
+/// the matched rows are read by the other elements of the query. A row that
+/// matches several filters is yielded once.
+///
+/// ```
+/// # use things::{Component, Or, Read, Things, With};
+/// # #[derive(Component, Debug)]
+/// # struct Sprite;
+/// # #[derive(Component, Debug)]
+/// # struct Mesh;
+/// # #[derive(Component, Debug)]
+/// # struct Position(u32);
+/// let mut ecs = Things::new();
+/// ecs.create_entity((Position(1), Sprite));
+/// ecs.create_entity((Position(2), Mesh));
+/// ecs.create_entity((Position(3), Sprite, Mesh));
+/// ecs.create_entity((Position(4),));
+///
+/// let drawn = ecs
+///     .query::<(Read<Position>, Or<(With<Sprite>, With<Mesh>)>)>()
+///     .unwrap()
+///     .map(|(position,)| position.0)
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(drawn, vec![1, 2, 3]);
+/// ```
+pub struct Or<F>(F);
+
+/// Implements [`QueryElement`] for [`Or`] over a tuple of filters, matching
+/// the arities for which [`Query`] is implemented.
+///
+/// Every filter is fetched for every row, even once a filter matched, as the
+/// slots of all filters have to advance in lockstep.
+macro_rules! impl_or {
+    ($($element:ident),+) => {
+        impl<'a, $($element),+> QueryElement<'a> for Or<($($element,)+)>
+        where
+            $($element: QueryElement<'a, Kind = Filter>,)+
+        {
+            type Item = ();
+            type Kind = Filter;
+            type Slots = ($($element::Slots,)+);
+
+            fn slots(things: &'a Things) -> Self::Slots {
+                ($($element::slots(things),)+)
+            }
+
+            fn slots_since(things: &'a Things, last_run: usize) -> Self::Slots {
+                ($($element::slots_since(things, last_run),)+)
+            }
+
+            fn slots_range(
+                things: &'a Things,
+                last_run: usize,
+                rows: Range<usize>,
+            ) -> Self::Slots {
+                ($($element::slots_range(things, last_run, rows.clone()),)+)
+            }
+
+            #[allow(non_snake_case)]
+            fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+                let ($($element,)+) = slots;
+                let matched = false;
+                $(let matched = $element::fetch($element).is_some() | matched;)+
+
+                matched.then_some(())
+            }
+
+            #[allow(non_snake_case)]
+            fn skip(slots: &mut Self::Slots, rows: usize) {
+                let ($($element,)+) = slots;
+                $($element::skip($element, rows);)+
+            }
+
+            fn matches(types: &[TypeId]) -> bool {
+                $($element::matches(types))||+
+            }
+        }
+
+        unsafe impl<$($element: ReadOnly),+> ReadOnly for Or<($($element,)+)> {}
+    };
+}
+
+impl_or!(A);
+impl_or!(A, B);
+impl_or!(A, B, C);
+impl_or!(A, B, C, D);
+impl_or!(A, B, C, D, E);
+impl_or!(A, B, C, D, E, F);
+impl_or!(A, B, C, D, E, F, G);
+impl_or!(A, B, C, D, E, F, G, H);
+
+/// Using `Entity` as an element of a query yields the handle of the entity that
+/// owns the components of each row.
+///
+/// An entity holding multiple components of the same type spans multiple rows,
+/// so its handle is yielded once for every row that matches the query.
+impl<'a> QueryElement<'a> for Entity {
+    type Item = Entity;
+    type Kind = Data;
+    type Slots = alloc::vec::IntoIter<Option<Entity>>;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        Self::slots_range(things, 0, 0..things.component_cursor)
+    }
+
+    /// Only the handles of the given rows are collected, so the chunks of a
+    /// parallel query don't each allocate a slot for every row.
+    fn slots_range(things: &'a Things, _last_run: usize, rows: Range<usize>) -> Self::Slots {
+        let mut entities = vec![None; rows.len()];
+
+        for (entity, &(position, len)) in &things.entity_component_references {
+            let start = core::cmp::max(position, rows.start);
+            let end = core::cmp::min(position + len, rows.end);
+
+            if start < end {
+                for slot in &mut entities[start - rows.start..end - rows.start] {
+                    *slot = Some(*entity);
+                }
+            }
+        }
+
+        entities.into_iter()
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        slots.next().and_then(|entity| entity)
+    }
+
+    fn skip(slots: &mut Self::Slots, rows: usize) {
+        if let Some(last) = rows.checked_sub(1) {
+            slots.nth(last);
+        }
+    }
+}
+
+/// `Tick` yields the current tick of the world within a `Query`, the same tick
+/// for every row, see [`Things::tick`].
+///
+/// This allows systems to keep time, such as for cooldowns, without having to
+/// maintain a resource of their own.
+pub struct Tick;
+
+impl<'a> QueryElement<'a> for Tick {
+    type Item = u64;
+    type Kind = Data;
+    type Slots = u64;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        things.tick()
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        Some(*slots)
+    }
+
+    fn skip(_slots: &mut Self::Slots, _rows: usize) {}
+}
+
+/// `World` yields a shared reference to the whole world within a `Query`, the
+/// same reference for every row.
+///
+/// This is the escape hatch for logic that can't be expressed as a flat query,
+/// such as following a reference to another entity to read its components:
+///
+/// ```
+/// # use things::{Component, Entity, Query, Read, System, Things, World};
+/// #[derive(Component, Debug)]
+/// struct Transform(i32);
+///
+/// #[derive(Component, Debug)]
+/// struct Parent(Entity);
+///
+/// struct FollowParent;
+/// impl<'a> System<'a> for FollowParent {
+///     type Query = (Read<Parent>, World);
+///
+///     fn update(components: <Self::Query as Query<'a>>::Iter) {
+///         for (parent, world) in components {
+///             let transform = world.get::<Transform>(parent.0).unwrap();
+///             assert_eq!(transform.0, 10);
+///         }
+///     }
+/// }
+///
+/// let mut ecs = Things::new();
+/// let parent = ecs.create_entity((Transform(10),));
+/// ecs.create_entity((Parent(parent),));
+/// ecs.execute_system::<FollowParent>().unwrap();
+/// ```
+///
+/// # Aliasing
+///
+/// Any component or resource can be read through the world, so `World` is
+/// registered as reading all of them. It conflicts with every [`Write`]
+/// element and [`ResMut`](crate::ResMut) query, in the same query and in any
+/// system run at the same time, which is reported as a
+/// [`BorrowError::WriteReadConflict`] for the written type. It never conflicts
+/// with other readers, including other `World` elements.
+///
+/// Structural changes still go through [`Commands`](crate::Commands), or
+/// [`Despawns`](crate::Despawns), as the world is only borrowed immutably.
+pub struct World;
+
+impl<'a> QueryElement<'a> for World {
+    type Item = &'a Things;
+    type Kind = Data;
+    type Slots = &'a Things;
+
+    fn slots(things: &'a Things) -> Self::Slots {
+        things
+    }
+
+    fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+        Some(*slots)
+    }
+
+    fn skip(_slots: &mut Self::Slots, _rows: usize) {}
+}
+
+/// Implements [`Query`] for a single query element, to allow querying one
+/// component type without wrapping it in a tuple.
+macro_rules! impl_query_single {
+    ($element:ident) => {
+        impl<'a, C: Component> Query<'a> for $element<C> {
+            type Borrow = Self;
+            type Iter = QueryIter<'a, Self>;
+
+            fn iter(things: &'a Things, _: BorrowToken) -> Self::Iter {
+                QueryIter::new(things)
+            }
+        }
+    };
+}
+
+impl_query_single!(Read);
+impl_query_single!(Write);
+
+/// `QueryIter` is the iterator returned by a [`Query`]. It walks all rows of
+/// the component stores, and yields the items of those rows that match every
+/// element of the query.
+///
+/// Rows of entities that lack a component required by the query, such as
+/// through [`Read`] or [`With`], are skipped based on the archetype of the
+/// entity, without visiting the stores. Every other row is checked against
+/// every element, which keeps queries correct for any combination of stores,
+/// including entities spanning multiple rows.
+///
+/// # Iteration order
+///
+/// Rows are always visited in ascending position, whatever the stores of the
+/// queried components. The position of each entity only depends on the
+/// operations applied to the world, and on their order: vacated rows are
+/// reused starting with the most recently vacated ones that fit, and no
+/// randomly seeded hashing is involved. So two worlds that go through the same
+/// operations iterate their queries in the same order, across runs and across
+/// machines, which makes them suited for lockstep simulations and replays.
+///
+/// The position order is not the order in which entities were created, as
+/// new entities may be stored in rows vacated by despawned ones, and entities
+/// that gain a component they already hold are moved to the end of the
+/// stores. [`Things::compact`] moves entities without changing their relative
+/// order. To visit the entities in the order of their handles instead, query
+/// the [`Entity`](crate::Entity) along with the components, and sort the
+/// collected items. Only [`QueryIter::par_iter`] yields its items in no
+/// particular order.
+///
+/// # Performance
+///
+/// For hot loops over components that are known to be held by the same
+/// entities, the stores can be zipped directly instead, see
+/// [`Things::store`]. With a [`DefaultStore`](crate::DefaultStore) for each
+/// type, this walks the slices of the stores without the per-row bookkeeping
+/// of a query, while each row is still checked for its components:
+///
+/// ```
+/// # use things::{Component, Things};
+/// # #[derive(Component, Debug)]
+/// # struct Velocity(u32);
+/// # #[derive(Component, Debug)]
+/// # struct Position(u32);
+/// let mut ecs = Things::new();
+/// ecs.spawn_batch((0..1000).map(|_| (Velocity(1), Position(10))));
+///
+/// let velocities = ecs.store::<Velocity>().unwrap().as_slice();
+/// let positions = ecs.store::<Position>().unwrap().as_slice();
+///
+/// let sum: u32 = velocities
+///     .iter()
+///     .zip(positions)
+///     .filter_map(|(velocity, position)| Some(velocity.as_ref()?.0 + position.as_ref()?.0))
+///     .sum();
+///
+/// assert_eq!(sum, 11_000);
+/// ```
+pub struct QueryIter<'a, E: QueryElement<'a>> {
+    slots: E::Slots,
+
+    /// row is the next row to visit, and rows the number of rows left.
+    row: usize,
+    rows: usize,
+
+    /// matching holds whether the rows of each archetype can match the query,
+    /// or `None` if every row has to be visited.
+    matching: Option<Vec<bool>>,
+
+    /// things and last_run are kept to look up the archetype of each row, and
+    /// to prepare new slots for the chunks of a parallel iteration.
+    things: &'a Things,
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    last_run: usize,
+}
+
+impl<'a, E: QueryElement<'a>> QueryIter<'a, E> {
+    pub(crate) fn new(things: &'a Things) -> Self {
+        Self::since(things, 0)
+    }
+
+    fn since(things: &'a Things, last_run: usize) -> Self {
+        QueryIter {
+            slots: E::slots_since(things, last_run),
+            row: 0,
+            rows: things.component_cursor,
+            matching: things.archetypes.matching(E::matches),
+            things,
+            last_run,
+        }
+    }
+
+    /// Returns an iterator over `rows` rows, starting at row `start`.
+    #[cfg(feature = "parallel")]
+    fn chunk(things: &'a Things, last_run: usize, start: usize, rows: usize) -> Self {
+        QueryIter {
+            slots: E::slots_range(things, last_run, start..start + rows),
+            row: start,
+            rows,
+            matching: things.archetypes.matching(E::matches),
+            things,
+            last_run,
+        }
+    }
+
+    /// Returns a parallel iterator over the remaining rows, yielding the same
+    /// items as this iterator, in no particular order.
+    ///
+    /// The rows are split into chunks by their position, and each chunk is
+    /// iterated by a single thread, using `rayon`. Because components of a
+    /// single row are never split across chunks, no two threads ever access
+    /// the same component.
+    ///
+    /// ```
+    /// # use things::{Component, Read, Things, Write};
+    /// use rayon::prelude::*;
+    ///
+    /// # #[derive(Component, Debug)]
+    /// # struct Velocity(u8);
+    /// # #[derive(Component, Debug)]
+    /// # struct Position(u32);
+    /// let mut ecs = Things::new();
+    /// ecs.spawn_batch((0..1000).map(|_| (Velocity(1), Position(10))));
+    ///
+    /// ecs.query::<(Read<Velocity>, Write<Position>)>()
+    ///     .unwrap()
+    ///     .par_iter()
+    ///     .for_each(|(velocity, position)| position.0 += u32::from(velocity.0));
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn par_iter(self) -> impl rayon::iter::ParallelIterator<Item = E::Item> + 'a
+    where
+        E: 'a,
+        E::Item: Send,
+    {
+        use rayon::prelude::*;
+
+        let QueryIter {
+            row: start,
+            rows,
+            things,
+            last_run,
+            ..
+        } = self;
+
+        // The rows are split into a few chunks per thread, which leaves room
+        // for balancing uneven work, while keeping the cost of preparing the
+        // slots of each chunk low.
+        let chunk_rows = core::cmp::max(PAR_CHUNK_ROWS, rows / (rayon::current_num_threads() * 4));
+        let chunks = rows.div_ceil(chunk_rows);
+
+        // The slots of each chunk are prepared in the lane of the running
+        // system, so its commands are applied along with those of the system.
+        let lane = crate::command::current_lane();
+
+        (0..chunks).into_par_iter().flat_map_iter(move |chunk| {
+            let offset = chunk * chunk_rows;
+            let len = core::cmp::min(chunk_rows, rows - offset);
+
+            crate::command::with_lane(lane, || {
+                QueryIter::<E>::chunk(things, last_run, start + offset, len)
             })
+        })
+    }
+
+    /// Returns an iterator that only yields the rows for which the predicate
+    /// returns `true`, to filter rows on the values of their components rather
+    /// than on their presence.
+    ///
+    /// This behaves like [`Iterator::filter`], but returns a [`QueryFilter`],
+    /// which can be named without referring to the iterator it wraps.
+    ///
+    /// ```
+    /// # use things::{Component, Things, Write};
+    /// #[derive(Component, Debug)]
+    /// struct Health {
+    ///     current: u32,
+    ///     max: u32,
+    /// }
+    ///
+    /// let mut ecs = Things::new();
+    /// ecs.create_entity((Health { current: 5, max: 10 },));
+    /// ecs.create_entity((Health { current: 10, max: 10 },));
+    ///
+    /// let wounded = ecs
+    ///     .query::<Write<Health>>()
+    ///     .unwrap()
+    ///     .filter(|health| health.current < health.max);
+    ///
+    /// for health in wounded {
+    ///     health.current += 1;
+    /// }
+    /// ```
+    pub fn filter<F>(self, predicate: F) -> QueryFilter<'a, E, F>
+    where
+        F: FnMut(&E::Item) -> bool,
+    {
+        QueryFilter {
+            iter: self,
+            predicate,
+        }
+    }
+
+    /// Returns an iterator that yields the matching rows in arrays of `N`
+    /// rows, to process several rows at a time, such as to vectorize math
+    /// over their components.
+    ///
+    /// Rows that do not match the query are skipped, so every chunk holds
+    /// exactly `N` rows. The rows left over after the last full chunk are
+    /// returned by [`QueryChunks::into_remainder`]. The components of a chunk
+    /// are not necessarily adjacent in their stores, and can be spread over
+    /// multiple stores, so the items are collected into an array rather than
+    /// handed out as slices of the stores.
+    ///
+    /// ```
+    /// # use things::{Component, Things, Write};
+    /// # #[derive(Component, Debug)]
+    /// # struct Position(f32);
+    /// let mut ecs = Things::new();
+    /// ecs.spawn_batch((0..10).map(|x| (Position(x as f32),)));
+    ///
+    /// let mut chunks = ecs.query::<Write<Position>>().unwrap().array_chunks::<8>();
+    /// for chunk in &mut chunks {
+    ///     let mut xs = chunk.each_ref().map(|position| position.0);
+    ///     xs.iter_mut().for_each(|x| *x *= 2.0);
+    ///
+    ///     for (x, position) in xs.iter().zip(chunk) {
+    ///         position.0 = *x;
+    ///     }
+    /// }
+    ///
+    /// for position in chunks.into_remainder() {
+    ///     position.0 *= 2.0;
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero.
+    pub fn array_chunks<const N: usize>(self) -> QueryChunks<'a, E, N> {
+        assert!(N != 0, "chunk size must be non-zero");
+
+        QueryChunks {
+            iter: self,
+            remainder: Vec::new(),
+        }
+    }
+
+    /// Returns an iterator that starts `n` rows further, without visiting the
+    /// skipped rows.
+    ///
+    /// Unlike [`Iterator::skip`], this counts rows rather than matching items,
+    /// so rows that do not match the query count as well. Skipping rows only
+    /// advances the position of each element, without fetching any of the
+    /// skipped items, which makes it cheap to resume
+    /// work at a saved offset, such as to spread heavy work over several
+    /// frames along with [`QueryIter::take_rows`] and [`QueryIter::rows`]:
+    ///
+    /// ```
+    /// # use things::{Component, Things, Write};
+    /// # #[derive(Component, Debug)]
+    /// # struct Agent(u32);
+    /// const ROWS_PER_FRAME: usize = 100;
+    ///
+    /// let mut ecs = Things::new();
+    /// ecs.spawn_batch((0..1000).map(|_| (Agent(0),)));
+    ///
+    /// let mut offset = 0;
+    /// for _frame in 0..10 {
+    ///     let agents = ecs.query::<Write<Agent>>().unwrap();
+    ///     let rows = agents.rows();
+    ///
+    ///     for agent in agents.skip_rows(offset).take_rows(ROWS_PER_FRAME) {
+    ///         agent.0 += 1;
+    ///     }
+    ///
+    ///     offset = (offset + ROWS_PER_FRAME) % rows;
+    /// }
+    ///
+    /// assert!(ecs.query::<Write<Agent>>().unwrap().all(|agent| agent.0 == 1));
+    /// ```
+    #[doc(alias = "skip")]
+    pub fn skip_rows(mut self, n: usize) -> Self {
+        let n = core::cmp::min(n, self.rows);
+
+        E::skip(&mut self.slots, n);
+        self.row += n;
+        self.rows -= n;
+        self
+    }
+
+    /// Returns an iterator that stops after visiting `n` more rows.
+    ///
+    /// Unlike [`Iterator::take`], this counts rows rather than matching items,
+    /// see [`QueryIter::skip_rows`].
+    #[doc(alias = "take")]
+    pub fn take_rows(mut self, n: usize) -> Self {
+        self.rows = core::cmp::min(n, self.rows);
+        self
+    }
+
+    /// Returns the number of rows left to visit, whether they match the query
+    /// or not.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the only row matching the query, for queries that are expected
+    /// to match a single entity, such as the player or the camera.
+    ///
+    /// Rows are yielded with mutable references for `Write` elements, so this
+    /// works the same for reading and writing queries. Returns an error if no
+    /// row, or more than one row matches.
+    pub fn single(mut self) -> Result<E::Item, SingleError> {
+        let item = self.next().ok_or(SingleError::NotFound)?;
+
+        match self.next() {
+            Some(_) => Err(SingleError::Multiple),
+            None => Ok(item),
+        }
     }
 }
 
-// TODO: This is a temporary stub implementation for a `Query` consisting of two
-//       `Read`s. In the future, this will be implemented for A..Z using a
-//       macro.
-impl<'a, A, B> Query<'a> for (Read<A>, Read<B>)
+/// The minimum number of rows iterated by a single thread in
+/// [`QueryIter::par_iter`].
+#[cfg(feature = "parallel")]
+const PAR_CHUNK_ROWS: usize = 1024;
+
+/// The error returned by [`QueryIter::single`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SingleError {
+    /// No row matches the query.
+    NotFound,
+
+    /// More than one row matches the query.
+    Multiple,
+}
+
+impl fmt::Display for SingleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SingleError::NotFound => "no entity matches the query",
+            SingleError::Multiple => "more than one entity matches the query",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for SingleError {}
+
+impl<'a, E: QueryElement<'a>> Iterator for QueryIter<'a, E> {
+    type Item = E::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.rows > 0 {
+            if let Some(ref matching) = self.matching {
+                let archetypes = &self.things.archetypes;
+                let skipped = archetypes.skippable(self.row, self.rows, matching);
+
+                if skipped > 0 {
+                    E::skip(&mut self.slots, skipped);
+                    self.row += skipped;
+                    self.rows -= skipped;
+                    continue;
+                }
+            }
+
+            self.row += 1;
+            self.rows -= 1;
+
+            if let Some(item) = E::fetch(&mut self.slots) {
+                return Some(item);
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.rows))
+    }
+}
+
+/// `QueryFilter` is an iterator over the rows of a query that satisfy a
+/// predicate.
+///
+/// This `struct` is created by [`QueryIter::filter`].
+pub struct QueryFilter<'a, E: QueryElement<'a>, F> {
+    iter: QueryIter<'a, E>,
+    predicate: F,
+}
+
+impl<'a, E, F> Iterator for QueryFilter<'a, E, F>
 where
-    A: Component,
-    B: Component,
+    E: QueryElement<'a>,
+    F: FnMut(&E::Item) -> bool,
 {
-    type Borrow = Self;
-    type Iter = FilterMap<
-        Zip<<Read<A> as Reader<'a>>::Iter, <Read<B> as Reader<'a>>::Iter>,
-        fn((&'a Option<A>, &'a Option<B>)) -> Option<(&'a A, &'a B)>,
-    >;
-
-    fn iter(store: &'a HashMap<TypeId, Box<ComponentStore>>) -> Self::Iter {
-        let a = <Read<A> as Reader<'a>>::find(store);
-        let b = <Read<B> as Reader<'a>>::find(store);
-
-        a.zip(b)
-            .filter_map(|(a, b)| match (a.as_ref(), b.as_ref()) {
-                (Some(a), Some(b)) => Some((a, b)),
-                _ => None,
-            })
+    type Item = E::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let predicate = &mut self.predicate;
+        self.iter.find(|item| predicate(item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// `QueryChunks` is an iterator over the rows of a query, in arrays of `N`
+/// rows.
+///
+/// This `struct` is created by [`QueryIter::array_chunks`].
+pub struct QueryChunks<'a, E: QueryElement<'a>, const N: usize> {
+    iter: QueryIter<'a, E>,
+
+    /// remainder holds the rows fetched for a chunk that could not be filled.
+    remainder: Vec<E::Item>,
+}
+
+impl<'a, E: QueryElement<'a>, const N: usize> QueryChunks<'a, E, N> {
+    /// Returns the rows that were not yielded in a full chunk, which are the
+    /// fewer than `N` rows left over after the last chunk, once the iterator is
+    /// exhausted.
+    pub fn into_remainder(self) -> Vec<E::Item> {
+        let QueryChunks {
+            iter,
+            mut remainder,
+        } = self;
+
+        remainder.extend(iter);
+        remainder
+    }
+}
+
+impl<'a, E: QueryElement<'a>, const N: usize> Iterator for QueryChunks<'a, E, N> {
+    type Item = [E::Item; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk: [Option<E::Item>; N] = core::array::from_fn(|_| None);
+
+        for slot in &mut chunk {
+            *slot = self.iter.next();
+            if slot.is_none() {
+                self.remainder
+                    .extend(IntoIterator::into_iter(chunk).flatten());
+                return None;
+            }
+        }
+
+        Some(chunk.map(Option::unwrap))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, rows) = self.iter.size_hint();
+        (0, rows.map(|rows| rows / N))
+    }
+}
+
+/// Implements [`QueryElement`] and [`Query`] for a tuple of query elements.
+///
+/// Every element is fetched for every row, even if an earlier element did not
+/// match, to keep the slots of all elements aligned. The yielded row only
+/// contains the items of [`Data`] elements, so `(Read<A>, With<B>, Read<C>)`
+/// yields `(&A, &C)`.
+///
+/// Optional elements never skip a row, and keep their place in the yielded
+/// row, so `(Read<A>, Option<Write<B>>, With<C>, Without<D>)` yields
+/// `(&A, Option<&mut B>)` for every entity with an `A` and a `C`, but no `D`.
+///
+/// The item type is built up by appending the item of each element to the
+/// items of the elements before it, collecting the required [`Append`] bounds
+/// along the way.
+macro_rules! impl_query {
+    ($($element:ident),+) => {
+        impl_query!(@build [$($element),+] [()] [] $($element),+);
+    };
+
+    (@build [$($all:ident),+] [$row:ty] [$($bounds:tt)*] $head:ident $(, $tail:ident)*) => {
+        impl_query!(
+            @build
+            [$($all),+]
+            [<$row as Append<$head::Item, $head::Kind>>::Output]
+            [$($bounds)* $row: Append<$head::Item, $head::Kind>,]
+            $($tail),*
+        );
+    };
+
+    (@build [$($element:ident),+] [$row:ty] [$($bounds:tt)*]) => {
+        impl<'a, $($element),+> QueryElement<'a> for ($($element,)+)
+        where
+            $($element: QueryElement<'a>,)+
+            $($bounds)*
+        {
+            type Item = $row;
+            type Kind = Data;
+            type Slots = ($($element::Slots,)+);
+
+            fn slots(things: &'a Things) -> Self::Slots {
+                ($($element::slots(things),)+)
+            }
+
+            fn slots_since(things: &'a Things, last_run: usize) -> Self::Slots {
+                ($($element::slots_since(things, last_run),)+)
+            }
+
+            fn slots_range(
+                things: &'a Things,
+                last_run: usize,
+                rows: Range<usize>,
+            ) -> Self::Slots {
+                ($($element::slots_range(things, last_run, rows.clone()),)+)
+            }
+
+            #[allow(non_snake_case)]
+            fn fetch(slots: &mut Self::Slots) -> Option<Self::Item> {
+                let ($($element,)+) = slots;
+                $(let $element = $element::fetch($element);)+
+
+                let row = ();
+                $(let row = Append::<_, $element::Kind>::append(row, $element?);)+
+
+                Some(row)
+            }
+
+            #[allow(non_snake_case)]
+            fn skip(slots: &mut Self::Slots, rows: usize) {
+                let ($($element,)+) = slots;
+                $($element::skip($element, rows);)+
+            }
+
+            fn matches(types: &[TypeId]) -> bool {
+                $($element::matches(types))&&+
+            }
+        }
+
+        impl<'a, $($element),+> Query<'a> for ($($element,)+)
+        where
+            Self: QueryElement<'a>,
+        {
+            type Borrow = Self;
+            type Iter = QueryIter<'a, Self>;
+
+            fn iter(things: &'a Things, _: BorrowToken) -> Self::Iter {
+                QueryIter::new(things)
+            }
+
+            fn iter_since(things: &'a Things, last_run: usize, _: BorrowToken) -> Self::Iter {
+                QueryIter::since(things, last_run)
+            }
+        }
+    };
+}
+
+impl_query!(A);
+impl_query!(A, B);
+impl_query!(A, B, C);
+impl_query!(A, B, C, D);
+impl_query!(A, B, C, D, E);
+impl_query!(A, B, C, D, E, F);
+impl_query!(A, B, C, D, E, F, G);
+impl_query!(A, B, C, D, E, F, G, H);
+
+/// `QuerySet` combines two or more queries into a single [`Query`], which
+/// yields a tuple holding the iterator of each query, so a single system can
+/// iterate them at the same time.
+///
+/// The queries are iterated independently, each over all of its matching
+/// rows. Each query is validated on its own, and the queries are then
+/// validated against each other: a component that is written by one query
+/// can't be read or written by any of the others, as a row matched by both
+/// would hand out aliasing references. Such a set fails with a
+/// [`BorrowError`]. Filters are not taken into account, so this holds even if
+/// the queries match different entities, such as through [`With`] and
+/// [`Without`].
+///
+/// ```
+/// # use things::{Component, QuerySet, Read, Things, With, Write};
+/// # #[derive(Component, Debug)]
+/// # struct Player;
+/// # #[derive(Component, Debug)]
+/// # struct Enemy;
+/// # #[derive(Component, Debug)]
+/// # struct Position(i32);
+/// # #[derive(Component, Debug)]
+/// # struct Target(i32);
+/// let mut ecs = Things::new();
+/// ecs.create_entity((Player, Position(10)));
+/// ecs.create_entity((Enemy, Target(0)));
+///
+/// let (players, enemies) = ecs
+///     .query::<QuerySet<((Read<Position>, With<Player>), (Write<Target>, With<Enemy>))>>()
+///     .unwrap();
+///
+/// let players = players.map(|(position,)| position.0).collect::<Vec<_>>();
+/// for (target,) in enemies {
+///     target.0 = players[0];
+/// }
+/// ```
+pub struct QuerySet<Q>(Q);
+
+macro_rules! impl_query_set {
+    ($($query:ident),+) => {
+        impl<'a, $($query),+> Query<'a> for QuerySet<($($query,)+)>
+        where
+            $($query: Query<'a>,)+
+        {
+            type Borrow = QuerySet<($($query::Borrow,)+)>;
+            type Iter = ($($query::Iter,)+);
+
+            // The queries of the set have been validated together, so each of
+            // them is given a token of its own.
+            fn iter(things: &'a Things, _: BorrowToken) -> Self::Iter {
+                ($($query::iter(things, BorrowToken::new()),)+)
+            }
+
+            fn iter_since(things: &'a Things, last_run: usize, _: BorrowToken) -> Self::Iter {
+                ($($query::iter_since(things, last_run, BorrowToken::new()),)+)
+            }
+        }
+    };
+}
+
+impl_query_set!(A, B);
+impl_query_set!(A, B, C);
+impl_query_set!(A, B, C, D);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Component;
+
+    #[derive(Component, Debug, PartialEq)]
+    struct A(u8);
+
+    #[derive(Component, Debug, PartialEq)]
+    struct B(u16);
+
+    #[derive(Component, Debug, PartialEq)]
+    struct C(u32);
+
+    #[derive(Component, Debug, PartialEq)]
+    struct Tag;
+
+    /// Iterates a query without validating its borrows, which every query in
+    /// these tests passes.
+    fn query_unchecked<'a, Q: Query<'a>>(things: &'a Things) -> Q::Iter {
+        Q::iter(things, BorrowToken::new())
+    }
+
+    #[test]
+    fn test_query_mixed_elements() {
+        let mut things = Things::new();
+        things.create_entity((A(1), B(10), C(100)));
+        things.create_entity((A(2), B(20), C(200)));
+
+        let iter = query_unchecked::<(Read<A>, Write<B>, Read<C>)>(&things);
+        for (a, b, c) in iter {
+            b.0 += u16::from(a.0) + c.0 as u16;
+        }
+
+        let rows: Vec<_> = query_unchecked::<(Read<B>,)>(&things).collect();
+        assert_eq!(rows, vec![(&B(111),), (&B(222),)]);
+    }
+
+    #[test]
+    fn test_query_skips_archetypes() {
+        let mut things = Things::new();
+        for i in 0..10 {
+            things.create_entity((A(i),));
+        }
+        let b = things.create_entity((A(10), B(10)));
+        things.create_entity((B(11), B(12), C(11)));
+        things.create_entity((A(12),));
+
+        let rows: Vec<_> = query_unchecked::<(Read<A>, Read<B>)>(&things).collect();
+        assert_eq!(rows, vec![(&A(10), &B(10))]);
+
+        let rows: Vec<_> = query_unchecked::<(Read<B>, Without<C>)>(&things).collect();
+        assert_eq!(rows, vec![(&B(10),), (&B(12),)]);
+
+        things.add_component(b, C(10));
+        things.store_mut::<C>().unwrap().remove(11);
+        let rows: Vec<_> = query_unchecked::<(Read<B>, Read<C>)>(&things).collect();
+        assert_eq!(rows, vec![(&B(10), &C(10))]);
+
+        things.refresh_archetypes();
+        let rows: Vec<_> = query_unchecked::<Write<C>>(&things).collect();
+        assert_eq!(rows, vec![&mut C(10)]);
+    }
+
+    #[test]
+    fn test_query_filter() {
+        let mut things = Things::new();
+        for i in 0..6 {
+            things.create_entity((A(i), B(0)));
+        }
+
+        type Row<'a> = (&'a A, &'a mut B);
+        type Even<'a> = QueryFilter<'a, (Read<A>, Write<B>), fn(&Row) -> bool>;
+
+        fn even((a, _): &Row) -> bool {
+            a.0 % 2 == 0
+        }
+
+        let rows: Even = query_unchecked::<(Read<A>, Write<B>)>(&things).filter(even);
+        for (a, b) in rows {
+            b.0 = u16::from(a.0);
+        }
+
+        let rows: Vec<_> = query_unchecked::<Read<B>>(&things).map(|b| b.0).collect();
+        assert_eq!(rows, vec![0, 0, 2, 0, 4, 0]);
+    }
+
+    #[test]
+    fn test_query_optional_and_filters() {
+        let mut things = Things::new();
+        things.create_entity((A(1), B(10), Tag));
+        things.create_entity((A(2), Tag));
+        things.create_entity((A(3), B(30), Tag, C(300)));
+        things.create_entity((A(4), B(40)));
+
+        type Q = (Read<A>, Option<Write<B>>, With<Tag>, Without<C>);
+        for (a, b) in query_unchecked::<Q>(&things) {
+            if let Some(b) = b {
+                b.0 += u16::from(a.0);
+            }
+        }
+
+        let rows: Vec<_> = query_unchecked::<(Read<A>, Option<Read<B>>)>(&things).collect();
+        assert_eq!(
+            rows,
+            vec![
+                (&A(1), Some(&B(11))),
+                (&A(2), None),
+                (&A(3), Some(&B(30))),
+                (&A(4), Some(&B(40)))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_single_element() {
+        let mut things = Things::new();
+        things.create_entity((A(1),));
+        things.create_entity((A(2),));
+
+        for a in query_unchecked::<Write<A>>(&things) {
+            a.0 *= 10;
+        }
+
+        let rows: Vec<_> = query_unchecked::<Read<A>>(&things).collect();
+        assert_eq!(rows, vec![&A(10), &A(20)]);
+    }
+
+    #[test]
+    fn test_query_optional_element() {
+        let mut things = Things::new();
+        things.create_entity((A(1), B(10)));
+        things.create_entity((A(2),));
+        things.create_entity((Tag,));
+
+        let rows: Vec<_> = query_unchecked::<(Read<A>, Option<Read<B>>)>(&things).collect();
+        assert_eq!(rows, vec![(&A(1), Some(&B(10))), (&A(2), None)]);
+    }
+
+    #[test]
+    fn test_query_filter_elements() {
+        let mut things = Things::new();
+        things.create_entity((A(1), Tag, B(10)));
+        things.create_entity((A(2), Tag, B(20)));
+        things.create_entity((A(3), B(30)));
+        things.create_entity((A(4),));
+
+        let rows: Vec<_> = query_unchecked::<(Read<A>, Without<B>)>(&things).collect();
+        assert_eq!(rows, vec![(&A(4),)]);
+
+        let rows: Vec<_> = query_unchecked::<(With<B>, Read<A>, Without<Tag>)>(&things).collect();
+        assert_eq!(rows, vec![(&A(3),)]);
+    }
+
+    #[test]
+    fn test_query_entity_element() {
+        let mut things = Things::new();
+        let a = things.create_entity((A(1),));
+        let b = things.create_entity((Tag,));
+        let c = things.create_entity((A(3), A(4)));
+
+        let rows: Vec<_> = query_unchecked::<(Entity, Read<A>)>(&things).collect();
+        assert_eq!(rows, vec![(a, &A(1)), (c, &A(3)), (c, &A(4))]);
+
+        let rows: Vec<_> = query_unchecked::<(Entity, With<Tag>)>(&things).collect();
+        assert_eq!(rows, vec![(b,)]);
+    }
+
+    #[test]
+    fn test_query_skips_unmatched_rows() {
+        let mut things = Things::new();
+        things.create_entity((A(1), B(10)));
+        things.create_entity((A(2),));
+        things.create_entity((A(3),));
+
+        let rows: Vec<_> = query_unchecked::<(Read<A>, Read<B>)>(&things).collect();
+        assert_eq!(rows, vec![(&A(1), &B(10))]);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_query_par_iter() {
+        use rayon::prelude::*;
+
+        let mut things = Things::new();
+        for i in 0..5000u32 {
+            match i % 3 {
+                0 => things.create_entity((A(1), C(i))),
+                1 => things.create_entity((A(2), B(1), C(i))),
+                _ => things.create_entity((B(2),)),
+            };
+        }
+
+        let mut query = query_unchecked::<(Read<A>, Write<C>)>(&things);
+        query.next().unwrap().1 .0 = 0;
+
+        query
+            .par_iter()
+            .for_each(|(a, c)| c.0 += u32::from(a.0) * 10_000);
+
+        let mut expected = (0..5000u32).filter(|i| i % 3 != 2).collect::<Vec<_>>();
+        for i in &mut expected[1..] {
+            *i += if *i % 3 == 0 { 10_000 } else { 20_000 };
+        }
+
+        let rows: Vec<_> = query_unchecked::<Read<C>>(&things).map(|c| c.0).collect();
+        assert_eq!(rows, expected);
+
+        let entities: Vec<_> = query_unchecked::<(Entity, With<B>)>(&things).collect();
+        let mut par_entities: Vec<_> = query_unchecked::<(Entity, With<B>)>(&things)
+            .par_iter()
+            .collect();
+        par_entities.sort_by_key(|(entity,)| entity.index().into_raw_parts().0);
+        assert_eq!(par_entities, entities);
+    }
+
+    #[test]
+    fn test_entity_slots_range() {
+        let mut things = Things::new();
+        let a = things.create_entity((A(1),));
+        let b = things.create_entity((A(2),));
+
+        // The second component moves the entity to the end, across rows 2..4.
+        things.add_component(b, A(3));
+
+        let slots = |rows| Entity::slots_range(&things, 0, rows).collect::<Vec<_>>();
+        assert_eq!(slots(0..4), vec![Some(a), None, Some(b), Some(b)]);
+        assert_eq!(slots(1..3), vec![None, Some(b)]);
+        assert_eq!(slots(3..4), vec![Some(b)]);
+        assert_eq!(slots(0..4), Entity::slots(&things).collect::<Vec<_>>());
     }
 }
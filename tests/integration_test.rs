@@ -1,13 +1,27 @@
-use things::{BorrowError, Query, Read, System, Things, Write};
+use std::{any::type_name,
+          sync::atomic::{AtomicBool, Ordering}};
+use things::{Added, AnySystem, BorrowConflict, BorrowError, BorrowKind, Bundle, Changed, Commands,
+             Component, Despawns, Entity, EntityError, EventReader, Or, Query, QueryIter,
+             QuerySet, Read, Relation, Res, ResMut, Schedule, SingleError, SparseStore,
+             StatefulSystem, Store, System, Things, Tick, With, Without, Write};
+
+#[derive(Component, Debug, PartialEq)]
+struct Count(i32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Name(&'static str);
+
+#[derive(Component, Debug, PartialEq)]
+struct Flag(bool);
 
 struct AssertValues;
 impl<'a> System<'a> for AssertValues {
-    type Query = (Read<i32>, Read<&'static str>);
+    type Query = (Read<Count>, Read<Name>);
 
     fn update(components: <Self::Query as Query<'a>>::Iter) {
         for (int, string) in components {
-            assert_eq!(int, &10);
-            assert_eq!(string, &"hello");
+            assert_eq!(int, &Count(10));
+            assert_eq!(string, &Name("hello"));
         }
     }
 }
@@ -15,19 +29,19 @@ impl<'a> System<'a> for AssertValues {
 #[test]
 fn test_reader_system() -> Result<(), BorrowError> {
     let mut ecs = Things::new();
-    ecs.create_entity((10, "hello"));
+    ecs.create_entity((Count(10), Name("hello")));
     ecs.execute_system::<AssertValues>()
 }
 
 struct IncrementCounter;
 impl<'a> System<'a> for IncrementCounter {
-    type Query = (Read<&'static str>, Write<i32>);
+    type Query = (Read<Name>, Write<Count>);
 
     fn update(components: <Self::Query as Query<'a>>::Iter) {
         for (string, int) in components {
-            *int += 1;
-            assert_eq!(int, &11);
-            assert_eq!(string, &"hello");
+            int.0 += 1;
+            assert_eq!(int, &Count(11));
+            assert_eq!(string, &Name("hello"));
         }
     }
 }
@@ -35,19 +49,2024 @@ impl<'a> System<'a> for IncrementCounter {
 #[test]
 fn test_writer_system() -> Result<(), BorrowError> {
     let mut ecs = Things::new();
-    ecs.create_entity(("hello", 10));
+    ecs.create_entity((Name("hello"), Count(10)));
     ecs.execute_system::<IncrementCounter>()
 }
 
 struct NonExclusiveMutating;
 impl<'a> System<'a> for NonExclusiveMutating {
-    type Query = (Read<&'static str>, Write<i32>);
+    type Query = (Read<Name>, Write<Count>);
 
     fn update(components: <Self::Query as Query<'a>>::Iter) {
         for (string, int) in components {
-            *int += 1;
-            assert_eq!(int, &11);
-            assert_eq!(string, &"hello");
+            int.0 += 1;
+            assert_eq!(int, &Count(11));
+            assert_eq!(string, &Name("hello"));
+        }
+    }
+}
+
+#[test]
+fn test_non_exclusive_mutating_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Name("hello"), Count(10)));
+    ecs.create_entity((Name("hello"),));
+    ecs.execute_system::<NonExclusiveMutating>()
+}
+
+#[test]
+fn test_create_entity_returns_handle() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10), Name("hello")));
+    let b = ecs.create_entity((Count(20), Name("world")));
+
+    assert_ne!(a, b);
+}
+
+#[test]
+fn test_is_alive() {
+    let mut ecs = Things::new();
+    let entity = ecs.create_entity((Count(10), Name("hello")));
+
+    assert!(ecs.is_alive(entity));
+    assert!(!Things::new().is_alive(entity));
+}
+
+#[test]
+fn test_sequential_systems() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.execute_system::<AssertValues>()?;
+    ecs.execute_system::<IncrementCounter>()
+}
+
+struct ToggleFlag;
+impl<'a> System<'a> for ToggleFlag {
+    type Query = (Read<Name>, Read<Count>, Write<Flag>);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for (string, int, flag) in components {
+            flag.0 = !flag.0;
+            assert_eq!(int, &Count(10));
+            assert_eq!(string, &Name("hello"));
+        }
+    }
+}
+
+#[test]
+fn test_three_component_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Name("hello"), Count(10), Flag(false)));
+    ecs.execute_system::<ToggleFlag>()
+}
+
+struct DoubleWrite;
+impl<'a> System<'a> for DoubleWrite {
+    type Query = (Read<Name>, Write<Count>, Write<Count>);
+
+    fn update(_: <Self::Query as Query<'a>>::Iter) {
+        unreachable!()
+    }
+}
+
+#[test]
+fn test_three_component_invalid_borrow() {
+    let mut ecs = Things::new();
+    ecs.create_entity((Name("hello"), Count(10)));
+
+    let err = ecs.execute_system::<DoubleWrite>().unwrap_err();
+    assert_eq!(
+        err,
+        BorrowError::ConflictingWrite {
+            type_name: type_name::<Count>(),
+            kind: BorrowKind::Component,
+        }
+    );
+}
+
+struct ReadWriteCount;
+impl<'a> System<'a> for ReadWriteCount {
+    type Query = (Write<Count>, Read<Count>, Commands);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for (count, _, commands) in components {
+            count.0 += 1;
+            commands.spawn((Count(0),));
+        }
+    }
+}
+
+#[test]
+fn test_invalid_borrow_skips_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10),));
+
+    assert!(ecs.execute_system::<ReadWriteCount>().is_err());
+
+    let rows: Vec<_> = ecs.query::<Read<Count>>()?.collect();
+    assert_eq!(rows, vec![&Count(10)]);
+    Ok(())
+}
+
+#[test]
+fn test_can_run() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10),));
+
+    let err = ecs.can_run::<ReadWriteCount>().unwrap_err();
+    assert_eq!(
+        err,
+        BorrowError::WriteReadConflict {
+            type_name: type_name::<Count>(),
+            kind: BorrowKind::Component,
+        }
+    );
+    ecs.can_run::<Gravity>()?;
+
+    let rows: Vec<_> = ecs.query::<Read<Count>>()?.collect();
+    assert_eq!(rows, vec![&Count(10)]);
+    Ok(())
+}
+
+#[test]
+fn test_missing_component_does_not_truncate() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(10),));
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.execute_system::<AssertValues>()
+}
+
+struct Gravity;
+impl<'a> System<'a> for Gravity {
+    type Query = Write<Count>;
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for int in components {
+            int.0 -= 1;
+        }
+    }
+}
+
+struct AssertGravity;
+impl<'a> System<'a> for AssertGravity {
+    type Query = Read<Count>;
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        assert_eq!(components.collect::<Vec<_>>(), vec![&Count(9), &Count(19)]);
+    }
+}
+
+#[test]
+fn test_single_component_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(20),));
+    ecs.execute_system::<Gravity>()?;
+    ecs.execute_system::<AssertGravity>()
+}
+
+struct Rename;
+impl<'a> System<'a> for Rename {
+    type Query = Write<Name>;
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for string in components {
+            string.0 = "world";
+        }
+    }
+}
+
+#[test]
+fn test_execute_systems() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(20),));
+
+    // `AssertGravity` conflicts with `Gravity`, so it runs in a later stage.
+    ecs.execute_systems(&[&Gravity, &Rename, &AssertGravity])?;
+
+    let rows: Vec<_> = ecs.query::<Read<Name>>()?.collect();
+    assert_eq!(rows, vec![&Name("world")]);
+
+    // No system runs if any of them has an invalid borrow.
+    assert!(ecs.execute_systems(&[&Gravity, &DoubleWrite]).is_err());
+    ecs.execute_system::<AssertGravity>()
+}
+
+const COUNTS: i32 = 100;
+
+/// Set once `SpawnSecond` recorded all of its commands.
+static SECOND_RECORDED: AtomicBool = AtomicBool::new(false);
+
+/// Spawns a `Flag(false)` for every `Count`, and marks the first counted entity
+/// to be despawned.
+struct SpawnFirst;
+impl<'a> System<'a> for SpawnFirst {
+    type Query = (Entity, Read<Count>, Commands, Despawns);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        // When run in parallel, wait for the second system to record its
+        // commands first, so a shared queue would apply them first.
+        #[cfg(feature = "parallel")]
+        {
+            let start = std::time::Instant::now();
+            while !SECOND_RECORDED.load(Ordering::SeqCst) && start.elapsed().as_secs() < 5 {
+                std::thread::yield_now();
+            }
+        }
+
+        for (entity, count, commands, despawns) in components {
+            commands.spawn((Flag(false),));
+            if count.0 == 0 {
+                despawns.mark(entity);
+            }
+        }
+    }
+}
+
+/// Spawns a `Flag(true)` for every `Count`, and marks the last counted entity
+/// to be despawned.
+struct SpawnSecond;
+impl<'a> System<'a> for SpawnSecond {
+    type Query = (Entity, Read<Count>, Commands, Despawns);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for (entity, count, commands, despawns) in components {
+            commands.spawn((Flag(true),));
+            if count.0 == COUNTS - 1 {
+                despawns.mark(entity);
+            }
+        }
+
+        SECOND_RECORDED.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_execute_systems_applies_commands_in_order() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.spawn_batch((0..COUNTS).map(|i| (Count(i),)));
+
+    // Both systems only read, so they run in the same stage, and at the same
+    // time with the `parallel` feature, on two threads even on a single core.
+    let systems: [&dyn AnySystem; 2] = [&SpawnFirst, &SpawnSecond];
+    #[cfg(feature = "parallel")]
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .build()
+        .unwrap()
+        .install(|| ecs.execute_systems(&systems))?;
+    #[cfg(not(feature = "parallel"))]
+    ecs.execute_systems(&systems)?;
+
+    let flags = ecs
+        .query::<Read<Flag>>()?
+        .map(|flag| flag.0)
+        .collect::<Vec<_>>();
+    let expected = [false, true]
+        .iter()
+        .flat_map(|&flag| vec![flag; COUNTS as usize]);
+    assert_eq!(flags, expected.collect::<Vec<_>>());
+
+    // The despawns are applied in the same order, so the row vacated last, by
+    // the second system, is reused first.
+    ecs.create_entity((Count(-1),));
+    let counts = ecs
+        .query::<Read<Count>>()?
+        .map(|count| count.0)
+        .collect::<Vec<_>>();
+    assert_eq!(counts.len(), COUNTS as usize - 1);
+    assert_eq!((counts[0], counts[counts.len() - 1]), (1, -1));
+    Ok(())
+}
+
+#[test]
+fn test_things_across_threads() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let entity = ecs.create_entity((Count(10), Name("hello")));
+
+    let ecs = std::thread::spawn(move || ecs.execute_system::<Gravity>().map(|_| ecs))
+        .join()
+        .unwrap()?;
+    assert_eq!(ecs.get::<Count>(entity), Some(&Count(9)));
+
+    let ecs = std::sync::Arc::new(ecs);
+    let shared = ecs.clone();
+    let name = std::thread::spawn(move || shared.get::<Name>(entity).map(|name| name.0));
+    assert_eq!(name.join().unwrap(), Some("hello"));
+    Ok(())
+}
+
+#[test]
+fn test_schedule() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(20),));
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(Gravity).add_system(AssertGravity);
+    schedule.run(&mut ecs)?;
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(DoubleWrite).add_system(Gravity);
+    assert!(schedule.run(&mut ecs).is_err());
+
+    ecs.execute_system::<AssertGravity>()
+}
+
+#[test]
+fn test_schedule_condition() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let entity = ecs.create_entity((Count(10),));
+    ecs.insert_resource(Flag(false));
+
+    let enabled = |things: &Things| things.get_resource::<Flag>() == Some(&Flag(true));
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system_with_condition(Gravity, enabled)
+        .add_system_with_condition(DoubleWrite, enabled);
+
+    // Skipped systems are not validated either.
+    schedule.run(&mut ecs)?;
+    assert_eq!(ecs.get::<Count>(entity), Some(&Count(10)));
+
+    ecs.insert_resource(Flag(true));
+    assert!(schedule.run(&mut ecs).is_err());
+    assert_eq!(ecs.get::<Count>(entity), Some(&Count(9)));
+    Ok(())
+}
+
+struct OptionalGreeting;
+impl<'a> System<'a> for OptionalGreeting {
+    type Query = (Read<Count>, Option<Read<Name>>);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        let rows: Vec<_> = components.collect();
+        assert_eq!(
+            rows,
+            vec![(&Count(10), Some(&Name("hello"))), (&Count(20), None)]
+        );
+    }
+}
+
+#[test]
+fn test_optional_component_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(20),));
+    ecs.create_entity((Name("world"),));
+    ecs.execute_system::<OptionalGreeting>()
+}
+
+struct GreetedOnly;
+impl<'a> System<'a> for GreetedOnly {
+    type Query = (Write<Count>, With<Name>, Without<Flag>);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for (int,) in components {
+            int.0 += 1;
+        }
+    }
+}
+
+struct AssertGreetedOnly;
+impl<'a> System<'a> for AssertGreetedOnly {
+    type Query = Read<Count>;
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        assert_eq!(
+            components.collect::<Vec<_>>(),
+            vec![&Count(11), &Count(20), &Count(30)]
+        );
+    }
+}
+
+#[test]
+fn test_filtered_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(20),));
+    ecs.create_entity((Count(30), Name("hello"), Flag(true)));
+    ecs.execute_system::<GreetedOnly>()?;
+    ecs.execute_system::<AssertGreetedOnly>()
+}
+
+struct CollectEntities;
+impl<'a> System<'a> for CollectEntities {
+    type Query = (Entity, Read<Count>);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        let rows: Vec<_> = components.map(|(_, int)| int).collect();
+        assert_eq!(rows, vec![&Count(10), &Count(20)]);
+    }
+}
+
+#[test]
+fn test_entity_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Name("hello"),));
+    ecs.create_entity((Count(20),));
+    ecs.execute_system::<CollectEntities>()
+}
+
+#[test]
+fn test_ad_hoc_query() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(20),));
+
+    for int in ecs.query::<Write<Count>>()? {
+        int.0 *= 2;
+    }
+
+    let rows: Vec<_> = ecs.query::<(Read<Count>, Option<Read<Name>>)>()?.collect();
+    assert_eq!(
+        rows,
+        vec![(&Count(20), Some(&Name("hello"))), (&Count(40), None)]
+    );
+
+    let err = ecs.query::<(Write<Count>, Read<Count>)>().err().unwrap();
+    assert_eq!(
+        err,
+        BorrowError::WriteReadConflict {
+            type_name: type_name::<Count>(),
+            kind: BorrowKind::Component,
+        }
+    );
+    assert!(err.to_string().contains("Count"));
+    Ok(())
+}
+
+fn double(components: QueryIter<(Read<Name>, Write<Count>)>) {
+    for (_, int) in components {
+        int.0 *= 2;
+    }
+}
+
+#[test]
+fn test_function_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10), Name("hello")));
+    ecs.run(double)?;
+
+    let mut count = 0;
+    ecs.run(|components: QueryIter<Read<Count>>| {
+        for int in components {
+            assert_eq!(int, &Count(20));
+            count += 1;
+        }
+    })?;
+
+    assert_eq!(count, 1);
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+struct DeltaTime(i32);
+
+#[test]
+fn test_resources() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    assert_eq!(ecs.get_resource::<DeltaTime>(), None);
+
+    ecs.insert_resource(DeltaTime(2));
+    ecs.get_resource_mut::<DeltaTime>().unwrap().0 += 1;
+    assert_eq!(ecs.get_resource::<DeltaTime>(), Some(&DeltaTime(3)));
+
+    ecs.create_entity((Count(10),));
+    ecs.create_entity((Count(20),));
+    ecs.run(|components: QueryIter<(Res<DeltaTime>, Write<Count>)>| {
+        for (delta, int) in components {
+            int.0 *= delta.0;
         }
+    })?;
+
+    let rows: Vec<_> = ecs.query::<Read<Count>>()?.collect();
+    assert_eq!(rows, vec![&Count(30), &Count(60)]);
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+struct Score(i32);
+
+#[test]
+fn test_resource_mut() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.insert_resource(DeltaTime(2));
+    ecs.insert_resource(Score(0));
+    ecs.create_entity((Count(10),));
+    ecs.create_entity((Count(20),));
+
+    let (score, components) =
+        ecs.query::<QuerySet<(ResMut<Score>, (Res<DeltaTime>, Read<Count>))>>()?;
+    let mut score = score.unwrap();
+    for (delta, int) in components {
+        score.0 += delta.0 * int.0;
     }
+    drop(score);
+    assert_eq!(ecs.get_resource::<Score>(), Some(&Score(60)));
+
+    // The resource is handed out once, so the rows can be alive at the same
+    // time.
+    let (score, components) = ecs.query::<QuerySet<(ResMut<Score>, Read<Count>)>>()?;
+    let ints = components.collect::<Vec<_>>();
+    score.unwrap().0 = ints.iter().map(|int| int.0).sum();
+    assert_eq!(ecs.get_resource::<Score>(), Some(&Score(30)));
+
+    ecs.query::<ResMut<DeltaTime>>()?.unwrap().0 = 3;
+    assert_eq!(ecs.get_resource::<DeltaTime>(), Some(&DeltaTime(3)));
+
+    let err = ecs
+        .query::<QuerySet<((Res<Score>,), ResMut<Score>)>>()
+        .err()
+        .unwrap();
+    assert_eq!(
+        err,
+        BorrowError::WriteReadConflict {
+            type_name: type_name::<Score>(),
+            kind: BorrowKind::Resource,
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "resource `{}` is both written and read",
+            type_name::<Score>()
+        )
+    );
+
+    // A resource is kept apart from a component of the same type.
+    ecs.insert_resource(Count(0));
+    ecs.query::<QuerySet<(ResMut<Count>, Write<Count>)>>()?;
+    Ok(())
+}
+
+#[derive(Bundle)]
+struct Counter {
+    count: Count,
+    flag: Flag,
+}
+
+#[derive(Bundle)]
+struct Named(Name, Count);
+
+#[test]
+fn test_bundles() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity(Counter {
+        count: Count(10),
+        flag: Flag(true),
+    });
+    ecs.create_entity(Named(Name("hello"), Count(20)));
+    ecs.spawn_batch((0..2).map(|_| Named(Name("world"), Count(30))));
+
+    assert_eq!(ecs.get::<Flag>(a), Some(&Flag(true)));
+
+    let rows: Vec<_> = ecs.query::<(Read<Count>, Option<Read<Name>>)>()?.collect();
+    assert_eq!(
+        rows,
+        vec![
+            (&Count(10), None),
+            (&Count(20), Some(&Name("hello"))),
+            (&Count(30), Some(&Name("world"))),
+            (&Count(30), Some(&Name("world"))),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_spawn_batch() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Name("first"),));
+
+    let entities = ecs.spawn_batch((1..=3).map(|i| (Count(i), Flag(i % 2 == 0))));
+    assert_eq!(entities.len(), 3);
+    assert_eq!(ecs.get::<Count>(entities[1]), Some(&Count(2)));
+
+    let last = ecs.create_entity((Count(4), Name("last")));
+    assert!(entities
+        .iter()
+        .all(|&entity| ecs.is_alive(entity) && entity != last));
+
+    let rows: Vec<_> = ecs.query::<(Read<Count>, Option<Read<Flag>>)>()?.collect();
+    assert_eq!(
+        rows,
+        vec![
+            (&Count(1), Some(&Flag(false))),
+            (&Count(2), Some(&Flag(true))),
+            (&Count(3), Some(&Flag(false))),
+            (&Count(4), None),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_despawn() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(20), Name("world")));
+
+    assert!(ecs.despawn(a));
+    assert!(!ecs.despawn(a));
+    assert!(!ecs.is_alive(a));
+
+    let rows: Vec<_> = ecs.query::<(Read<Count>, Read<Name>)>()?.collect();
+    assert_eq!(rows, vec![(&Count(20), &Name("world"))]);
+    Ok(())
+}
+
+#[test]
+fn test_has_component() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10), Name("hello")));
+    let b = ecs.create_entity((Count(20),));
+
+    assert!(ecs.has_component::<Name>(a));
+    assert!(!ecs.has_component::<Name>(b));
+    assert!(!ecs.has_component::<Flag>(a));
+
+    ecs.add_component(b, Count(30));
+    ecs.remove_component::<Count>(b);
+    assert!(ecs.has_component::<Count>(b));
+
+    ecs.despawn(a);
+    assert!(!ecs.has_component::<Count>(a));
+}
+
+#[test]
+fn test_despawn_take() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10), Name("hello"), Count(20)));
+    ecs.create_entity((Count(30),));
+
+    let mut components = ecs.despawn_take(a).unwrap();
+    assert!(!ecs.is_alive(a));
+    assert!(ecs.despawn_take(a).is_none());
+
+    assert_eq!(components.len(), 3);
+    assert_eq!(components.get::<Name>(), Some(&Name("hello")));
+    assert_eq!(components.get::<Flag>(), None);
+    assert_eq!(components.take::<Count>(), Some(Count(10)));
+    assert_eq!(components.take::<Count>(), Some(Count(20)));
+    assert_eq!(components.take::<Count>(), None);
+    assert_eq!(components.len(), 1);
+
+    let rows: Vec<_> = ecs.query::<Read<Count>>()?.collect();
+    assert_eq!(rows, vec![&Count(30)]);
+    Ok(())
+}
+
+#[test]
+fn test_entity_count() {
+    let mut ecs = Things::new();
+    assert!(ecs.is_empty());
+
+    let a = ecs.create_entity((Count(10),));
+    ecs.create_entity((Count(20), Name("world")));
+    assert_eq!(ecs.entity_count(), 2);
+    assert!(!ecs.is_empty());
+
+    ecs.despawn(a);
+    ecs.despawn(a);
+    assert_eq!(ecs.entity_count(), 1);
+}
+
+#[test]
+fn test_entities() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10),));
+    let b = ecs.create_entity((Name("hello"),));
+    let c = ecs.create_entity((Count(30), Name("world")));
+    ecs.despawn(b);
+
+    let entities: Vec<_> = ecs.entities().collect();
+    assert_eq!(entities, vec![a, c]);
+
+    for entity in entities {
+        ecs.despawn(entity);
+    }
+    assert_eq!(ecs.entities().count(), 0);
+}
+
+#[test]
+fn test_single() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    assert_eq!(
+        ecs.query::<Read<Count>>()?.single(),
+        Err(SingleError::NotFound)
+    );
+
+    ecs.create_entity((Count(10), Flag(true)));
+    ecs.create_entity((Count(20),));
+
+    let (count,) = ecs.query::<(Write<Count>, With<Flag>)>()?.single().unwrap();
+    count.0 += 1;
+
+    assert_eq!(
+        ecs.query::<(Read<Count>, With<Flag>)>()?.single(),
+        Ok((&Count(11),))
+    );
+    assert_eq!(
+        ecs.query::<Read<Count>>()?.single(),
+        Err(SingleError::Multiple)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_clear() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(20),));
+
+    ecs.clear();
+    assert!(ecs.is_empty());
+    assert!(!ecs.is_alive(a));
+    assert_eq!(ecs.query::<Read<Count>>()?.count(), 0);
+
+    let b = ecs.create_entity((Count(30),));
+    assert_ne!(a, b);
+
+    let rows: Vec<_> = ecs.query::<(Read<Count>, Option<Read<Name>>)>()?.collect();
+    assert_eq!(rows, vec![(&Count(30), None)]);
+    Ok(())
+}
+
+fn replace_large(components: QueryIter<(Entity, Read<Count>, Commands)>) {
+    for (entity, int, commands) in components {
+        if int.0 > 15 {
+            commands.despawn(entity);
+            commands.spawn((Count(int.0 / 10),));
+        }
+    }
+}
+
+#[test]
+fn test_commands() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(10),));
+    let b = ecs.create_entity((Count(20),));
+    ecs.create_entity((Count(30),));
+    ecs.run(replace_large)?;
+
+    assert!(!ecs.is_alive(b));
+
+    let rows: Vec<_> = ecs.query::<Read<Count>>()?.collect();
+    assert_eq!(rows, vec![&Count(10), &Count(2), &Count(3)]);
+    Ok(())
+}
+
+#[test]
+fn test_add_component() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1),));
+    let b = ecs.create_entity((Count(2), Name("world")));
+
+    assert!(ecs.add_component(a, Name("hello")));
+    let rows: Vec<_> = ecs.query::<(Entity, Read<Count>, Read<Name>)>()?.collect();
+    assert_eq!(
+        rows,
+        vec![
+            (a, &Count(1), &Name("hello")),
+            (b, &Count(2), &Name("world"))
+        ]
+    );
+
+    // A second component of the same type moves the entity to new rows.
+    assert!(ecs.add_component(a, Name("again")));
+    let rows: Vec<_> = ecs.query::<(Entity, Read<Name>)>()?.collect();
+    assert_eq!(
+        rows,
+        vec![
+            (b, &Name("world")),
+            (a, &Name("hello")),
+            (a, &Name("again"))
+        ]
+    );
+
+    let rows: Vec<_> = ecs.query::<(Entity, Read<Count>)>()?.collect();
+    assert_eq!(rows, vec![(b, &Count(2)), (a, &Count(1))]);
+
+    assert!(ecs.add_component(b, Flag(true)));
+    assert!(ecs.despawn(a));
+    assert!(!ecs.add_component(a, Flag(true)));
+
+    let rows: Vec<_> = ecs.query::<(Entity, Read<Flag>)>()?.collect();
+    assert_eq!(rows, vec![(b, &Flag(true))]);
+    Ok(())
+}
+
+#[test]
+fn test_replace_component() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1),));
+    let b = ecs.create_entity((Count(2),));
+
+    assert_eq!(ecs.replace_component(a, Count(10)), Some(Count(1)));
+    assert_eq!(ecs.replace_component(a, Name("a")), None);
+    assert_eq!(ecs.replace_component(a, Name("again")), Some(Name("a")));
+
+    let rows: Vec<_> = ecs.query::<(Entity, Read<Count>)>()?.collect();
+    assert_eq!(rows, vec![(a, &Count(10)), (b, &Count(2))]);
+    assert_eq!(
+        ecs.query::<Read<Name>>()?.collect::<Vec<_>>(),
+        vec![&Name("again")]
+    );
+
+    assert!(ecs.despawn(b));
+    assert_eq!(ecs.replace_component(b, Count(20)), None);
+    assert!(!ecs.has_component::<Count>(b));
+    Ok(())
+}
+
+#[test]
+fn test_query_entities() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1), Name("a")));
+    let b = ecs.create_entity((Count(2),));
+    ecs.create_entity((Name("c"),));
+
+    let rows: Vec<_> = ecs.query_entities::<Read<Count>>()?.collect();
+    assert_eq!(rows, vec![(a, &Count(1)), (b, &Count(2))]);
+
+    for (entity, (count,)) in ecs.query_entities::<(Write<Count>, With<Name>)>()? {
+        assert_eq!(entity, a);
+        count.0 += 10;
+    }
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(11)));
+
+    let err = ecs.query_entities::<(Read<Count>, Write<Count>)>().err();
+    assert_eq!(
+        err,
+        Some(BorrowError::WriteReadConflict {
+            type_name: type_name::<Count>(),
+            kind: BorrowKind::Component,
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_count() {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(1), Name("a")));
+    ecs.create_entity((Count(2), Count(3)));
+    let c = ecs.create_entity((Name("c"), Flag(true)));
+
+    assert_eq!(ecs.count::<Read<Count>>(), 3);
+    assert_eq!(ecs.count::<(With<Name>, Without<Flag>)>(), 1);
+    assert_eq!(ecs.count::<(Entity, Read<Name>, Option<Read<Count>>)>(), 2);
+    assert_eq!(ecs.count::<Read<Selected>>(), 0);
+
+    ecs.despawn(c);
+    assert_eq!(ecs.count::<With<Name>>(), 1);
+}
+
+#[test]
+fn test_mark_despawn() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(0),));
+    let b = ecs.create_entity((Count(1),));
+
+    for entity in ecs.entities() {
+        if ecs.get::<Count>(entity) == Some(&Count(0)) {
+            ecs.mark_despawn(entity);
+            ecs.mark_despawn(entity);
+        }
+    }
+    assert!(ecs.is_alive(a));
+
+    ecs.apply_despawns();
+    assert!(!ecs.is_alive(a));
+    assert!(ecs.is_alive(b));
+
+    // Marks are also applied after every system.
+    ecs.mark_despawn(b);
+    ecs.execute_system::<Gravity>()?;
+    assert!(ecs.is_empty());
+    Ok(())
+}
+
+struct DespawnEmpty;
+impl<'a> System<'a> for DespawnEmpty {
+    type Query = (Entity, Read<Count>, Despawns);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for (entity, int, despawns) in components {
+            if int.0 == 0 {
+                despawns.mark(entity);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_despawn_from_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(0),));
+    let b = ecs.create_entity((Count(1),));
+    let c = ecs.create_entity((Count(0), Name("c")));
+
+    ecs.execute_system::<DespawnEmpty>()?;
+    assert!(!ecs.is_alive(a));
+    assert!(ecs.is_alive(b));
+    assert!(!ecs.is_alive(c));
+
+    ecs.run(|components: QueryIter<(Entity, Despawns)>| {
+        for (entity, despawns) in components {
+            despawns.mark(entity);
+        }
+    })?;
+    assert!(ecs.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_iter_positions() {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(0),));
+    ecs.create_entity((Name("wall"),));
+    ecs.create_entity((Count(2), Name("door")));
+
+    let positions: Vec<_> = ecs.iter_positions::<Count>().collect();
+    assert_eq!(
+        positions,
+        vec![(0, Some(&Count(0))), (1, None), (2, Some(&Count(2)))]
+    );
+    assert_eq!(ecs.iter_positions::<Flag>().count(), 0);
+}
+
+#[test]
+fn test_insert_command() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(1),));
+    ecs.create_entity((Count(2),));
+    ecs.run(|components: QueryIter<(Entity, Read<Count>, Commands)>| {
+        for (entity, int, commands) in components {
+            commands.insert(entity, Flag(int.0 > 1));
+        }
+    })?;
+
+    let rows: Vec<_> = ecs.query::<(Read<Count>, Read<Flag>)>()?.collect();
+    assert_eq!(
+        rows,
+        vec![(&Count(1), &Flag(false)), (&Count(2), &Flag(true))]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_remove_component() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1), Name("hello"), Name("again")));
+    let b = ecs.create_entity((Count(2), Name("world")));
+
+    assert_eq!(ecs.remove_component::<Name>(a), Some(Name("again")));
+    assert_eq!(ecs.remove_component::<Name>(a), Some(Name("hello")));
+    assert_eq!(ecs.remove_component::<Name>(a), None);
+    assert_eq!(ecs.remove_component::<Flag>(a), None);
+
+    let rows: Vec<_> = ecs
+        .query::<(Entity, Read<Count>, Option<Read<Name>>)>()?
+        .collect();
+    assert_eq!(
+        rows,
+        vec![(a, &Count(1), None), (b, &Count(2), Some(&Name("world")))]
+    );
+
+    ecs.run(|components: QueryIter<(Entity, With<Name>, Commands)>| {
+        for (entity, commands) in components {
+            commands.remove::<Count>(entity);
+        }
+    })?;
+
+    let rows: Vec<_> = ecs.query::<Read<Count>>()?.collect();
+    assert_eq!(rows, vec![&Count(1)]);
+    Ok(())
+}
+
+#[test]
+fn test_get_component() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1), Name("hello")));
+    let b = ecs.create_entity((Count(2),));
+
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(1)));
+    assert_eq!(ecs.get::<Name>(a), Some(&Name("hello")));
+    assert_eq!(ecs.get::<Name>(b), None);
+    assert_eq!(ecs.get::<Flag>(b), None);
+
+    ecs.get_mut::<Count>(b).unwrap().0 += 40;
+    assert_eq!(ecs.get::<Count>(b), Some(&Count(42)));
+
+    ecs.despawn(a);
+    assert_eq!(ecs.get::<Count>(a), None);
+    assert_eq!(ecs.get_mut::<Count>(a), None);
+}
+
+#[derive(Component, Debug, PartialEq)]
+#[component(storage = "SparseStore")]
+struct Selected;
+
+#[test]
+fn test_sparse_component() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1),));
+    let b = ecs.create_entity((Count(2), Selected));
+    let c = ecs.create_entity((Count(3),));
+
+    assert!(ecs.add_component(c, Selected));
+    for (int,) in ecs.query::<(Write<Count>, With<Selected>)>()? {
+        int.0 *= 10;
+    }
+
+    let rows: Vec<_> = ecs
+        .query::<(Entity, Read<Count>, Option<Read<Selected>>)>()?
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (a, &Count(1), None),
+            (b, &Count(20), Some(&Selected)),
+            (c, &Count(30), Some(&Selected)),
+        ]
+    );
+
+    assert_eq!(ecs.remove_component::<Selected>(b), Some(Selected));
+    assert_eq!(ecs.get::<Selected>(b), None);
+    assert_eq!(ecs.get::<Selected>(c), Some(&Selected));
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Collision(Entity);
+
+struct Damage;
+impl<'a> System<'a> for Damage {
+    type Query = (Entity, Write<Count>, EventReader<Collision>);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for (entity, int, collisions) in components {
+            int.0 -= 5 * collisions.iter().filter(|c| c.0 == entity).count() as i32;
+        }
+    }
+}
+
+#[test]
+fn test_events() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10),));
+    let b = ecs.create_entity((Count(20),));
+
+    // Events only become readable once the frame has ended.
+    ecs.send_event(Collision(b));
+    ecs.execute_system::<Damage>()?;
+    assert_eq!(ecs.get::<Count>(b), Some(&Count(20)));
+
+    ecs.update_events();
+    ecs.execute_system::<Damage>()?;
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(10)));
+    assert_eq!(ecs.get::<Count>(b), Some(&Count(15)));
+
+    // Events of the previous frame are dropped at the end of the next frame.
+    ecs.update_events();
+    ecs.execute_system::<Damage>()?;
+    assert_eq!(ecs.get::<Count>(b), Some(&Count(15)));
+
+    ecs.run(|components: QueryIter<(Entity, Read<Count>, Commands)>| {
+        for (entity, int, commands) in components {
+            if int.0 < 12 {
+                commands.send(Collision(entity));
+            }
+        }
+    })?;
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(Damage);
+    schedule.run(&mut ecs)?;
+
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(5)));
+    assert_eq!(ecs.get::<Count>(b), Some(&Count(15)));
+    Ok(())
+}
+
+#[test]
+fn test_events_read_since_last_run() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(20),));
+
+    // A system running twice in a frame reads the events once.
+    ecs.send_event(Collision(a));
+    ecs.update_events();
+    ecs.execute_system::<Damage>()?;
+    ecs.execute_system::<Damage>()?;
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(15)));
+
+    // A system skipping a frame reads the events of both frames it missed.
+    ecs.send_event(Collision(a));
+    ecs.update_events();
+    ecs.send_event(Collision(a));
+    ecs.update_events();
+    ecs.execute_system::<Damage>()?;
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(5)));
+
+    // Events are dropped once two more frames have ended.
+    ecs.send_event(Collision(a));
+    for _ in 0..3 {
+        ecs.update_events();
+    }
+    ecs.execute_system::<Damage>()?;
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(5)));
+    Ok(())
+}
+
+struct FlagChanged;
+impl<'a> System<'a> for FlagChanged {
+    type Query = (Write<Flag>, Changed<Count>);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for (flag,) in components {
+            flag.0 = true;
+        }
+    }
+}
+
+struct FlagAdded;
+impl<'a> System<'a> for FlagAdded {
+    type Query = (Write<Flag>, Added<Count>);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for (flag,) in components {
+            flag.0 = true;
+        }
+    }
+}
+
+fn take_flags(ecs: &mut Things) -> Vec<bool> {
+    ecs.query::<Write<Flag>>()
+        .unwrap()
+        .map(|flag| std::mem::replace(&mut flag.0, false))
+        .collect()
+}
+
+#[test]
+fn test_changed_filter() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(1), Flag(false)));
+    let b = ecs.create_entity((Count(2), Flag(false)));
+
+    // A system that never ran before sees every component as changed.
+    ecs.execute_system::<FlagChanged>()?;
+    assert_eq!(take_flags(&mut ecs), vec![true, true]);
+
+    ecs.execute_system::<FlagChanged>()?;
+    assert_eq!(take_flags(&mut ecs), vec![false, false]);
+
+    ecs.get_mut::<Count>(b).unwrap().0 += 1;
+    ecs.execute_system::<FlagChanged>()?;
+    assert_eq!(take_flags(&mut ecs), vec![false, true]);
+
+    // Reading a component does not mark it as changed.
+    assert_eq!(ecs.query::<Read<Count>>()?.count(), 2);
+    ecs.execute_system::<FlagChanged>()?;
+    assert_eq!(take_flags(&mut ecs), vec![false, false]);
+
+    ecs.execute_system::<Gravity>()?;
+    ecs.execute_system::<FlagChanged>()?;
+    assert_eq!(take_flags(&mut ecs), vec![true, true]);
+    Ok(())
+}
+
+#[test]
+fn test_added_filter() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1), Flag(false)));
+    ecs.create_entity((Flag(false),));
+
+    ecs.execute_systems(&[&FlagAdded])?;
+    assert_eq!(take_flags(&mut ecs), vec![true, false]);
+
+    ecs.execute_system::<Gravity>()?;
+    ecs.create_entity((Count(3), Flag(false)));
+    ecs.execute_systems(&[&FlagAdded])?;
+    assert_eq!(take_flags(&mut ecs), vec![false, false, true]);
+
+    // Moving the components of an entity to make room for another component
+    // does not mark them as added.
+    ecs.add_component(a, Count(4));
+    ecs.execute_systems(&[&FlagAdded])?;
+    let rows: Vec<_> = ecs.query::<(Read<Count>, Read<Flag>)>()?.collect();
+    assert_eq!(
+        rows,
+        vec![(&Count(3), &Flag(false)), (&Count(0), &Flag(false))]
+    );
+    assert_eq!(take_flags(&mut ecs), vec![false, false, false]);
+    Ok(())
+}
+
+#[test]
+fn test_debug() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10),));
+    let b = ecs.create_entity((Name("hello"),));
+    let c = ecs.create_entity((Count(20),));
+    ecs.despawn(b);
+
+    assert_eq!(
+        format!("{:?}", ecs),
+        format!(
+            "Things {{ entities: {{{:?}: [Count(10)], {:?}: [Count(20)]}} }}",
+            a, c
+        )
+    );
+}
+
+#[test]
+fn test_get_all() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10), Name("hello"), Count(20)));
+    let b = ecs.create_entity((Count(30),));
+
+    let counts = ecs.get_all::<Count>(a).map(|c| c.0).collect::<Vec<_>>();
+    assert_eq!(counts, vec![10, 20]);
+    assert_eq!(ecs.get_all::<Name>(b).count(), 0);
+    assert_eq!(ecs.get_all::<Flag>(a).count(), 0);
+
+    for count in ecs.get_all_mut::<Count>(a) {
+        count.0 += 1;
+    }
+    assert_eq!(ecs.get_all::<Count>(a).map(|c| c.0).sum::<i32>(), 32);
+
+    // Only the first row of `a` holds both a `Count` and a `Name`.
+    let rows = ecs.query::<(Read<Count>, Read<Name>)>()?.count();
+    assert_eq!(rows, 1);
+
+    ecs.despawn(a);
+    assert_eq!(ecs.get_all::<Count>(a).count(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_store() {
+    let mut ecs = Things::new();
+    assert!(ecs.store::<Count>().is_none());
+
+    let a = ecs.create_entity((Count(10),));
+    ecs.create_entity((Name("hello"),));
+    let c = ecs.create_entity((Count(30),));
+
+    let store = ecs.store::<Count>().unwrap();
+    let counts = store
+        .as_slice()
+        .iter()
+        .flatten()
+        .map(|c| c.0)
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![10, 30]);
+
+    let store = ecs.store_mut::<Count>().unwrap();
+    store.remove(2);
+    assert!(ecs.has_component::<Count>(a));
+    assert!(!ecs.has_component::<Count>(c));
+}
+
+#[derive(Default)]
+struct Timer {
+    frames: i32,
+}
+
+impl<'a> StatefulSystem<'a> for Timer {
+    type Query = Write<Count>;
+
+    fn update(&mut self, components: <Self::Query as Query<'a>>::Iter) {
+        self.frames += 1;
+
+        for int in components {
+            int.0 = self.frames;
+        }
+    }
+}
+
+#[test]
+fn test_stateful_system() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(0),));
+
+    let mut timer = Timer::default();
+    ecs.execute_stateful_system(&mut timer)?;
+    ecs.execute_stateful_system(&mut timer)?;
+    assert_eq!(timer.frames, 2);
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(2)));
+
+    let mut schedule = Schedule::new();
+    schedule.add_stateful_system(timer).add_system(Gravity);
+    schedule.run(&mut ecs)?;
+    schedule.run(&mut ecs)?;
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(3)));
+    Ok(())
+}
+
+#[test]
+fn test_component_stats() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10), Name("hello")));
+    ecs.create_entity((Count(20),));
+    ecs.create_entity((Count(30), Count(40)));
+    ecs.despawn(a);
+
+    assert_eq!(
+        ecs.component_stats(),
+        vec![(type_name::<Count>(), 3, 4), (type_name::<Name>(), 0, 4),]
+    );
+}
+
+#[test]
+fn test_get_many() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(10),));
+    let b = ecs.create_entity((Name("hello"),));
+    let c = ecs.create_entity((Name("world"), Count(30)));
+    let d = ecs.create_entity((Count(40),));
+    ecs.despawn(d);
+
+    let counts = ecs.get_many::<Count>(&[c, b, a, d]);
+    assert_eq!(counts, vec![Some(&Count(30)), None, Some(&Count(10)), None]);
+    assert_eq!(ecs.get_many::<Flag>(&[a]), vec![None]);
+}
+
+#[test]
+fn test_merge() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1), Name("a")));
+
+    let mut chunk = Things::new();
+    let b = chunk.create_entity((Count(2), Count(3)));
+    let c = chunk.create_entity((Name("c"), Selected));
+    let d = chunk.create_entity((Flag(true),));
+    chunk.despawn(d);
+
+    let merged = ecs.merge(chunk);
+    assert_eq!(merged.len(), 2);
+    assert_eq!(ecs.entity_count(), 3);
+
+    let new = |old| merged.iter().find(|&&(_, entity)| entity == old).unwrap().0;
+    let counts = ecs.get_all::<Count>(new(b)).collect::<Vec<_>>();
+    assert_eq!(counts, vec![&Count(2), &Count(3)]);
+    assert_eq!(ecs.get::<Name>(new(c)), Some(&Name("c")));
+    assert!(ecs.has_component::<Selected>(new(c)));
+    assert_eq!(ecs.get::<Name>(a), Some(&Name("a")));
+
+    let names = ecs.query::<Read<Name>>().unwrap().collect::<Vec<_>>();
+    assert_eq!(names, vec![&Name("a"), &Name("c")]);
+
+    // The rows of the despawned entity are merged as well, and can be reused.
+    let e = ecs.create_entity((Flag(false),));
+    assert_eq!(ecs.get::<Flag>(e), Some(&Flag(false)));
+    assert_eq!(ecs.component_stats()[0].2, 5);
+}
+
+struct FlagCount;
+impl<'a> System<'a> for FlagCount {
+    type Query = QuerySet<((Read<Count>, With<Name>), Write<Flag>)>;
+
+    fn update((sources, targets): <Self::Query as Query<'a>>::Iter) {
+        let total: i32 = sources.map(|(count,)| count.0).sum();
+        for flag in targets {
+            flag.0 = total > 2;
+        }
+    }
+}
+
+#[test]
+fn test_query_set() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(1), Name("source")));
+    ecs.create_entity((Count(2), Name("source")));
+    ecs.create_entity((Count(10),));
+    let target = ecs.create_entity((Flag(false),));
+
+    ecs.execute_system::<FlagCount>()?;
+    assert_eq!(ecs.get::<Flag>(target), Some(&Flag(true)));
+
+    // Filters don't make the queries disjoint.
+    let err = ecs
+        .query::<QuerySet<((Read<Count>, With<Name>), (Write<Count>, Without<Name>))>>()
+        .err();
+    assert_eq!(
+        err,
+        Some(BorrowError::WriteReadConflict {
+            type_name: type_name::<Count>(),
+            kind: BorrowKind::Component,
+        })
+    );
+
+    let err = ecs
+        .query::<QuerySet<(Read<Name>, Write<Flag>, Write<Flag>)>>()
+        .err();
+    assert_eq!(
+        err,
+        Some(BorrowError::ConflictingWrite {
+            type_name: type_name::<Flag>(),
+            kind: BorrowKind::Component,
+        })
+    );
+
+    let err = ecs
+        .query::<QuerySet<((Read<Name>, Write<Name>), Read<Flag>)>>()
+        .err();
+    assert_eq!(
+        err,
+        Some(BorrowError::WriteReadConflict {
+            type_name: type_name::<Name>(),
+            kind: BorrowKind::Component,
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_get_mut2() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1), Name("hello")));
+    let b = ecs.create_entity((Count(2),));
+
+    let (count, name) = ecs.get_mut2::<Count, Name>(a).unwrap();
+    count.0 += 1;
+    name.0 = "world";
+
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(2)));
+    assert_eq!(ecs.get::<Name>(a), Some(&Name("world")));
+    assert!(ecs.get_mut2::<Count, Name>(b).is_none());
+    assert!(ecs.get_mut2::<Count, Flag>(a).is_none());
+}
+
+struct StampTick;
+impl<'a> System<'a> for StampTick {
+    type Query = (Tick, Write<Count>);
+
+    fn update(components: <Self::Query as Query<'a>>::Iter) {
+        for (tick, int) in components {
+            int.0 = tick as i32;
+        }
+    }
+}
+
+#[test]
+fn test_tick() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(0),));
+    assert_eq!(ecs.tick(), 1);
+
+    ecs.execute_system::<StampTick>()?;
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(1)));
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(StampTick).add_system(StampTick);
+    schedule.run(&mut ecs)?;
+
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(3)));
+    assert_eq!(ecs.tick(), 4);
+    Ok(())
+}
+
+#[test]
+fn test_query_order() {
+    fn simulate() -> Vec<i32> {
+        let mut ecs = Things::new();
+        let entities = (0..5)
+            .map(|i| ecs.create_entity((Count(i),)))
+            .collect::<Vec<_>>();
+
+        ecs.despawn(entities[1]);
+        ecs.despawn(entities[3]);
+        ecs.create_entity((Count(5), Name("reused")));
+        ecs.add_component(entities[0], Count(6));
+        ecs.create_entity((Count(7),));
+
+        ecs.query::<Read<Count>>()
+            .unwrap()
+            .map(|count| count.0)
+            .collect()
+    }
+
+    // Rows are visited by position, where vacated rows are reused, and an
+    // entity holding a second component of a type is moved to the end.
+    assert_eq!(simulate(), vec![7, 2, 5, 4, 0, 6]);
+    assert_eq!(simulate(), simulate());
+}
+
+#[test]
+#[should_panic(expected = "borrowed mutably twice")]
+fn test_get_mut2_same_type() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1), Count(2)));
+    ecs.get_mut2::<Count, Count>(a);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_borrow_error_source() {
+    use std::error::Error;
+
+    let mut ecs = Things::new();
+    let err = ecs.execute_system::<DoubleWrite>().unwrap_err();
+    let err: Box<dyn Error + Send + Sync> = err.into();
+
+    let expected = format!(
+        "component `{}` is written more than once",
+        type_name::<Count>()
+    );
+    assert_eq!(err.to_string(), expected);
+    assert!(err.source().is_none());
+}
+
+#[test]
+fn test_hooks() {
+    use std::sync::{Arc, Mutex};
+
+    let added = Arc::new(Mutex::new(Vec::new()));
+    let removed = Arc::new(Mutex::new(Vec::new()));
+
+    let mut ecs = Things::new();
+    let log = Arc::clone(&added);
+    ecs.set_on_add(move |entity, int: &Count| log.lock().unwrap().push((entity, int.0)));
+    let log = Arc::clone(&removed);
+    ecs.set_on_remove(move |entity, int: &Count| log.lock().unwrap().push((entity, int.0)));
+
+    let a = ecs.create_entity((Count(10), Name("hello")));
+    let b = ecs.spawn_batch(vec![(Count(20),)])[0];
+    ecs.add_component(a, Count(11));
+    ecs.add_component(a, Name("world"));
+    assert_eq!(*added.lock().unwrap(), vec![(a, 10), (b, 20), (a, 11)]);
+
+    ecs.remove_component::<Count>(a);
+    ecs.remove_component::<Name>(a);
+    ecs.despawn(a);
+    ecs.despawn(a);
+    ecs.clear();
+    assert_eq!(*removed.lock().unwrap(), vec![(a, 11), (a, 10), (b, 20)]);
+}
+
+#[test]
+fn test_entity_views() {
+    let mut ecs = Things::new();
+    let entity = ecs.create_entity((Count(1),));
+
+    let view = ecs.entity(entity).unwrap();
+    assert_eq!(view.id(), entity);
+    assert_eq!(view.get::<Count>().map(|c| c.0), Some(1));
+    assert!(!view.has::<Name>());
+
+    let mut view = ecs.entity_mut(entity).unwrap();
+    view.insert(Name("hello")).get_mut::<Count>().unwrap().0 += 1;
+    assert_eq!(view.get::<Count>().map(|c| c.0), Some(2));
+    assert_eq!(view.remove::<Name>().map(|n| n.0), Some("hello"));
+    assert!(view.remove::<Name>().is_none());
+    view.despawn();
+
+    assert!(ecs.entity(entity).is_none());
+    assert!(ecs.entity_mut(entity).is_none());
+}
+
+#[test]
+fn test_schedule_validate() {
+    let mut schedule = Schedule::new();
+    schedule.add_system(AssertValues).add_system(ToggleFlag);
+    assert_eq!(schedule.validate(), Ok(()));
+
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(AssertValues)
+        .add_system(IncrementCounter)
+        .add_system(DoubleWrite)
+        .add_system(ToggleFlag);
+
+    let conflict = |a, b, error| BorrowConflict {
+        systems: (a, b),
+        error,
+    };
+    let (count, kind) = (type_name::<Count>(), BorrowKind::Component);
+    assert_eq!(
+        schedule.validate(),
+        Err(vec![
+            conflict(
+                type_name::<DoubleWrite>(),
+                type_name::<DoubleWrite>(),
+                BorrowError::ConflictingWrite {
+                    type_name: count,
+                    kind
+                },
+            ),
+            conflict(
+                type_name::<AssertValues>(),
+                type_name::<IncrementCounter>(),
+                BorrowError::WriteReadConflict {
+                    type_name: count,
+                    kind
+                },
+            ),
+            conflict(
+                type_name::<IncrementCounter>(),
+                type_name::<ToggleFlag>(),
+                BorrowError::WriteReadConflict {
+                    type_name: count,
+                    kind
+                },
+            ),
+        ])
+    );
+}
+
+#[test]
+fn test_for_each_pair_mut() {
+    let mut ecs = Things::new();
+    let mut pairs = Vec::new();
+    ecs.for_each_pair_mut(|a: &mut Count, b: &mut Count| pairs.push((a.0, b.0)));
+    assert!(pairs.is_empty());
+
+    let a = ecs.create_entity((Count(1),));
+    ecs.create_entity((Name("no count"),));
+    let b = ecs.create_entity((Count(2),));
+    let c = ecs.create_entity((Count(3),));
+
+    ecs.for_each_pair_mut(|a: &mut Count, b: &mut Count| {
+        pairs.push((a.0, b.0));
+        a.0 += 10;
+        b.0 += 100;
+    });
+
+    assert_eq!(pairs, vec![(1, 2), (11, 3), (102, 103)]);
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(21)));
+    assert_eq!(ecs.get::<Count>(b), Some(&Count(112)));
+    assert_eq!(ecs.get::<Count>(c), Some(&Count(203)));
+}
+
+#[test]
+fn test_for_each() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1), Name("a")));
+    let b = ecs.create_entity((Count(2),));
+
+    ecs.for_each::<(Write<Count>, Without<Name>), _>(|(count,)| count.0 *= 10)?;
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(1)));
+    assert_eq!(ecs.get::<Count>(b), Some(&Count(20)));
+
+    let mut names = Vec::new();
+    ecs.for_each::<Read<Name>, _>(|name| names.push(name.0))?;
+    assert_eq!(names, vec!["a"]);
+
+    let err = ecs.for_each::<(Read<Count>, Write<Count>), _>(|_| unreachable!());
+    assert_eq!(
+        err,
+        Err(BorrowError::WriteReadConflict {
+            type_name: type_name::<Count>(),
+            kind: BorrowKind::Component,
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_query_chunks() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.spawn_batch((0..5).map(|i| (Count(i),)));
+    ecs.create_entity((Name("gap"),));
+    ecs.spawn_batch((5..10).map(|i| (Count(i),)));
+
+    let mut chunks = ecs.query::<Read<Count>>()?.array_chunks::<4>();
+    let counts = chunks
+        .by_ref()
+        .map(|chunk| chunk.map(|count| count.0))
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![[0, 1, 2, 3], [4, 5, 6, 7]]);
+    assert_eq!(chunks.into_remainder(), vec![&Count(8), &Count(9)]);
+
+    // Rows not yet yielded in a chunk are part of the remainder.
+    let mut chunks = ecs.query::<Read<Count>>()?.array_chunks::<3>();
+    assert_eq!(chunks.next(), Some([&Count(0), &Count(1), &Count(2)]));
+    assert_eq!(chunks.into_remainder().len(), 7);
+
+    let mut chunks = ecs.query::<Write<Count>>()?.array_chunks::<3>();
+    for count in (&mut chunks).flatten() {
+        count.0 *= 2;
+    }
+    chunks.into_remainder()[0].0 *= 2;
+    assert_eq!(
+        ecs.query::<Read<Count>>()?
+            .map(|count| count.0)
+            .sum::<i32>(),
+        90
+    );
+    Ok(())
+}
+
+#[test]
+fn test_query_skip_take_rows() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.spawn_batch((0..5).map(|i| (Count(i),)));
+    ecs.create_entity((Name("gap"),));
+    ecs.spawn_batch((5..10).map(|i| (Count(i),)));
+
+    let counts = |query: QueryIter<Read<Count>>| query.map(|count| count.0).collect::<Vec<_>>();
+
+    assert_eq!(ecs.query::<Read<Count>>()?.rows(), 11);
+    assert_eq!(
+        counts(ecs.query::<Read<Count>>()?.skip_rows(3)),
+        vec![3, 4, 5, 6, 7, 8, 9]
+    );
+    assert_eq!(
+        counts(ecs.query::<Read<Count>>()?.take_rows(6)),
+        vec![0, 1, 2, 3, 4]
+    );
+    assert_eq!(
+        counts(ecs.query::<Read<Count>>()?.skip_rows(4).take_rows(3)),
+        vec![4, 5]
+    );
+    assert_eq!(counts(ecs.query::<Read<Count>>()?.skip_rows(20)), vec![]);
+
+    for count in ecs.query::<Write<Count>>()?.skip_rows(6) {
+        count.0 *= 10;
+    }
+    assert_eq!(
+        counts(ecs.query::<Read<Count>>()?.skip_rows(8)),
+        vec![70, 80, 90]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_or_filter() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.create_entity((Count(1), Name("a")));
+    ecs.create_entity((Count(2), Selected));
+    ecs.create_entity((Count(3), Name("c"), Selected));
+    ecs.create_entity((Count(4), Flag(true)));
+    ecs.create_entity((Name("e"), Selected));
+
+    let counts = ecs
+        .query::<(Read<Count>, Or<(With<Name>, With<Selected>)>)>()?
+        .map(|(count,)| count.0)
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![1, 2, 3]);
+
+    for (count,) in ecs.query::<(Write<Count>, Or<(Without<Name>, With<Flag>)>)>()? {
+        count.0 *= 10;
+    }
+    let counts = ecs
+        .query::<Read<Count>>()?
+        .map(|count| count.0)
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![1, 20, 3, 40]);
+
+    assert_eq!(
+        ecs.count::<(Read<Name>, Or<(With<Count>, With<Flag>)>)>(),
+        2
+    );
+
+    let err = ecs
+        .query::<(Write<Name>, Or<(With<Name>, With<Flag>)>)>()
+        .err()
+        .unwrap();
+    assert_eq!(
+        err,
+        BorrowError::WriteReadConflict {
+            type_name: type_name::<Name>(),
+            kind: BorrowKind::Component,
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_clear_component() -> Result<(), BorrowError> {
+    use std::sync::{Arc, Mutex};
+
+    let mut ecs = Things::new();
+    ecs.clear_component::<Count>();
+
+    let removed = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&removed);
+    ecs.set_on_remove(move |_, count: &Count| log.lock().unwrap().push(count.0));
+
+    let a = ecs.create_entity((Count(1), Name("a")));
+    let b = ecs.create_entity((Count(2),));
+    let c = ecs.create_entity((Name("c"),));
+    ecs.add_component(a, Count(3));
+
+    ecs.clear_component::<Count>();
+    assert_eq!(*removed.lock().unwrap(), vec![2, 1, 3]);
+
+    assert!(ecs.is_alive(a) && ecs.is_alive(b) && ecs.is_alive(c));
+    assert!(!ecs.has_component::<Count>(a));
+    assert!(!ecs.has_component::<Count>(b));
+    assert_eq!(ecs.get::<Name>(a), Some(&Name("a")));
+    assert_eq!(ecs.count::<Read<Count>>(), 0);
+    assert_eq!(ecs.count::<Read<Name>>(), 2);
+    assert!(ecs.store::<Count>().is_some());
+
+    ecs.add_component(b, Count(4));
+    let rows: Vec<_> = ecs.query::<(Entity, Read<Count>)>()?.collect();
+    assert_eq!(rows, vec![(b, &Count(4))]);
+    Ok(())
+}
+
+#[test]
+fn test_drain() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    assert_eq!(ecs.drain::<Count>().count(), 0);
+
+    let a = ecs.create_entity((Count(1), Name("a")));
+    let b = ecs.create_entity((Count(2),));
+    ecs.add_component(b, Count(3));
+
+    let drained: Vec<_> = ecs.drain::<Count>().collect();
+    assert_eq!(drained, vec![(a, Count(1)), (b, Count(2)), (b, Count(3))]);
+    assert_eq!(ecs.count::<Read<Count>>(), 0);
+    assert_eq!(ecs.get::<Name>(a), Some(&Name("a")));
+    assert!(ecs.is_alive(b));
+
+    ecs.add_component(b, Count(4));
+    let rows: Vec<_> = ecs.query::<(Entity, Read<Count>)>()?.collect();
+    assert_eq!(rows, vec![(b, &Count(4))]);
+    Ok(())
+}
+
+#[test]
+fn test_try_get() {
+    let mut ecs = Things::new();
+    let a = ecs.create_entity((Count(1),));
+    let b = ecs.create_entity((Name("b"),));
+
+    assert_eq!(ecs.try_get::<Count>(a), Ok(Some(&Count(1))));
+    assert_eq!(ecs.try_get::<Count>(b), Ok(None));
+
+    ecs.despawn(a);
+    let err = ecs.try_get::<Count>(a).unwrap_err();
+    assert_eq!(err, EntityError::Dead(a));
+    assert_eq!(err.to_string(), format!("entity {:?} is not alive", a));
+
+    ecs.create_entity((Count(2),));
+    assert_eq!(ecs.try_get::<Count>(a), Err(EntityError::Dead(a)));
+}
+
+#[derive(Component, Debug, PartialEq)]
+struct Target(Entity);
+
+impl Relation for Target {
+    fn target(&self) -> Entity {
+        self.0
+    }
+
+    fn set_target(&mut self, target: Entity) {
+        self.0 = target;
+    }
+}
+
+#[test]
+fn test_relations() {
+    use std::sync::{Arc, Mutex};
+
+    let mut ecs = Things::new();
+    ecs.register_relation::<Target>();
+
+    let lost = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&lost);
+    ecs.set_on_remove(move |owner, target: &Target| log.lock().unwrap().push((owner, target.0)));
+
+    let a = ecs.create_entity((Count(1),));
+    let b = ecs.create_entity((Count(2),));
+    let c = ecs.create_entity((Target(a), Target(b), Name("c")));
+    let d = ecs.create_entity((Target(a),));
+
+    ecs.despawn(a);
+    assert_eq!(*lost.lock().unwrap(), vec![(c, a), (d, a)]);
+    assert_eq!(
+        ecs.get_all::<Target>(c).collect::<Vec<_>>(),
+        vec![&Target(b)]
+    );
+    assert!(!ecs.has_component::<Target>(d));
+    assert!(ecs.is_alive(d));
+
+    assert!(ecs.despawn_take(b).is_some());
+    assert!(!ecs.has_component::<Target>(c));
+    assert_eq!(ecs.get::<Name>(c), Some(&Name("c")));
+    assert!(ecs.entity(a).is_none());
+}
+
+#[test]
+fn test_relations_indexed() {
+    let mut ecs = Things::new();
+
+    let a = ecs.create_entity((Count(1),));
+    let b = ecs.create_entity((Count(2),));
+    let c = ecs.create_entity((Target(a),));
+    let d = ecs.create_entity((Target(b),));
+
+    // Components added before the type is registered are indexed as well.
+    ecs.register_relation::<Target>();
+
+    assert_eq!(ecs.replace_component(c, Target(b)), Some(Target(a)));
+    ecs.despawn(a);
+    assert_eq!(ecs.get::<Target>(c), Some(&Target(b)));
+
+    ecs.remove_component::<Target>(d);
+    ecs.add_component(d, Target(c));
+    ecs.despawn(b);
+    assert!(!ecs.has_component::<Target>(c));
+    assert_eq!(ecs.get::<Target>(d), Some(&Target(c)));
+
+    ecs.clear();
+    let e = ecs.create_entity((Count(3),));
+    let f = ecs.create_entity((Target(e),));
+    ecs.despawn(e);
+    assert!(!ecs.has_component::<Target>(f));
+}
+
+#[test]
+fn test_relations_retargeted() -> Result<(), BorrowError> {
+    let mut ecs = Things::new();
+    ecs.register_relation::<Target>();
+
+    let a = ecs.create_entity((Count(1),));
+    let b = ecs.create_entity((Count(2),));
+    let c = ecs.create_entity((Count(3),));
+    let d = ecs.create_entity((Target(a),));
+    let e = ecs.create_entity((Target(a),));
+
+    ecs.get_mut::<Target>(d).unwrap().0 = b;
+    ecs.despawn(a);
+    assert_eq!(ecs.get::<Target>(d), Some(&Target(b)));
+    assert!(!ecs.has_component::<Target>(e));
+
+    ecs.run(|targets: QueryIter<Write<Target>>| {
+        for target in targets {
+            target.0 = c;
+        }
+    })?;
+
+    ecs.despawn(b);
+    assert_eq!(ecs.get::<Target>(d), Some(&Target(c)));
+
+    ecs.despawn(c);
+    assert!(!ecs.has_component::<Target>(d));
+    Ok(())
+}
+
+#[test]
+fn test_relations_merged() {
+    let mut ecs = Things::new();
+    ecs.register_relation::<Target>();
+    let local = ecs.create_entity((Count(1),));
+
+    let mut chunk = Things::new();
+    let enemy = chunk.create_entity((Count(2),));
+    let turret = chunk.create_entity((Target(enemy),));
+    let gone = chunk.create_entity((Count(3),));
+    let stale = chunk.create_entity((Target(gone), Name("stale")));
+    chunk.despawn(gone);
+
+    // The handles of both worlds are allocated the same way.
+    assert_eq!(enemy, local);
+
+    let merged = ecs.merge(chunk);
+    let handle = |entity| merged.iter().find(|&&(_, e)| e == entity).unwrap().0;
+    let (enemy, turret, stale) = (handle(enemy), handle(turret), handle(stale));
+
+    assert_eq!(ecs.get::<Target>(turret), Some(&Target(enemy)));
+    assert!(!ecs.has_component::<Target>(stale));
+    assert_eq!(ecs.get::<Name>(stale), Some(&Name("stale")));
+
+    ecs.despawn(local);
+    assert_eq!(ecs.get::<Target>(turret), Some(&Target(enemy)));
+
+    ecs.despawn(enemy);
+    assert!(!ecs.has_component::<Target>(turret));
+}
+
+#[test]
+fn test_write_all() {
+    let mut ecs = Things::new();
+    ecs.write_all(|_: &mut Count| unreachable!());
+
+    let a = ecs.create_entity((Count(1), Name("a")));
+    ecs.create_entity((Name("b"),));
+    let c = ecs.create_entity((Count(3),));
+    ecs.add_component(c, Count(4));
+
+    let mut visited = 0;
+    ecs.write_all(|count: &mut Count| {
+        count.0 *= 10;
+        visited += 1;
+    });
+
+    assert_eq!(visited, 3);
+    assert_eq!(ecs.get::<Count>(a), Some(&Count(10)));
+    assert_eq!(
+        ecs.get_all::<Count>(c).collect::<Vec<_>>(),
+        vec![&Count(30), &Count(40)]
+    );
 }
@@ -0,0 +1,185 @@
+//! Derive macros for the [Things] Entity–Component–System library.
+//!
+//! [Things]: https://docs.rs/things
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Expr, Index, Lit, Meta,
+          NestedMeta, Path, Result, Type};
+
+/// Derives the `Component` trait.
+///
+/// Components are kept in a `DefaultStore`, or in a `TagStore` for zero-sized
+/// structs, unless another store is selected using the `component` attribute.
+/// The store is given as the path to a type that takes the component type as
+/// its only generic parameter:
+///
+/// ```ignore
+/// #[derive(Component, Debug)]
+/// #[component(storage = "things::SparseStore")]
+/// struct Selected;
+/// ```
+#[proc_macro_derive(Component, attributes(component))]
+pub fn derive_component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match storage(&input) {
+        Ok(storage) => {
+            let name = &input.ident;
+            let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+            TokenStream::from(quote! {
+                impl #impl_generics ::things::Component for #name #ty_generics #where_clause {
+                    type Storage = #storage;
+                }
+            })
+        },
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+/// Derives the `Bundle` trait.
+///
+/// Every field of the struct is stored as a separate component of the entity,
+/// so all field types have to implement `Component`:
+///
+/// ```ignore
+/// #[derive(Bundle)]
+/// struct PlayerBundle {
+///     position: Position,
+///     health: Health,
+/// }
+/// ```
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match bundle(&input) {
+        Ok(tokens) => tokens,
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn bundle(input: &DeriveInput) -> Result<TokenStream> {
+    let fields = match input.data {
+        Data::Struct(ref data) => &data.fields,
+        _ => return Err(Error::new_spanned(input, "only structs can be bundles")),
+    };
+
+    // A component collection holds at most twelve components.
+    if fields.is_empty() || fields.len() > 12 {
+        return Err(Error::new_spanned(
+            fields,
+            "expected between 1 and 12 fields",
+        ));
+    }
+
+    let types = fields.iter().map(|field| &field.ty);
+    let members = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match field.ident {
+            Some(ref ident) => quote!(#ident),
+            None => {
+                let index = Index::from(index);
+                quote!(#index)
+            },
+        });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(TokenStream::from(quote! {
+        impl #impl_generics ::things::Bundle for #name #ty_generics #where_clause {
+            type Components = (#(#types,)*);
+
+            fn into_components(self) -> Self::Components {
+                (#(self.#members,)*)
+            }
+        }
+    }))
+}
+
+/// Returns the store configured through the `component` attribute, or the
+/// default store if no store is configured.
+fn storage(input: &DeriveInput) -> Result<Type> {
+    let mut storage: Option<Path> = None;
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("component"))
+    {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected `component(...)`")),
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref pair)) if pair.path.is_ident("storage") => {
+                    storage = match pair.lit {
+                        Lit::Str(ref path) => Some(path.parse()?),
+                        ref lit => return Err(Error::new_spanned(lit, "expected a string")),
+                    };
+                },
+                nested => {
+                    return Err(Error::new_spanned(nested, "expected `storage = \"...\"`"));
+                },
+            }
+        }
+    }
+
+    match storage {
+        Some(storage) => Ok(parse_quote!(#storage<Self>)),
+        None => Ok(default_storage(input)),
+    }
+}
+
+/// Returns the default store, which is a `TagStore` for zero-sized structs, and
+/// a `DefaultStore` otherwise.
+///
+/// The size of a type is only known after expansion, so for types without
+/// generic parameters the store is selected by the generated code. The size of
+/// a generic type can't be used to select a type, so those are only kept in a
+/// `TagStore` if all of their fields are known to be zero-sized.
+fn default_storage(input: &DeriveInput) -> Type {
+    let fields = match input.data {
+        Data::Struct(ref data) => &data.fields,
+        _ => return parse_quote!(::things::DefaultStore<Self>),
+    };
+
+    if fields.is_empty() || fields.iter().all(|field| is_zero_sized(&field.ty)) {
+        return parse_quote!(::things::TagStore<Self>);
+    }
+
+    if !input.generics.params.is_empty() {
+        return parse_quote!(::things::DefaultStore<Self>);
+    }
+
+    let name = &input.ident;
+    parse_quote! {
+        <::things::ZeroSized<{ ::core::mem::size_of::<#name>() == 0 }>
+            as ::things::SelectStorage<Self>>::Storage
+    }
+}
+
+/// Returns `true` if the type is known to be zero-sized without expanding it,
+/// such as `()`, `PhantomData<T>` or `[T; 0]`.
+fn is_zero_sized(ty: &Type) -> bool {
+    match ty {
+        Type::Tuple(tuple) => tuple.elems.iter().all(is_zero_sized),
+        Type::Array(array) => match array.len {
+            Expr::Lit(ref lit) => {
+                matches!(lit.lit, Lit::Int(ref int) if int.base10_digits() == "0")
+            },
+            _ => false,
+        },
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| {
+            segment.ident == "PhantomData" || segment.ident == "PhantomPinned"
+        }),
+        _ => false,
+    }
+}